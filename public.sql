@@ -2,7 +2,29 @@
 This file makes public the Postgres schema for select tables, demononstrating how immutability is guaranteed.
 */
 
-CREATE TABLE IF NOT EXISTS authors_immut (
+-- pg_trgm provides word_similarity(), used when autocomplete falls back to fuzzy matching (see Xtchr::autocomplete_authors)
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+
+CREATE OR REPLACE FUNCTION prefix_tsquery(config REGCONFIG, input VARCHAR) RETURNS TSQUERY AS $$
+	/*Build a prefix-matching tsquery from raw user input, i.e. 'AT&T' becomes 'at:* & t:*'.
+	to_tsquery() throws on operators such as '&', '|', '!', ':', or unbalanced parentheses in its input,
+	so everything other than letters and digits is treated as a word separator. All autocomplete and fulltext queries use this */
+	SELECT CASE WHEN words = '' THEN ''::TSQUERY
+		ELSE TO_TSQUERY(config, REGEXP_REPLACE(words, '\s+', ':* & ', 'g') || ':*') END
+	FROM (SELECT TRIM(REGEXP_REPLACE(input, '[^[:alnum:]]+', ' ', 'g')) AS words) w
+$$ LANGUAGE SQL IMMUTABLE;
+
+
+CREATE OR REPLACE FUNCTION image_src(src TEXT, prefix VARCHAR, bytes BYTEA) RETURNS TEXT AS $$
+	/*The base64 image source for an image stored either as text (src) or as its decoded bytes with the data URI prefix, 
+	i.e. 'data:image/png;base64,' (see the "compress-images" feature). Both forms give the same source, which is what is hashed.
+	ENCODE() wraps base64 lines at 76 characters, so the newlines are removed */
+	SELECT COALESCE(src, prefix || TRANSLATE(ENCODE(bytes, 'base64'), E'\n', ''))
+$$ LANGUAGE SQL IMMUTABLE;
+
+
+CREATE TABLE IF NOT EXISTS authors (
 	prior_id INTEGER UNIQUE,
 	auth_id INTEGER NOT NULL PRIMARY KEY,
 	name VARCHAR NOT NULL UNIQUE,
@@ -41,27 +63,58 @@ INSERT INTO authors (auth_id, name, prior_sha256, write_timestamp, new_sha256) V
 	'hex')
 );
 
-CREATE TABLE IF NOT EXISTS artile_titles_immut (
+CREATE TABLE IF NOT EXISTS author_names (
+	/*Authors are never rewritten: a new name is appended here, in one chain across all authors as for every other table.
+	An author's current name is that of their latest row here, or their name in authors if they have never been renamed 
+	(see Xtchr::rename_author and the author_current view) */
+	prior_id INTEGER UNIQUE,
+	name_id INTEGER NOT NULL PRIMARY KEY,
+	auth_id INTEGER NOT NULL,						-- the author being renamed
+	name VARCHAR NOT NULL,
+	prior_sha256 CHAR(64) NOT NULL, 				-- included for checking integrity
+	write_timestamp TIMESTAMPTZ NOT NULL,     
+	new_sha256 CHAR(64) NOT NULL,
+	UNIQUE(name_id, new_sha256),					-- this allows the no_delete constraint below 
+	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', name )) STORED,
+CONSTRAINT aname_prior CHECK ( (name_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = name_id - 1)) ),
+CONSTRAINT aname_author FOREIGN KEY (auth_id) REFERENCES authors (auth_id),
+CONSTRAINT aname_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES author_names (name_id, new_sha256),
+CONSTRAINT aname_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
+CONSTRAINT aname_verify_sha256 CHECK (
+	ENCODE(
+		SHA256(
+			CONCAT(
+				'name_id=', name_id::VARCHAR,
+				' auth_id=', auth_id::VARCHAR,
+				' name=', name,
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			)::BYTEA
+		),
+	'hex') = new_sha256)
+);
+
+CREATE TABLE IF NOT EXISTS titles_immut (
 	-- this table shows that the title of articles and the timestamp on which they were posted is immutable
 	prior_id INTEGER UNIQUE,
-	art_id INTEGER NOT NULL PRIMARY KEY,		-- the unique id for this article
+	a_id_draft CHAR(21) NOT NULL,				-- the nanoID this article had while it was being drafted
+	a_id_immut INTEGER NOT NULL PRIMARY KEY,	-- the unique id for this article
 	auth_id INTEGER NOT NULL,					-- indicate's the articles authof
 	title VARCHAR NOT NULL UNIQUE,				
 	prior_sha256 CHAR(64) NOT NULL, 			-- included for checking integrity
 	write_timestamp TIMESTAMPTZ NOT NULL,     
 	new_sha256 CHAR(64) NOT NULL,				-- the new_sha256 is what must be verified
-	UNIQUE(art_id, new_sha256),					-- this allows the no_delete constraint below 
+	UNIQUE(a_id_immut, new_sha256),				-- this allows the no_delete constraint below 
 	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', title )) STORED,	-- for autocompletion indexing
 CONSTRAINT art_auth FOREIGN KEY (auth_id) REFERENCES authors(auth_id),
-CONSTRAINT art_image FOREIGN KEY (image_file) REFERENCES image_files(image_file) ON UPDATE CASCADE,
-CONSTRAINT art_prior CHECK ( (art_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = art_id - 1)) ),
-CONSTRAINT art_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES articles (art_id, new_sha256),
+CONSTRAINT art_prior CHECK ( (a_id_immut = 0) OR ((prior_id IS NOT NULL) AND (prior_id = a_id_immut - 1)) ),
+CONSTRAINT art_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES titles_immut (a_id_immut, new_sha256),
 CONSTRAINT art_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
 CONSTRAINT art_verify_sha256 CHECK (
 	ENCODE(
 		SHA256(
 			CONCAT(
-				'art_id=', art_id::VARCHAR,
+				'a_id_immut=', a_id_immut::VARCHAR,
 				' auth_id=', auth_id::VARCHAR,
 				' title=', title,
 				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
@@ -86,7 +139,7 @@ CREATE TABLE IF NOT EXISTS article_mut (
 	are kept separately in this "mutable" table so they don't trigger an attempted rewrite if changed*/
 	art_id INTEGER NOT NULL PRIMARY KEY,
 	image_file VARCHAR,			-- an image to use as the 'cover' for an article
-CONSTRAINT amuta FOREIGN KEY (art_id) REFERENCES articles(art_id)
+CONSTRAINT amuta FOREIGN KEY (art_id) REFERENCES titles_immut(a_id_immut)
 );
 
 
@@ -95,13 +148,14 @@ CREATE TABLE IF NOT EXISTS article_para (
 	apara_id INTEGER NOT NULL UNIQUE,
 	art_id INTEGER NOT NULL,
 	md VARCHAR NOT NULL,		-- Markdown for this paragarph as provided by the author
+	plain VARCHAR NOT NULL,		-- md with the Markdown syntax stripped (see xrows::strip_markdown), used for search and snippets
 	prior_sha256 CHAR(64) NOT NULL, -- included for checking integrity
 	write_timestamp TIMESTAMPTZ NOT NULL,     
 	new_sha256 CHAR(64) NOT NULL,
 	PRIMARY KEY (art_id, apara_id),
 	UNIQUE(apara_id, new_sha256), -- this allows the no_delete constraint below 
-	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', md)) STORED,
-CONSTRAINT apapa_art FOREIGN KEY (art_id) REFERENCES articles(art_id),
+	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', plain)) STORED,
+CONSTRAINT apapa_art FOREIGN KEY (art_id) REFERENCES titles_immut(a_id_immut),
 CONSTRAINT apara_prior CHECK ( (apara_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = apara_id - 1)) ),
 CONSTRAINT apara_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES article_para (apara_id, new_sha256),
 CONSTRAINT apara_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
@@ -119,7 +173,15 @@ CONSTRAINT apara_verify_sha256 CHECK (
 	'hex') = new_sha256)
 );
 CREATE INDEX article_fulltext ON article_para USING GIN(ts);
-INSERT INTO article_para (apara_id, art_id, md, prior_sha256, write_timestamp, new_sha256) 
+-- Xtchr::search_paragraphs with a TsConfig other than English matches to_tsvector('<config>', plain), which needs an index per configuration
+CREATE INDEX IF NOT EXISTS article_fulltext_simple ON article_para USING GIN(to_tsvector('simple', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_spanish ON article_para USING GIN(to_tsvector('spanish', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_german ON article_para USING GIN(to_tsvector('german', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_french ON article_para USING GIN(to_tsvector('french', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_italian ON article_para USING GIN(to_tsvector('italian', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_portuguese ON article_para USING GIN(to_tsvector('portuguese', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_dutch ON article_para USING GIN(to_tsvector('dutch', plain));
+CREATE INDEX IF NOT EXISTS article_fulltext_russian ON article_para USING GIN(to_tsvector('russian', plain));
 
 
 
@@ -137,12 +199,14 @@ CREATE TABLE IF NOT EXISTS images_mut (
 
 
 
-CREATE TABLE IF NOT EXISTS images (
+CREATE TABLE IF NOT EXISTS images_immut (
 	/*This table stores images encoded as base64. 
 	Many of them may be screenshots: the url field captures the source in those cases*/
 	prior_id INTEGER,							-- id of the prior image
 	img_id INTEGER NOT NULL PRIMARY KEY,		-- id for this image
-	src_full TEXT NOT NULL,						-- image source encoded as base64: "<img src="data:image/png;base64, iVBORw0KGgoA..." etc
+	src_full TEXT,								-- image source encoded as base64: "<img src="data:image/png;base64, iVBORw0KGgoA..." etc
+	full_prefix VARCHAR,						-- or, the data URI prefix, i.e. "data:image/png;base64," 
+	full_bytes BYTEA,							-- and the decoded image, which Postgres compresses (TOAST) if it can: read both with image_src()
 	src_thmb TEXT NOT NULL,						-- thumbnail source encoded as base64: "<img src="data:image/png;base64, iVBORw0KGgoA..." etc
 	alt VARCHAR NOT NULL,						-- caption / alt text for accessability
 	url VARCHAR,								-- url for a screenshot or image download 
@@ -150,29 +214,142 @@ CREATE TABLE IF NOT EXISTS images (
 	prior_sha256 CHAR(64) NOT NULL, 			-- included for checking integrity
 	write_timestamp TIMESTAMPTZ NOT NULL,     	-- timestamp when this row was written 
 	new_sha256 CHAR(64) NOT NULL,				-- new sha256 based on the below constraint
+	hash_v SMALLINT NOT NULL,					-- which ImmutableImage::state_string this row was hashed with: see img_verify_sha256 below
 	UNIQUE(img_id, new_sha256),				-- this allows the below constraint 
-	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', alt || ' ' || archive )) STORED,
-	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', alt || ' ' || archive )) STORED,
+	-- archive is usually NULL, which would make the whole vector NULL (and the image unsearchable) without COALESCE
+	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', alt || ' ' || COALESCE(archive, '') )) STORED,
+	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', alt || ' ' || COALESCE(archive, '') )) STORED,
 	CONSTRAINT img_prior CHECK ( (img_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = img_id - 1)) ),
-	CONSTRAINT img_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES images (img_id, new_sha256),
-	CONSTRAINT img_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
-	CONSTRAINT img_verify_sha256 CHECK (
-		ENCODE(
-			SHA256(
-				CONCAT(
-					'img_id=', img_id::VARCHAR,
-					' src_full=', src_full,
-					' src_thmb=', src_thmb,
-					' alt=', alt,
-					' url=', url,
-					' archive=', archive,
-					' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
-					' prior_sha256=', prior_sha256
-				)::BYTEA
-			),
+	CONSTRAINT img_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES images_immut (img_id, new_sha256),
+	CONSTRAINT img_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1)
+);
+-- Databases created before full_bytes kept every full image as base64 in src_full, which stays valid: those rows keep it,
+-- and image_src() reads either form. The columns must exist before img_verify_sha256 below, which reads them.
+-- phash is no longer a column here: see image_phashes below 
+ALTER TABLE images_immut ADD COLUMN IF NOT EXISTS full_prefix VARCHAR;
+ALTER TABLE images_immut ADD COLUMN IF NOT EXISTS full_bytes BYTEA;
+ALTER TABLE images_immut ALTER COLUMN src_full DROP NOT NULL;
+ALTER TABLE images_immut DROP CONSTRAINT IF EXISTS img_full_src;
+ALTER TABLE images_immut ADD CONSTRAINT img_full_src CHECK ( (num_nonnulls(src_full, full_bytes) = 1) AND ((full_prefix IS NULL) = (full_bytes IS NULL)) );
+-- Databases created before hash_v hashed every image with the full image inline (hash_v = 1): existing rows are marked as such. 
+-- img_verify_sha256 is (re)created here rather than in CREATE TABLE so that such databases get the form which verifies both versions 
+ALTER TABLE images_immut ADD COLUMN IF NOT EXISTS hash_v SMALLINT NOT NULL DEFAULT 1;
+ALTER TABLE images_immut ALTER COLUMN hash_v DROP DEFAULT;
+ALTER TABLE images_immut DROP CONSTRAINT IF EXISTS img_verify_sha256;
+ALTER TABLE images_immut ADD CONSTRAINT img_verify_sha256 CHECK (
+	(hash_v = 1 AND ENCODE(
+		SHA256(
+			CONCAT(
+				'img_id=', img_id::VARCHAR,
+				' src_full=', image_src(src_full, full_prefix, full_bytes),
+				' src_thmb=', src_thmb,
+				' alt=', alt,
+				' url=', url,
+				' archive=', archive,
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			)::BYTEA
+		),
+	'hex') = new_sha256)
+	OR (hash_v = 2 AND ENCODE(
+		SHA256(
+			CONCAT(
+				'img_id=', img_id::VARCHAR,
+				-- the full image is hashed on its own, keeping the string hashed short (see ImmutableImage::state_string)
+				' src_full_sha256=', ENCODE(SHA256(image_src(src_full, full_prefix, full_bytes)::BYTEA), 'hex'),
+				' src_thmb=', src_thmb,
+				' alt=', alt,
+				' url=', url,
+				' archive=', archive,
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			)::BYTEA
+		),
+	'hex') = new_sha256)
+);
+CREATE INDEX image_ts ON images_immut USING GIN(ts);
+CREATE INDEX image_archive ON images_immut (archive);		-- see Xtchr::images_by_archive
+
+
+CREATE TABLE IF NOT EXISTS image_phashes (
+	/*The perceptual hash of the full image of each immutable image (see xrows::image_phash), to find similar images (see Xtchr::similar_images).
+	It is derived from the image, so it is not hashed, and it is kept out of images_immut as a row there cannot be updated once written 
+	(see img_no_rewrite_later): an image written without one is given one later by Xtchr::backfill_phashes */
+	img_id INTEGER NOT NULL PRIMARY KEY,
+	phash BIGINT NOT NULL,
+	CONSTRAINT phash_img FOREIGN KEY (img_id) REFERENCES images_immut (img_id)
+);
+-- phash was a column of images_immut: being derived, it is recomputed into image_phashes by Xtchr::backfill_phashes 
+ALTER TABLE images_immut DROP COLUMN IF EXISTS phash;
+
+
+CREATE TABLE IF NOT EXISTS pages_immut (
+	/*Each page of an article has paragraphs of plaintext and exactly one source (see xrows::PageSrc):
+	an image_file for the author's own pages, an img_id for a screenshot, or the id of a prior xtchd article.
+	A page is never edited: a revision (i.e. fixing a typo) is a new page which supersedes the prior revision of the same article,
+	so every revision remains in the chain and verifiable (see Xtchr::revise_article_page) */
+	prior_id INTEGER UNIQUE,
+	p_id_draft CHAR(21) NOT NULL,				-- the nanoID this page had while it was being drafted
+	p_id_immut INTEGER NOT NULL PRIMARY KEY,	-- the globally unique id for this page
+	a_id_immut INTEGER NOT NULL,				-- the article this page belongs to
+	paragraphs VARCHAR[] NOT NULL,				-- paragraphs of plaintext
+	img_id INTEGER,								-- source: a webpage, TwitterX, or YouTube screenshot
+	image_file VARCHAR,							-- source: the author's own page 
+	refs_a_id_immut INTEGER,					-- source: a prior xtchd article
+	supersedes INTEGER UNIQUE,					-- the page this revises, or NULL for the first revision of a page
+	prior_sha256 CHAR(64) NOT NULL, 			-- included for checking integrity
+	write_timestamp TIMESTAMPTZ NOT NULL,     
+	new_sha256 CHAR(64) NOT NULL,
+	hash_v SMALLINT NOT NULL,					-- which ArticlePage::state_string this page was hashed with: see page_verify_sha256 below
+	UNIQUE(p_id_immut, new_sha256),				-- this allows the no_delete constraint below 
+	UNIQUE(p_id_immut, a_id_immut),				-- this allows the page_supersedes constraint below 
+CONSTRAINT page_art FOREIGN KEY (a_id_immut) REFERENCES titles_immut(a_id_immut),
+CONSTRAINT page_supersedes FOREIGN KEY (supersedes, a_id_immut) REFERENCES pages_immut (p_id_immut, a_id_immut),
+CONSTRAINT page_img FOREIGN KEY (img_id) REFERENCES images_immut(img_id),
+CONSTRAINT page_refs FOREIGN KEY (refs_a_id_immut) REFERENCES titles_immut(a_id_immut),
+CONSTRAINT page_one_src CHECK ( num_nonnulls(img_id, image_file, refs_a_id_immut) = 1 ),
+CONSTRAINT page_prior CHECK ( (p_id_immut = 0) OR ((prior_id IS NOT NULL) AND (prior_id = p_id_immut - 1)) ),
+CONSTRAINT page_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES pages_immut (p_id_immut, new_sha256),
+CONSTRAINT page_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1)
+);
+-- Databases created before hash_v hashed every page with its paragraphs joined by ' | ' (hash_v = 1): existing rows are marked as such. 
+-- page_verify_sha256 is (re)created here rather than in CREATE TABLE so that such databases get the form which verifies both versions 
+ALTER TABLE pages_immut ADD COLUMN IF NOT EXISTS hash_v SMALLINT NOT NULL DEFAULT 1;
+ALTER TABLE pages_immut ALTER COLUMN hash_v DROP DEFAULT;
+ALTER TABLE pages_immut DROP CONSTRAINT IF EXISTS page_verify_sha256;
+ALTER TABLE pages_immut ADD CONSTRAINT page_verify_sha256 CHECK (
+	(hash_v = 1 AND ENCODE(
+		SHA256(
+			CONCAT(
+				'a_id_immut=', a_id_immut::VARCHAR,
+				' p_id_immut=', p_id_immut::VARCHAR,
+				' paragraphs=', ARRAY_TO_STRING(paragraphs, ' | '),
+				' img_id=', img_id::VARCHAR,
+				' image_file=', image_file,
+				' refs_a_id_immut=', refs_a_id_immut::VARCHAR,
+				' supersedes=' || supersedes::VARCHAR,		-- NULL (so omitted) unless the page is a revision
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			)::BYTEA
+		),
+	'hex') = new_sha256)
+	OR (hash_v = 2 AND ENCODE(
+		SHA256(
+			-- CONVERT_TO rather than ::BYTEA, which would parse the backslashes JSON escapes '"', '\\' and newlines with
+			CONVERT_TO(CONCAT(
+				'a_id_immut=', a_id_immut::VARCHAR,
+				' p_id_immut=', p_id_immut::VARCHAR,
+				' paragraphs=', ARRAY_TO_JSON(paragraphs)::VARCHAR,	-- a JSON array, so paragraphs cannot run together
+				' img_id=', img_id::VARCHAR,
+				' image_file=', image_file,
+				' refs_a_id_immut=', refs_a_id_immut::VARCHAR,
+				' supersedes=' || supersedes::VARCHAR,		-- NULL (so omitted) unless the page is a revision
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			), 'UTF8')
+		),
 	'hex') = new_sha256)
 );
-CREATE INDEX image_ts ON images USING GIN(ts);
 
 
 
@@ -269,9 +446,9 @@ CREATE TABLE IF NOT EXISTS article_ref_article (
 	new_sha256 CHAR(64) NOT NULL,
 	UNIQUE(aref_id, new_sha256), 					-- this allows the no_delete constraint below 
 	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', comment)) STORED,
-CONSTRAINT arafa FOREIGN KEY (from_art) REFERENCES articles(art_id),
+CONSTRAINT arafa FOREIGN KEY (from_art) REFERENCES titles_immut(a_id_immut),
 CONSTRAINT arafp FOREIGN KEY (from_art, from_para) REFERENCES article_para (art_id, apara_id),
-CONSTRAINT arata FOREIGN KEY (refs_art) REFERENCES articles(art_id),
+CONSTRAINT arata FOREIGN KEY (refs_art) REFERENCES titles_immut(a_id_immut),
 CONSTRAINT aratp FOREIGN KEY (refs_art, refs_para) REFERENCES article_para (art_id, apara_id),
 CONSTRAINT ara_prior CHECK ( (aref_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = aref_id - 1)) ),
 CONSTRAINT ara_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES article_ref_article (aref_id, new_sha256),
@@ -312,7 +489,7 @@ CREATE TABLE IF NOT EXISTS article_ref_video (
 	UNIQUE(vref_id, new_sha256), 					-- this allows the no_delete constraint below 
 	UNIQUE(vref_id, vid_pk),						-- allows FOREIGN KEY contraits tying vid_pk to the references
 	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', comment)) STORED,
-CONSTRAINT arrefvar FOREIGN KEY (art_id) REFERENCES articles(art_id),
+CONSTRAINT arrefvar FOREIGN KEY (art_id) REFERENCES titles_immut(a_id_immut),
 CONSTRAINT arrefvap FOREIGN KEY (art_id, apara_id) REFERENCES article_para (art_id, apara_id),
 CONSTRAINT arrefytt FOREIGN KEY (vid_pk) REFERENCES youtube_videos(vid_pk),
 CONSTRAINT arrefv_prior CHECK ( (vref_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = vref_id - 1)) ),
@@ -353,9 +530,9 @@ CREATE TABLE IF NOT EXISTS article_ref_image (
 	new_sha256 CHAR(64) NOT NULL,
 	UNIQUE(iref_id, new_sha256), 					-- this allows the imref_no_delete constraint below 
 	ts tsvector GENERATED ALWAYS AS ( to_tsvector('english', comment)) STORED,
-CONSTRAINT imrefar FOREIGN KEY (art_id) REFERENCES articles(art_id),
+CONSTRAINT imrefar FOREIGN KEY (art_id) REFERENCES titles_immut(a_id_immut),
 CONSTRAINT imrefpa FOREIGN KEY (art_id, apara_id) REFERENCES article_para (art_id, apara_id),
-CONSTRAINT imrefim FOREIGN KEY (img_id) REFERENCES images (img_id),
+CONSTRAINT imrefim FOREIGN KEY (img_id) REFERENCES images_immut (img_id),
 CONSTRAINT imref_prior CHECK ( (iref_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = iref_id - 1)) ),
 CONSTRAINT imref_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES article_ref_image (iref_id, new_sha256),
 CONSTRAINT imref_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
@@ -397,7 +574,7 @@ CREATE TABLE IF NOT EXISTS nlp_topics (
 	tkey VARCHAR NOT NULL PRIMARY KEY,	-- primary key, string
 	name VARCHAR NOT NULL,				-- the name, i.e. the topic
 	count SMALLINT NOT NULL DEFAULT 1,	-- the frequency with which this topic has been identified 
-	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', name )) STORED,
+	ac tsvector GENERATED ALWAYS AS ( to_tsvector('simple', name )) STORED
 --CONSTRAINT nlpentpos FOREIGN KEY (pos) REFERENCES nlp_topic_pos(pos)
 );
 
@@ -413,3 +590,117 @@ CONSTRAINT aparavid FOREIGN KEY (art_id, apara_id) REFERENCES article_para (art_
 );
 CREATE INDEX byart ON apara_ment_topic(art_id, apara_id);
 
+CREATE TABLE IF NOT EXISTS vid_ment_topic (
+	/*Each time a youtube video mentions a topic, 
+	add a row to this table*/
+	tkey VARCHAR NOT NULL,
+	vid_id INTEGER NOT NULL,
+	PRIMARY KEY (tkey, vid_id),
+CONSTRAINT vidtop FOREIGN KEY (tkey) REFERENCES nlp_topics(tkey),
+CONSTRAINT vidvid FOREIGN KEY (vid_id) REFERENCES youtube_videos(vid_id)
+);
+
+
+
+CREATE TABLE IF NOT EXISTS tombstones (
+	/*The hash chains are append-only: physically deleting a row would break verification for every row after it.
+	Instead, content that must be removed (i.e. a legal takedown) is marked as redacted by appending a tombstone here.
+	The target row (and its hash) stay in place, but is read back as a redacted placeholder (see xrows::Tombstone) */
+	prior_id INTEGER UNIQUE,
+	tomb_id INTEGER NOT NULL PRIMARY KEY,
+	target_table VARCHAR NOT NULL,				-- the table with hash chain integrity containing the redacted row
+	target_id INTEGER NOT NULL,					-- the id of the redacted row within target_table
+	reason VARCHAR NOT NULL,					-- why the row was redacted 
+	prior_sha256 CHAR(64) NOT NULL, 			-- included for checking integrity
+	write_timestamp TIMESTAMPTZ NOT NULL,     
+	new_sha256 CHAR(64) NOT NULL,
+	UNIQUE(tomb_id, new_sha256),				-- this allows the no_delete constraint below 
+	UNIQUE(target_table, target_id),			-- a row can only be redacted once
+CONSTRAINT tomb_prior CHECK ( (tomb_id = 0) OR ((prior_id IS NOT NULL) AND (prior_id = tomb_id - 1)) ),
+CONSTRAINT tomb_no_delete FOREIGN KEY (prior_id, prior_sha256) REFERENCES tombstones (tomb_id, new_sha256),
+CONSTRAINT tomb_no_rewrite_later CHECK (EXTRACT(EPOCH FROM (CURRENT_TIMESTAMP - write_timestamp)) <= 1),
+CONSTRAINT tomb_verify_sha256 CHECK (
+	ENCODE(
+		SHA256(
+			CONCAT(
+				'tomb_id=', tomb_id::VARCHAR,
+				' target_table=', target_table,
+				' target_id=', target_id::VARCHAR,
+				' reason=', reason,
+				' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'),
+				' prior_sha256=', prior_sha256
+			)::BYTEA
+		),
+	'hex') = new_sha256)
+);
+
+
+
+CREATE TABLE IF NOT EXISTS write_idempotency (
+	/*When a client supplies an idempotency key with a write, the key is recorded here along with the id
+	of the row it created. A retried write with the same key returns that row instead of appending a duplicate */
+	idem_key VARCHAR NOT NULL PRIMARY KEY,			-- the key supplied by the client 
+	table_name VARCHAR NOT NULL,					-- the table the original write went to 
+	row_id INTEGER NOT NULL,						-- the id of the row created by the original write 
+	write_timestamp TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+
+CREATE TABLE IF NOT EXISTS author_popularity (
+	/*A cached count of how often each author's articles are cited by pages of other articles, 
+	used to order autocomplete by popularity (see AutoCompOrder). This is derived data and so is mutable, 
+	rebuilt by Xtchr::refresh_author_popularity(): authors with no row here have a popularity of 0 */
+	auth_id INTEGER NOT NULL PRIMARY KEY REFERENCES authors (auth_id),
+	refs BIGINT NOT NULL,
+	refreshed TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+
+CREATE TABLE IF NOT EXISTS chain_signatures (
+	/*Ed25519 signatures over the new_sha256 of the head of a table with hash chain integrity, anchoring the chain 
+	so external parties can verify the server vouched for that head (see Xtchr::sign_head). 
+	The message signed is the new_sha256 as stored, i.e. 64 hex characters. Each row of a table may be signed by many keys */
+	table_name VARCHAR NOT NULL,					-- the table signed, i.e. 'authors'
+	head_id INTEGER NOT NULL,						-- the id of the row that was the head when signed 
+	signature BYTEA NOT NULL,						-- the 64-byte Ed25519 signature
+	pubkey BYTEA NOT NULL,							-- the 32-byte Ed25519 public key which verifies it
+	signed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (table_name, head_id, pubkey)
+);
+
+
+CREATE TABLE IF NOT EXISTS tsa_tokens (
+	/*RFC 3161 timestamp tokens over the new_sha256 of the head of a table with hash chain integrity, anchoring the chain 
+	to trusted external time (see Xtchr::timestamp_head). The hash submitted is the new_sha256 decoded from hex, 
+	so the token verifies with i.e. openssl ts -verify -digest <new_sha256>. A row may be timestamped many times */
+	table_name VARCHAR NOT NULL,					-- the table timestamped, i.e. 'authors'
+	head_id INTEGER NOT NULL,						-- the id of the row that was the head when timestamped
+	tsa_url VARCHAR NOT NULL,						-- the timestamp authority which granted the token
+	token BYTEA NOT NULL,							-- the DER TimeStampToken 
+	stamped_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (table_name, head_id, tsa_url, stamped_at)
+);
+
+
+-- index each new_sha256 so a hash (i.e. from an external attestation) can be looked up directly: see Xtchr::find_by_sha256()
+CREATE INDEX IF NOT EXISTS authors_new_sha256 ON authors (new_sha256);
+CREATE INDEX IF NOT EXISTS authors_name_trgm ON authors USING GIN (name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS author_names_new_sha256 ON author_names (new_sha256);
+-- the latest rename of each author is looked up for their current name: see the author_current view 
+CREATE INDEX IF NOT EXISTS author_names_auth_id ON author_names (auth_id, name_id);
+CREATE INDEX IF NOT EXISTS titles_immut_new_sha256 ON titles_immut (new_sha256);
+CREATE INDEX IF NOT EXISTS pages_immut_new_sha256 ON pages_immut (new_sha256);
+CREATE INDEX IF NOT EXISTS article_para_new_sha256 ON article_para (new_sha256);
+CREATE INDEX IF NOT EXISTS youtube_channels_new_sha256 ON youtube_channels (new_sha256);
+CREATE INDEX IF NOT EXISTS youtube_videos_new_sha256 ON youtube_videos (new_sha256);
+CREATE INDEX IF NOT EXISTS images_immut_new_sha256 ON images_immut (new_sha256);
+CREATE INDEX IF NOT EXISTS tombstones_new_sha256 ON tombstones (new_sha256);
+
+
+CREATE TYPE page_src AS (
+	/*The source for one page of an article: exactly one of these is set (see xrows::PageSrc).
+	Pages are read as ROW(img_id, image_file, refs_a_id_immut)::page_src so reading the source is defined in one place */
+	img_id INTEGER,					-- for webpage, TwitterX, and YouTube screenshots
+	image_file VARCHAR,				-- for the author's own pages 
+	refs_a_id_immut INTEGER			-- for pages citing a prior xtchd article
+);
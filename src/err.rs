@@ -0,0 +1,154 @@
+//! err.rs contains the XtchdError, returned by Xtchr methods that can fail for reasons other than
+//! a database error: i.e. content that fails validation or content read back from Postgres that fails hash verification. 
+
+use std::fmt;
+use pachydurable::err::PachyDarn;
+use tokio_postgres::error::SqlState;
+use crate::{integrity::IntegrityError, xrows::XrowError};
+
+
+/// Non-exhaustive: variants are added with new features (i.e. Tsa with the "tsa" feature), 
+/// so a match on XtchdError needs a wildcard arm whichever features are enabled 
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum XtchdError {
+    /// An error from Postgres or the connection pool
+    Pachy(PachyDarn),
+    /// Content read back from Postgres does not hash to its stored new_sha256
+    Integrity(IntegrityError),
+    /// Content failed validation before being written 
+    Xrow(XrowError),
+    /// A method was called with an argument outside its allowed range 
+    InvalidArgument(String),
+    /// Writing an export failed 
+    Io(std::io::Error),
+    /// A statement was cancelled: usually for running longer than the statement_timeout (see PoolConfig), 
+    /// but Postgres reports a statement cancelled by pg_cancel_backend() the same way.
+    /// Only methods returning XtchdError report this: methods which still return PachyDarn report a cancelled statement as XtchdError::Pachy 
+    Timeout,
+    /// The row requested, i.e. by id, does not exist 
+    NotFound(String),
+    /// Another writer took the same position in a chain first: a unique violation of a chain table's id or prior_id 
+    /// (see xtchr::is_chain_position), i.e. by a writer not holding the table's lock. Nothing was written, so the write can be retried 
+    ChainContended{constraint: String},
+    /// A timestamp authority could not be reached or did not grant a token (see Xtchr::timestamp_head)
+    #[cfg(feature = "tsa")]
+    Tsa(crate::tsa::TsaError),
+}
+
+impl fmt::Display for XtchdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XtchdError::Pachy(e) => write!(f, "{}", e),
+            XtchdError::Integrity(e) => write!(f, "{}", e),
+            XtchdError::Xrow(e) => write!(f, "{}", e),
+            XtchdError::InvalidArgument(msg) => write!(f, "invalid argument: {}", msg),
+            XtchdError::Io(e) => write!(f, "{}", e),
+            XtchdError::Timeout => write!(f, "the statement was cancelled, i.e. for exceeding the statement_timeout"),
+            XtchdError::NotFound(msg) => write!(f, "not found: {}", msg),
+            XtchdError::ChainContended{constraint} => write!(f, "another write took the same position in the chain first (unique constraint '{}'): retry the write", constraint),
+            #[cfg(feature = "tsa")]
+            XtchdError::Tsa(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for XtchdError {}
+
+impl From<PachyDarn> for XtchdError {
+    fn from(e: PachyDarn) -> Self {
+        XtchdError::Pachy(e)
+    }
+}
+
+/// A unique violation becomes XrowError::Conflict, so callers can tell "already exists" apart from real failures,
+/// except a violation of a row's position in a chain, which is retryable and becomes XtchdError::ChainContended.
+/// A cancelled statement (QUERY_CANCELED, whether by the statement_timeout or pg_cancel_backend()) becomes XtchdError::Timeout.
+/// Methods which still return PachyDarn report all of these as any other database error 
+impl From<tokio_postgres::Error> for XtchdError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        if e.code() == Some(&SqlState::QUERY_CANCELED) {
+            return XtchdError::Timeout
+        }
+        if e.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            let constraint = e.as_db_error().and_then(|db| db.constraint()).unwrap_or_default().to_string();
+            if crate::xtchr::is_chain_position(&constraint) {
+                return XtchdError::ChainContended{constraint}
+            }
+            return XtchdError::Xrow(XrowError::Conflict{constraint})
+        }
+        XtchdError::Pachy(PachyDarn::from(e))
+    }
+}
+
+impl From<IntegrityError> for XtchdError {
+    fn from(e: IntegrityError) -> Self {
+        XtchdError::Integrity(e)
+    }
+}
+
+impl From<XrowError> for XtchdError {
+    fn from(e: XrowError) -> Self {
+        XtchdError::Xrow(e)
+    }
+}
+
+impl From<std::io::Error> for XtchdError {
+    fn from(e: std::io::Error) -> Self {
+        XtchdError::Io(e)
+    }
+}
+
+#[cfg(feature = "tsa")]
+impl From<crate::tsa::TsaError> for XtchdError {
+    fn from(e: crate::tsa::TsaError) -> Self {
+        XtchdError::Tsa(e)
+    }
+}
+
+
+/// The status an HTTP handler should respond with for an error, so every handler maps errors the same way:
+/// a missing row is 404, a unique violation 409, a timeout 504, a failing timestamp authority 502, a contended chain 503 
+/// (so the client retries), and invalid input 400. 
+/// Anything else, including every PachyDarn (which cannot be told apart from outside pachydurable) and content 
+/// failing hash verification, is 500 
+#[cfg(feature = "http")]
+impl From<&XtchdError> for http::StatusCode {
+    fn from(e: &XtchdError) -> Self {
+        match e {
+            XtchdError::NotFound(_) => http::StatusCode::NOT_FOUND,
+            XtchdError::Xrow(XrowError::Conflict{..}) => http::StatusCode::CONFLICT,
+            XtchdError::Timeout => http::StatusCode::GATEWAY_TIMEOUT,
+            XtchdError::ChainContended{..} => http::StatusCode::SERVICE_UNAVAILABLE,
+            XtchdError::InvalidArgument(_) | XtchdError::Xrow(_) => http::StatusCode::BAD_REQUEST,
+            XtchdError::Pachy(_) | XtchdError::Integrity(_) | XtchdError::Io(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+            #[cfg(feature = "tsa")]
+            XtchdError::Tsa(_) => http::StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_status_code() {
+        use http::StatusCode;
+        let status = |e: XtchdError| StatusCode::from(&e);
+        assert_eq!(status(XtchdError::NotFound("missing row".to_string())), StatusCode::NOT_FOUND);
+        assert_eq!(status(XtchdError::Xrow(XrowError::Conflict{constraint: "authors_name_key".to_string()})), StatusCode::CONFLICT);
+        assert_eq!(status(XtchdError::Timeout), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(status(XtchdError::ChainContended{constraint: "authors_pkey".to_string()}), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(XtchdError::InvalidArgument("batch_size".to_string())), StatusCode::BAD_REQUEST);
+        assert_eq!(status(XtchdError::Xrow(XrowError::SvgRejected)), StatusCode::BAD_REQUEST);
+        let pachy = PachyDarn::from(pachydurable::err::MissingRowError::from_str("missing row"));
+        assert_eq!(status(XtchdError::Pachy(pachy)), StatusCode::INTERNAL_SERVER_ERROR);
+        let integrity = IntegrityError::HashMismatch{dtype: "Author".to_string(), stored: "a".repeat(64), computed: "b".repeat(64)};
+        assert_eq!(status(XtchdError::Integrity(integrity)), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(status(XtchdError::Io(std::io::Error::from(std::io::ErrorKind::BrokenPipe))), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -4,7 +4,8 @@ use serde::{Serialize, Deserialize, de::DeserializeOwned};
 use serde_json;
 use tokio_postgres;
 use sha2::{Sha256, Digest}; // Digest brings the ::new() method into scope
-use chrono::{DateTime, offset::Utc};
+use chrono::{DateTime, TimeZone, offset::Utc};
+use rayon::prelude::*;
 
 
 /// Rust does not allow Options to be displayed using the "{}" format
@@ -26,12 +27,17 @@ pub fn now() -> DateTime<Utc> {
     Utc::now()
 }
 
-pub fn time_fmt(ts: &DateTime<Utc>) -> String {
-    // format a timestamp like this:
-    // 'YYYY.MM.DD HH24:MI:SS' (Postgres)
-    ts.format("%Y.%m.%d %H:%M:%S").to_string()
+/// Format a timestamp like this: 'YYYY.MM.DD HH24:MI:SS' (Postgres), always as the UTC instant.
+/// A timestamp in any other time zone (i.e. DateTime<Local>) is converted first, as the string is hashed.
+/// NOTE: Postgres formats write_timestamp in the session time zone, so the session must use UTC: 
+/// see PoolConfig::deadpool_config(), which sets TimeZone=UTC for every connection 
+pub fn time_fmt<Tz: TimeZone>(ts: &DateTime<Tz>) -> String {
+    ts.with_timezone(&Utc).format("%Y.%m.%d %H:%M:%S").to_string()
 }
 
+/// The prior_sha256 for the very first row of each table with hash chain integrity
+pub const GENESIS_SHA256: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
 pub fn sha256(input: &str) -> String { 
     let mut hasher = Sha256::new();                                 
     hasher.update(input.as_bytes());
@@ -60,6 +66,24 @@ pub trait Xtchable {
 }
 
 
+/// The ToRow trait describes the content-specific columns for an Xtchable struct, 
+/// so it can be written with Xtchr::append() rather than a bespoke add_* method.
+/// The columns common to every table with hash chain integrity (prior_id, prior_sha256, write_timestamp, new_sha256) are not included 
+pub trait ToRow {
+    /// The id column, which increases by one for each row, i.e. 'auth_id'
+    fn id_column() -> &'static str;
+
+    /// The content-specific columns including the id column, in the same order as .values()
+    fn columns() -> &'static [&'static str];
+
+    /// The values for the columns, in the same order as ::columns()
+    fn values(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)>;
+
+    /// Assign the id, which is only known once the last row of the table has been read (and must be set before hashing)
+    fn set_id(&mut self, id: i32);
+}
+
+
 /// When an instance of a struct implementing the Xtchable trait is written to disk,
 /// data including the prior_id, write_timestamp, and new_sha_256 are written as well.
 /// This data is used in Postgres to cryptographically verify the integrity of the row being written. 
@@ -94,7 +118,73 @@ pub struct XtchdSQL<T: Xtchable> {
 }
 
 
-impl<T: Xtchable + Serialize + DeserializeOwned> XtchdContent<T> {
+/// Controls whether content read back from Postgres is trusted as stored,
+/// or whether its new_sha256 is recomputed (and the read rejected if it does not match) 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VerifyMode {
+    /// Return the stored content and new_sha256 as-is, leaving verification to the client 
+    Trust,
+    /// Recompute the hash from the content, write_timestamp, and prior_sha256 and return an IntegrityError on mismatch
+    Recompute,
+}
+
+
+/// The result of Xtchr::verify_row(), which checks a single row against its immediate predecessor 
+/// rather than walking the whole chain from the first row
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RowVerification {
+    /// The row's prior_sha256 matches the new_sha256 of the row before it (or GENESIS_SHA256 for the first row)
+    pub prior_matches: bool,
+    /// The row's new_sha256 recomputes from its content, write_timestamp, and prior_sha256
+    pub hash_matches: bool,
+}
+
+impl RowVerification {
+    /// True only if both checks passed 
+    pub fn is_valid(&self) -> bool {
+        self.prior_matches && self.hash_matches
+    }
+}
+
+
+/// An IntegrityError indicates content whose hash does not match what was stored for it, 
+/// i.e. the content (or its hash) has been tampered with
+#[derive(Debug)]
+pub enum IntegrityError {
+    /// The new_sha256 recomputed from the content does not match the stored new_sha256
+    HashMismatch{dtype: String, stored: String, computed: String},
+    /// A row's prior_sha256 does not match the new_sha256 of the row before it (or GENESIS_SHA256 for the first row)
+    BrokenLink{dtype: String, prior_id: Option<i32>, expected: String, found: String},
+    /// A row's prior_id does not follow on from the row before it, i.e. a row is missing or out of order
+    OutOfSequence{dtype: String, expected: Option<i32>, found: Option<i32>},
+    /// The doc_sha256 of a document does not match the hash over the new_sha256 of its parts (see document_sha256)
+    DocumentMismatch{stored: String, computed: String},
+    /// A part of a document belongs elsewhere: a page of another article, or an author other than the title's,
+    /// i.e. a valid row spliced in from another document 
+    ForeignPart{dtype: String, expected: i32, found: i32},
+}
+
+impl std::fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IntegrityError::HashMismatch{dtype, stored, computed} => 
+                write!(f, "{} has stored new_sha256={} but its content hashes to {}", dtype, stored, computed),
+            IntegrityError::BrokenLink{dtype, prior_id, expected, found} => 
+                write!(f, "{} with prior_id={} has prior_sha256={} but the prior row has new_sha256={}", dtype, nonefmt(prior_id), found, expected),
+            IntegrityError::OutOfSequence{dtype, expected, found} => 
+                write!(f, "{} has prior_id={} where prior_id={} was expected", dtype, nonefmt(found), nonefmt(expected)),
+            IntegrityError::DocumentMismatch{stored, computed} => 
+                write!(f, "document has doc_sha256={} but its parts hash to {}", stored, computed),
+            IntegrityError::ForeignPart{dtype, expected, found} => 
+                write!(f, "{} in the document belongs to {} rather than {}", dtype, found, expected),
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+
+impl<T: Xtchable> XtchdContent<T> {
 
     pub fn new(prior_id: Option<i32>, prior_sha256: String, write_timestamp: DateTime<Utc>, content: T, new_sha256: String) -> Self {
         let hcl = HashChainLink::from_timestamp(&prior_sha256, write_timestamp.clone(), &content);
@@ -103,18 +193,121 @@ impl<T: Xtchable + Serialize + DeserializeOwned> XtchdContent<T> {
         XtchdContent{dtype, prior_id, prior_sha256, content, hcl, new_sha256, write_timestamp_str}
     }
 
+    /// Build from a row read back from Postgres without checking the stored new_sha256,
+    /// i.e. for verification tooling, which must read tampered rows in order to report them 
     pub fn from_sql(xsql: XtchdSQL<T>) -> Self {
         XtchdContent::new(xsql.prior_id, xsql.prior_sha256, xsql.write_timestamp, xsql.content, xsql.new_sha256)
     }
 
+    /// Build from a row read back from Postgres, returning an IntegrityError if sha256(string_to_hash) 
+    /// is not the stored new_sha256, i.e. if the row was tampered with or Rust and Postgres disagree on formatting  
+    pub fn from_sql_checked(xsql: XtchdSQL<T>) -> Result<Self, IntegrityError> {
+        let xc = XtchdContent::from_sql(xsql);
+        xc.verify()?;
+        Ok(xc)
+    }
+
+    /// Confirm the stored new_sha256 matches the hash of a HashChainLink rebuilt from the content 
+    pub fn verify(&self) -> Result<(), IntegrityError> {
+        let computed = HashChainLink::from_timestamp(&self.prior_sha256, self.hcl.write_timestamp, &self.content).new_sha256();
+        match computed == self.new_sha256 {
+            true => Ok(()),
+            false => Err(IntegrityError::HashMismatch{dtype: self.dtype.clone(), stored: self.new_sha256.clone(), computed}),
+        }
+    }
+
+}
+
+
+impl<T: Xtchable + Serialize> XtchdContent<T> {
+
+    /// Serialize to JSON in a canonical form, so the JSON sent to the browser is byte-for-byte 
+    /// what the JavaScript verification code produces when it re-serializes the same content:
+    /// - object keys are sorted by code point at every level (regardless of struct field order)
+    /// - there is no whitespace between tokens
+    /// - strings are escaped as by JSON.stringify: only '"', '\\' and control characters
+    /// - integers are written exactly, as are floats with no fractional part (i.e. 2.0 becomes 2, as in JavaScript),
+    ///   other floats use the shortest representation which round-trips
+    pub fn canonical_json(&self) -> String {
+        let value = serde_json::to_value(self).expect("XtchdContent always serializes to JSON");
+        let mut out = String::new();
+        write_canonical(&value, &mut out);
+        out
+    }
+
+    /// Borrow as an XtchdSummary, which serializes without hcl.string_to_hash (i.e. for list responses)
+    pub fn summary(&self) -> XtchdSummary<'_, T> {
+        XtchdSummary{
+            dtype: &self.dtype, prior_id: self.prior_id, prior_sha256: &self.prior_sha256, content: &self.content,
+            hcl: LinkSummary{write_timestamp: self.hcl.write_timestamp},
+            write_timestamp_str: &self.write_timestamp_str, new_sha256: &self.new_sha256,
+        }
+    }
+
 }
 
 
-impl<'a, T: Xtchable + Serialize + DeserializeOwned> tokio_postgres::types::FromSql<'a> for XtchdContent<T> {
+/// XtchdContent as serialized for list and summary responses: the same fields, but the hcl omits string_to_hash.
+/// The string_to_hash repeats the content, so this roughly halves the payload for large content (i.e. images and long pages).
+/// The tradeoff is that a client cannot simply hash the string it was sent: to verify new_sha256 it must reconstruct 
+/// the string_to_hash from the content, write_timestamp_str and prior_sha256, so detail responses should send XtchdContent itself
+#[derive(Serialize)]
+pub struct XtchdSummary<'a, T: Xtchable> {
+    pub dtype: &'a str,
+    pub prior_id: Option<i32>,
+    pub prior_sha256: &'a str,
+    pub content: &'a T,
+    pub hcl: LinkSummary,
+    pub write_timestamp_str: &'a str,
+    pub new_sha256: &'a str,
+}
+
+/// A HashChainLink without its string_to_hash, see XtchdSummary
+#[derive(Serialize, Deserialize)]
+pub struct LinkSummary {
+    pub write_timestamp: DateTime<Utc>,
+}
+
+/// Append one JSON value to out in the form documented on XtchdContent::canonical_json()
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    use serde_json::Value;
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => out.push_str(&value.to_string()),
+        Value::Number(num) => match num.as_f64() {
+            Some(f) if !num.is_i64() && !num.is_u64() && f.fract() == 0.0 && f.abs() < 1e21 => out.push_str(&format!("{:.0}", f)),
+            _ => out.push_str(&num.to_string()),
+        },
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 { out.push(','); }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        },
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 { out.push(','); }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        },
+    }
+}
+
+
+/// Reading XtchdContent from a row is checked (see XtchdContent::from_sql_checked()), 
+/// so a self-inconsistent object is a deserialization error rather than something sent to a client 
+impl<'a, T: Xtchable + DeserializeOwned> tokio_postgres::types::FromSql<'a> for XtchdContent<T> {
 
     fn from_sql(_ty: &tokio_postgres::types::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
         let xsql: XtchdSQL<T> = serde_json::from_slice(raw)?;
-        let xc = XtchdContent::from_sql(xsql);
+        let xc = XtchdContent::from_sql_checked(xsql)?;
         Ok(xc)
     }
 
@@ -123,6 +316,19 @@ impl<'a, T: Xtchable + Serialize + DeserializeOwned> tokio_postgres::types::From
     }
 }
 
+
+impl<'a, T: Xtchable + DeserializeOwned> tokio_postgres::types::FromSql<'a> for XtchdSQL<T> {
+
+    fn from_sql(_ty: &tokio_postgres::types::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let xsql: XtchdSQL<T> = serde_json::from_slice(raw)?;
+        Ok(xsql)
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+}
+
 /// The hash chain link contains key information needed to help write Postgres rows
 /// Creating a hash chain between the prior row and a new row with its content 
 #[derive(Serialize, Deserialize)]
@@ -139,7 +345,9 @@ impl HashChainLink {
         HashChainLink::from_timestamp(prior_sha256, write_timestamp, content)
     }
 
-    pub fn from_timestamp<T: Xtchable>(prior_sha256: &str, write_timestamp: DateTime<Utc>, content: &T) -> Self {
+    /// The write_timestamp may be in any time zone: it is converted to UTC (see time_fmt)
+    pub fn from_timestamp<T: Xtchable, Tz: TimeZone>(prior_sha256: &str, write_timestamp: DateTime<Tz>, content: &T) -> Self {
+        let write_timestamp = write_timestamp.with_timezone(&Utc);
         let string_to_hash = format!("{} write_timestamp={} prior_sha256={}",
             content.state_string(), time_fmt(&write_timestamp), &prior_sha256); 
         HashChainLink{write_timestamp, string_to_hash}
@@ -152,3 +360,391 @@ impl HashChainLink {
 }
 
 
+
+/// Verify a whole chain purely in memory, i.e. one exported with Xtchr::export_chain(), without access to the database.
+/// The items must start from the first row of the table and be in id order. Each item must hash to its new_sha256, 
+/// and each prior_sha256 must match the new_sha256 of the item before it (or GENESIS_SHA256 for the first item)
+pub fn verify_bundle<T: Xtchable>(items: &[XtchdContent<T>]) -> Result<(), IntegrityError> {
+    walk_chain(items, None, GENESIS_SHA256, |_, item| item.verify())
+}
+
+/// Verify a batch of consecutive rows from part way along a chain, which follows on from the row with 
+/// prior_id and prior_sha256 (None and GENESIS_SHA256 for the first batch). The checks, and the first error returned,
+/// are the same as for verify_bundle(), but the hashes are recomputed in parallel on the rayon thread pool as sha256 is CPU-bound
+pub fn verify_batch<T: Xtchable + Sync>(items: &[XtchdContent<T>], prior_id: Option<i32>, prior_sha256: &str) -> Result<(), IntegrityError> {
+    let mut verified: Vec<Result<(), IntegrityError>> = items.par_iter().map(XtchdContent::verify).collect();
+    walk_chain(items, prior_id, prior_sha256, |i, _| std::mem::replace(&mut verified[i], Ok(())))
+}
+
+/// Check that each item follows on from the one before it (starting from prior_id and prior_sha256), 
+/// and that hash_check passes for each item, returning the first error in chain order
+fn walk_chain<T: Xtchable, F>(items: &[XtchdContent<T>], prior_id: Option<i32>, prior_sha256: &str, mut hash_check: F) -> Result<(), IntegrityError> 
+    where F: FnMut(usize, &XtchdContent<T>) -> Result<(), IntegrityError> {
+    let mut expected_prior_id: Option<i32> = prior_id;
+    let mut expected_prior_sha256: &str = prior_sha256;
+    for (i, item) in items.iter().enumerate() {
+        if item.prior_id != expected_prior_id {
+            return Err(IntegrityError::OutOfSequence{dtype: item.dtype.clone(), expected: expected_prior_id, found: item.prior_id})
+        }
+        if item.prior_sha256 != expected_prior_sha256 {
+            return Err(IntegrityError::BrokenLink{dtype: item.dtype.clone(), prior_id: item.prior_id, 
+                expected: expected_prior_sha256.to_string(), found: item.prior_sha256.clone()})
+        }
+        hash_check(i, item)?;
+        expected_prior_id = Some(expected_prior_id.map_or(0, |id| id + 1));
+        expected_prior_sha256 = item.new_sha256.as_str();
+    }
+    Ok(())
+}
+
+
+
+/// The id of the row wrapped by an XtchdContent, which follows on from its prior_id (or is 0 for the first row)
+pub fn row_id<T: Xtchable>(item: &XtchdContent<T>) -> i32 {
+    item.prior_id.map_or(0, |prior_id| prior_id + 1)
+}
+
+/// Compare two copies of a chain (i.e. a backup and a replica, both starting from the first row) and return the first id 
+/// at which they diverge: where their new_sha256 differ, or where one chain has a row the other is missing. 
+/// Returns None if the chains are identical 
+pub fn find_divergence<T: Xtchable>(a: &[XtchdContent<T>], b: &[XtchdContent<T>]) -> Option<i32> {
+    if let Some((item, _)) = a.iter().zip(b.iter()).find(|(x, y)| x.new_sha256 != y.new_sha256) {
+        return Some(row_id(item))
+    }
+    let common = a.len().min(b.len());
+    a.get(common).or_else(|| b.get(common)).map(row_id)
+}
+
+
+/// One field which differs between two state strings (see diff_state_strings): 
+/// the value is None on the side where the field is missing 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub key: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+/// Split a state string (see Xtchable::state_string and HashChainLink.string_to_hash) into its key=value fields, in order.
+/// A field starts at each space-separated word of the form key=..., where the key is lowercase letters, digits and underscores,
+/// so a value may contain spaces (i.e. a write_timestamp) but a value containing such a word is split in two 
+fn state_fields(state: &str) -> Vec<(&str, String)> {
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for word in state.split(' ') {
+        let key = word.split_once('=').map(|(key, _)| key)
+            .filter(|key| !key.is_empty() && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'));
+        match key {
+            Some(key) => fields.push((key, word[key.len() + 1..].to_string())),
+            None => match fields.last_mut() {
+                Some((_, value)) => { value.push(' '); value.push_str(word); },
+                None => fields.push(("", word.to_string())),
+            },
+        }
+    }
+    fields
+}
+
+/// Which fields differ between two state strings, i.e. the string_to_hash computed in Rust and one rebuilt 
+/// from a Postgres CHECK constraint, to turn a hash mismatch into "the title field differs".
+/// Fields are listed in the order they appear in expected, followed by any only in actual 
+pub fn diff_state_strings(expected: &str, actual: &str) -> Vec<FieldDiff> {
+    let (expected, actual) = (state_fields(expected), state_fields(actual));
+    let find = |fields: &[(&str, String)], key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, value)| value.clone());
+    let mut diffs: Vec<FieldDiff> = expected.iter()
+        .map(|(key, value)| FieldDiff{key: key.to_string(), expected: Some(value.clone()), actual: find(&actual, key)})
+        .filter(|diff| diff.expected != diff.actual)
+        .collect();
+    diffs.extend(actual.iter().filter(|(key, _)| find(&expected, key).is_none())
+        .map(|(key, value)| FieldDiff{key: key.to_string(), expected: None, actual: Some(value.clone())}));
+    diffs
+}
+
+
+/// The domain separation prefixes for Merkle tree hashes (as in RFC 6962), so a leaf can never be passed off as an 
+/// interior node or vice versa: a leaf hashes as sha256(0x00 || leaf) and a node as sha256(0x01 || left || right)
+const MERKLE_LEAF: u8 = 0x00;
+const MERKLE_NODE: u8 = 0x01;
+
+/// The lowercase hex sha256 of the prefix byte followed by each of the parts 
+fn merkle_hash(prefix: u8, parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update([prefix]);
+    for part in parts {
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// The hash of a leaf (i.e. the new_sha256 of a row) within a Merkle tree 
+fn merkle_leaf(leaf: &str) -> String {
+    merkle_hash(MERKLE_LEAF, &[leaf])
+}
+
+/// The parents of one level of a Merkle tree: each parent is the node hash of its two (hex) children.
+/// When a level has an odd number of nodes, the last one is promoted to the next level unchanged, 
+/// so [a, b, c] and [a, b, c, c] have different roots 
+fn merkle_level(nodes: &[String]) -> Vec<String> {
+    nodes.chunks(2).map(|pair| match pair {
+        [left, right] => merkle_hash(MERKLE_NODE, &[left.as_str(), right.as_str()]),
+        _ => pair[0].clone(),
+    }).collect()
+}
+
+/// The Merkle root over the leaves (i.e. the new_sha256 of each row in id order), or None if there are no leaves.
+/// The root for a single leaf is the leaf hash of that leaf 
+pub fn merkle_root(leaves: &[String]) -> Option<String> {
+    let mut level: Vec<String> = leaves.iter().map(|leaf| merkle_leaf(leaf)).collect();
+    while level.len() > 1 {
+        level = merkle_level(&level);
+    }
+    level.pop()
+}
+
+/// The audit path from the leaf at index up to the root: each step is the sibling hash and 
+/// whether that sibling is on the left. A level where the node has no sibling (and so is promoted) adds no step.
+/// Returns None if the index is out of range 
+pub fn merkle_proof(leaves: &[String], index: usize) -> Option<Vec<(String, bool)>> {
+    if index >= leaves.len() {
+        return None
+    }
+    let mut proof = Vec::new();
+    let mut level: Vec<String> = leaves.iter().map(|leaf| merkle_leaf(leaf)).collect();
+    let mut index = index;
+    while level.len() > 1 {
+        let step = match index % 2 {
+            0 => level.get(index + 1).map(|sibling| (sibling.clone(), false)),
+            _ => Some((level[index - 1].clone(), true)),
+        };
+        proof.extend(step);
+        level = merkle_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Confirm the leaf hashes up to the root via the audit path returned by merkle_proof() 
+pub fn verify_merkle_proof(leaf: &str, proof: &[(String, bool)], root: &str) -> bool {
+    let computed = proof.iter().fold(merkle_leaf(leaf), |node, (sibling, sibling_is_left)| {
+        match sibling_is_left {
+            true => merkle_hash(MERKLE_NODE, &[sibling.as_str(), node.as_str()]),
+            false => merkle_hash(MERKLE_NODE, &[node.as_str(), sibling.as_str()]),
+        }
+    });
+    computed == root
+}
+
+
+/// The hash over a document made of several rows: the sha256 of the concatenation of the new_sha256 of each part, in order 
+pub fn document_sha256(parts: &[&str]) -> String {
+    sha256(&parts.concat())
+}
+
+/// Verify an ArticleDocument (see Xtchr::article_document) without database access: 
+/// the title, author, and every page must each hash to its new_sha256, the author and every page must belong to the title
+/// (as each row verifies on its own, rows of another document could otherwise be spliced in), 
+/// and the doc_sha256 must be the document_sha256() of the title, author, and pages in that order 
+pub fn verify_article_document(doc: &crate::views::ArticleDocument) -> Result<(), IntegrityError> {
+    doc.title.verify()?;
+    doc.author.verify()?;
+    if doc.author.content.auth_id != doc.title.content.auth_id {
+        return Err(IntegrityError::ForeignPart{dtype: doc.author.dtype.clone(), expected: doc.title.content.auth_id, found: doc.author.content.auth_id})
+    }
+    for page in doc.pages.iter() {
+        page.verify()?;
+        if page.content.a_id_immut != doc.title.content.a_id_immut {
+            return Err(IntegrityError::ForeignPart{dtype: page.dtype.clone(), expected: doc.title.content.a_id_immut, found: page.content.a_id_immut})
+        }
+    }
+    let computed = doc.compute_sha256();
+    match computed == doc.doc_sha256 {
+        true => Ok(()),
+        false => Err(IntegrityError::DocumentMismatch{stored: doc.doc_sha256.clone(), computed}),
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    struct Note {
+        id: i32,
+        text: String,
+    }
+
+    impl Xtchable for Note {
+        fn state_string(&self) -> String {
+            format!("id={} text={}", &self.id, &self.text)
+        }
+        fn dtype() -> &'static str {
+            "Note"
+        }
+    }
+
+    /// build a valid chain of Notes in memory, as if they had been read back from Postgres
+    fn chain_of_notes(texts: &[&str]) -> Vec<XtchdContent<Note>> {
+        let mut chain: Vec<XtchdContent<Note>> = Vec::new();
+        for (id, text) in texts.iter().enumerate() {
+            let id = id as i32;
+            let prior_sha256 = chain.last().map_or(GENESIS_SHA256.to_string(), |prior| prior.new_sha256.clone());
+            let note = Note{id, text: text.to_string()};
+            let hcl = HashChainLink::new(&prior_sha256, &note);
+            let prior_id = match id { 0 => None, _ => Some(id - 1) };
+            chain.push(XtchdContent::new(prior_id, prior_sha256, hcl.write_timestamp, note, hcl.new_sha256()));
+        }
+        chain
+    }
+
+    #[test]
+    fn test_verify_bundle() {
+        let mut chain = chain_of_notes(&["first", "second", "third"]);
+        assert!(verify_bundle(&chain).is_ok());
+        chain[1].content.text = "edited".to_string();
+        assert!(matches!(verify_bundle(&chain), Err(IntegrityError::HashMismatch{..})));
+        let mut chain = chain_of_notes(&["first", "second", "third"]);
+        chain.remove(1);
+        assert!(matches!(verify_bundle(&chain), Err(IntegrityError::OutOfSequence{..})));
+        // a batch part way along the chain follows on from the row before it 
+        let chain = chain_of_notes(&["first", "second", "third"]);
+        assert!(verify_batch(&chain[1..], Some(0), &chain[0].new_sha256).is_ok());
+        assert!(matches!(verify_batch(&chain[1..], Some(0), GENESIS_SHA256), Err(IntegrityError::BrokenLink{..})));
+    }
+
+    #[test]
+    fn test_merkle() {
+        let leaves: Vec<String> = ["a", "b", "c"].iter().map(|leaf| sha256(leaf)).collect();
+        let leaf = |i: usize| merkle_leaf(&leaves[i]);
+        let node = |left: &str, right: &str| merkle_hash(MERKLE_NODE, &[left, right]);
+        // a single leaf's root is its leaf hash (never the leaf itself), with an empty proof 
+        assert_eq!(merkle_root(&leaves[..1]), Some(leaf(0)));
+        assert_ne!(merkle_root(&leaves[..1]), Some(leaves[0].clone()));
+        assert_eq!(merkle_proof(&leaves[..1], 0), Some(Vec::new()));
+        // two leaves 
+        let root2 = merkle_root(&leaves[..2]).unwrap();
+        assert_eq!(root2, node(&leaf(0), &leaf(1)));
+        assert_eq!(merkle_proof(&leaves[..2], 1).unwrap(), vec![(leaf(0), true)]);
+        // an odd count promotes the last leaf unchanged 
+        let root3 = merkle_root(&leaves).unwrap();
+        assert_eq!(root3, node(&root2, &leaf(2)));
+        assert_eq!(merkle_proof(&leaves, 2).unwrap(), vec![(root2.clone(), true)]);
+        for (i, leaf) in leaves.iter().enumerate() {
+            let proof = merkle_proof(&leaves, i).unwrap();
+            assert!(verify_merkle_proof(leaf, &proof, &root3));
+            assert!(!verify_merkle_proof(&sha256("tampered"), &proof, &root3));
+        }
+        assert!(merkle_root(&[]).is_none());
+        assert!(merkle_proof(&leaves, 3).is_none());
+    }
+
+    #[test]
+    fn test_merkle_no_duplicate_collision() {
+        // duplicating the last leaf must not give the same root (CVE-2012-2459), nor may an interior node pass as a leaf 
+        let leaves: Vec<String> = ["a", "b", "c"].iter().map(|leaf| sha256(leaf)).collect();
+        let mut padded = leaves.clone();
+        padded.push(leaves[2].clone());
+        assert_ne!(merkle_root(&leaves), merkle_root(&padded));
+        let root2 = merkle_root(&leaves[..2]).unwrap();
+        let interior = merkle_hash(MERKLE_NODE, &[merkle_leaf(&leaves[0]).as_str(), merkle_leaf(&leaves[1]).as_str()]);
+        assert_eq!(root2, interior);
+        assert!(!verify_merkle_proof(&interior, &[], &root2));
+    }
+
+    #[test]
+    fn test_find_divergence() {
+        let chain = chain_of_notes(&["a", "b", "c"]);
+        assert_eq!(find_divergence(&chain, &chain), None);
+        // one chain is a prefix of the other: they diverge at the first missing row 
+        assert_eq!(find_divergence(&chain[..2], &chain), Some(2));
+        assert_eq!(find_divergence(&chain, &chain[..1]), Some(1));
+        // a copy with a different row diverges at that row 
+        let mut forked: Vec<XtchdContent<Note>> = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+        assert_eq!(find_divergence(&chain, &forked), None);
+        forked[1].new_sha256 = sha256("forked");
+        assert_eq!(find_divergence(&chain, &forked), Some(1));
+    }
+
+    #[test]
+    fn test_diff_state_strings() {
+        let expected = "a_id_immut=3 auth_id=7 title=A title write_timestamp=2023.01.02 03:04:05 prior_sha256=abc";
+        assert!(diff_state_strings(expected, expected).is_empty());
+        // one field differs, including a value with spaces 
+        let retitled = "a_id_immut=3 auth_id=7 title=Another title write_timestamp=2023.01.02 03:04:05 prior_sha256=abc";
+        assert_eq!(diff_state_strings(expected, retitled), vec![FieldDiff{key: "title".to_string(), 
+            expected: Some("A title".to_string()), actual: Some("Another title".to_string())}]);
+        // several fields differ, and fields missing from either side are reported 
+        let drifted = "a_id_immut=4 title=A title write_timestamp=2023.01.02 03:04:06 prior_sha256=abc draft=x";
+        let keys: Vec<(String, Option<String>, Option<String>)> = diff_state_strings(expected, drifted).into_iter()
+            .map(|diff| (diff.key, diff.expected, diff.actual)).collect();
+        assert_eq!(keys, vec![
+            ("a_id_immut".to_string(), Some("3".to_string()), Some("4".to_string())),
+            ("auth_id".to_string(), Some("7".to_string()), None),
+            ("write_timestamp".to_string(), Some("2023.01.02 03:04:05".to_string()), Some("2023.01.02 03:04:06".to_string())),
+            ("draft".to_string(), None, Some("x".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn test_time_fmt_utc() {
+        // the same instant hashes the same whatever time zone it is given in 
+        let utc = Utc.with_ymd_and_hms(2023, 6, 30, 22, 30, 0).unwrap();
+        let tokyo = utc.with_timezone(&chrono::FixedOffset::east_opt(9 * 3600).unwrap());
+        assert_eq!(tokyo.format("%Y.%m.%d %H:%M:%S").to_string(), "2023.07.01 07:30:00");
+        assert_eq!(time_fmt(&tokyo), "2023.06.30 22:30:00");
+        assert_eq!(time_fmt(&tokyo), time_fmt(&utc));
+        let note = Note{id: 0, text: "zoned".to_string()};
+        let from_tokyo = HashChainLink::from_timestamp(GENESIS_SHA256, tokyo, &note);
+        assert_eq!(from_tokyo.write_timestamp, utc);
+        assert_eq!(from_tokyo.new_sha256(), HashChainLink::from_timestamp(GENESIS_SHA256, utc, &note).new_sha256());
+    }
+
+    #[test]
+    fn test_canonical_json() {
+        // pinned against testdata/author_canonical.json: a change here breaks verification in the browser 
+        let author = crate::xrows::Author{auth_id: 1, name: "Ada Lovelace".to_string()};
+        let written = Utc.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).unwrap();
+        let hcl = HashChainLink::from_timestamp(GENESIS_SHA256, written, &author);
+        let xc = XtchdContent::new(None, GENESIS_SHA256.to_string(), written, author, hcl.new_sha256());
+        assert_eq!(xc.canonical_json(), include_str!("../testdata/author_canonical.json").trim_end());
+        // floats without a fractional part are written as JavaScript would 
+        let mut out = String::new();
+        write_canonical(&serde_json::json!({"b": [2.0, 0.5, -3], "a": "\"quoted\"\n"}), &mut out);
+        assert_eq!(out, r#"{"a":"\"quoted\"\n","b":[2,0.5,-3]}"#);
+    }
+
+    #[test]
+    fn test_summary_omits_string_to_hash() {
+        let chain = chain_of_notes(&["a long note"]);
+        let detail = serde_json::to_value(&chain[0]).unwrap();
+        let summary = serde_json::to_value(chain[0].summary()).unwrap();
+        assert!(detail["hcl"].get("string_to_hash").is_some());
+        assert!(summary["hcl"].get("string_to_hash").is_none());
+        assert_eq!(summary["hcl"]["write_timestamp"], detail["hcl"]["write_timestamp"]);
+        // apart from the string_to_hash, the summary is the detail 
+        let mut trimmed = detail.clone();
+        trimmed["hcl"].as_object_mut().unwrap().remove("string_to_hash");
+        assert_eq!(summary, trimmed);
+    }
+
+    #[test]
+    fn test_from_sql_checked() {
+        let note = Note{id: 0, text: "first".to_string()};
+        let hcl = HashChainLink::new(GENESIS_SHA256, &note);
+        let xsql = |new_sha256: String| serde_json::json!({"prior_id": null, "prior_sha256": GENESIS_SHA256, 
+            "content": {"id": 0, "text": "first"}, "write_timestamp": hcl.write_timestamp, "new_sha256": new_sha256});
+        let ty = tokio_postgres::types::Type::JSON;
+        let good = serde_json::to_vec(&xsql(hcl.new_sha256())).unwrap();
+        let xc = <XtchdContent<Note> as tokio_postgres::types::FromSql>::from_sql(&ty, &good).unwrap();
+        assert_eq!(xc.hcl.string_to_hash, hcl.string_to_hash);
+        // a crafted row whose stored hash is wrong is rejected, but can still be read unchecked 
+        let bad = serde_json::to_vec(&xsql("f".repeat(64))).unwrap();
+        assert!(<XtchdContent<Note> as tokio_postgres::types::FromSql>::from_sql(&ty, &bad).is_err());
+        let xsql: XtchdSQL<Note> = serde_json::from_slice(&bad).unwrap();
+        let xc = XtchdContent::from_sql(xsql);
+        assert!(matches!(xc.verify(), Err(IntegrityError::HashMismatch{..})));
+        let xsql: XtchdSQL<Note> = serde_json::from_slice(&bad).unwrap();
+        assert!(matches!(XtchdContent::from_sql_checked(xsql), Err(IntegrityError::HashMismatch{..})));
+    }
+
+}
@@ -1,7 +1,14 @@
+pub mod err;
 pub mod integrity;
 pub mod xrows;
 pub mod views;
 pub mod xtchr;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(feature = "tsa")]
+pub mod tsa;
+#[cfg(test)]
+pub(crate) mod test_harness;
 
 
 pub fn add(left: usize, right: usize) -> usize {
@@ -0,0 +1,134 @@
+//! test_harness.rs creates a uniquely-named Postgres schema containing the xtchd tables (public.sql) 
+//! and views (views.sql), with the genesis rows seeded, so tests that write rows are hermetic and can run in parallel.
+//! The connection used by the TestSchema has its search_path set to the new schema (then public, for extensions), 
+//! which is dropped when the TestSchema is dropped. 
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::runtime::Runtime;
+use crate::xtchr::{Pool, Xtchr};
+
+
+const PUBLIC_SQL: &str = include_str!("../public.sql");
+const VIEWS_SQL: &str = include_str!("../views.sql");
+
+/// distinguishes schemas created by different tests in the same process 
+static SCHEMA_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// extensions are created once, in public: concurrent CREATE EXTENSION IF NOT EXISTS can fail, 
+/// and an extension created in a test schema would be dropped with it
+static EXTENSIONS: tokio::sync::OnceCell<()> = tokio::sync::OnceCell::const_new();
+
+
+pub(crate) struct TestSchema {
+    /// the name of the temporary schema
+    pub name: String,
+    /// an Xtchr whose connection reads and writes the temporary schema
+    pub x: Xtchr,
+    // held so the pool outlives the connection in .x, and for connect()
+    pool: Pool,
+}
+
+impl TestSchema {
+    pub async fn new() -> Self {
+        let pool = Pool::new_from_env().await;
+        let x = pool.get().await.unwrap();
+        let name = format!("xtchd_test_{}_{}_{}", std::process::id(), 
+            crate::integrity::now().timestamp_micros(), SCHEMA_COUNTER.fetch_add(1, Ordering::SeqCst));
+        EXTENSIONS.get_or_init(|| async { x.c.batch_execute("CREATE EXTENSION IF NOT EXISTS pg_trgm SCHEMA public").await.unwrap() }).await;
+        x.c.batch_execute(&format!("CREATE SCHEMA {}; SET search_path TO {}, public; SET TIME ZONE 'UTC';", &name, &name)).await.unwrap();
+        x.c.batch_execute(PUBLIC_SQL).await.unwrap();
+        x.c.batch_execute(VIEWS_SQL).await.unwrap();
+        TestSchema{name, x, pool}
+    }
+
+    /// Another connection to the temporary schema, i.e. for tests of concurrent writers 
+    pub async fn connect(&self) -> Xtchr {
+        let x = self.pool.get().await.unwrap();
+        x.c.batch_execute(&format!("SET search_path TO {}, public; SET TIME ZONE 'UTC';", &self.name)).await.unwrap();
+        x
+    }
+}
+
+impl Drop for TestSchema {
+    fn drop(&mut self) {
+        // Drop cannot be async (and may run while the test's runtime is shutting down),
+        // so the schema is dropped from a fresh runtime on its own thread 
+        let name = self.name.clone();
+        let dropped = std::thread::spawn(move || {
+            let rt = Runtime::new().unwrap();
+            rt.block_on(async {
+                let pool = Pool::new_from_env().await;
+                let x = pool.get().await.unwrap();
+                x.c.batch_execute(&format!("DROP SCHEMA {} CASCADE", &name)).await.unwrap();
+            });
+        }).join();
+        if dropped.is_err() && !std::thread::panicking() {
+            panic!("failed to drop test schema {}", &self.name);
+        }
+    }
+}
+
+
+/// A CHAR(21) draft id derived from a number, for tests which need distinct draft ids 
+pub(crate) fn draft_id(seed: i32) -> String {
+    format!("{:0>21}", seed)
+}
+
+
+/// A grayscale gradient as a PNG data URI, darkening left to right if reversed
+#[cfg(feature = "image-audit")]
+pub(crate) fn gradient_png(width: u32, height: u32, reversed: bool) -> String {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    let img = image::GrayImage::from_fn(width, height, |x, y| {
+        let level = ((x * 255 / width) + (y * 32 / height)).min(255) as u8;
+        image::Luma([if reversed { 255 - level } else { level }])
+    });
+    let mut png = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+    format!("data:image/png;base64,{}", STANDARD.encode(png.into_inner()))
+}
+
+
+/// A granted TimeStampResp whose token holds the MessageImprint for the digest, as a TSA would send (but unsigned)
+#[cfg(feature = "tsa")]
+pub(crate) fn granted_tsa_response(digest: &[u8; 32]) -> Vec<u8> {
+    use crate::tsa::{der, message_imprint, INTEGER, OCTET_STRING, SEQUENCE};
+    let token = der(SEQUENCE, &der(OCTET_STRING, &message_imprint(digest)));
+    let mut content = der(SEQUENCE, &der(INTEGER, &[0]));
+    content.extend(token);
+    der(SEQUENCE, &content)
+}
+
+
+/// Serve one HTTP request on a local port, replying 200 with the body, as a stand-in for a timestamp authority.
+/// Returns the URL to request and a handle which resolves to the body of the request received
+#[cfg(feature = "tsa")]
+pub(crate) async fn mock_http_once(body: Vec<u8>) -> (String, tokio::task::JoinHandle<Vec<u8>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/tsa", listener.local_addr().unwrap());
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let mut buf = [0u8; 4096];
+        // read until the headers and the Content-Length bytes of body after them have arrived 
+        let request_body = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+            let Some(end) = received.windows(4).position(|w| w == b"\r\n\r\n") else {
+                assert!(n > 0, "the connection closed before the request headers were sent");
+                continue
+            };
+            let headers = String::from_utf8_lossy(&received[..end]).to_lowercase();
+            let len: usize = headers.lines().find_map(|line| line.strip_prefix("content-length:")).map_or(0, |len| len.trim().parse().unwrap());
+            if received.len() >= end + 4 + len || n == 0 {
+                break received[end + 4..].to_vec()
+            }
+        };
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Type: application/timestamp-reply\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len());
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&body).await.unwrap();
+        request_body
+    });
+    (url, handle)
+}
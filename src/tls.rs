@@ -0,0 +1,24 @@
+//! tls.rs builds a connection pool whose connections are encrypted with TLS (via rustls),
+//! as required by most managed cloud Postgres databases. 
+//! Server certificates are always verified against the webpki root certificates.
+
+use deadpool_postgres::{Config, Runtime};
+use rustls::{ClientConfig, OwnedTrustAnchor, RootCertStore};
+use tokio_postgres_rustls::MakeRustlsConnect;
+use pachydurable::{connect::ConnPoolNoTLS, err::GenericError};
+
+
+/// Instantiate a pool with TLS from a deadpool_postgres::Config (see PoolConfig::deadpool_config).
+/// The pool type is the same as for unencrypted connections, so the Pool/Xtchr API is unchanged 
+pub fn pool_tls(cfg: Config) -> Result<ConnPoolNoTLS, GenericError> {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let pool = cfg.create_pool(Some(Runtime::Tokio1), MakeRustlsConnect::new(tls_config))?;
+    Ok(pool)
+}
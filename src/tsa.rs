@@ -0,0 +1,206 @@
+//! tsa.rs anchors the head of a chain to trusted external time with an RFC 3161 timestamp authority (TSA):
+//! the head's new_sha256 is submitted as a TimeStampReq and the TimeStampToken returned is stored (see Xtchr::timestamp_head).
+//! Only the little DER needed for requests and responses is encoded and parsed here. The token is NOT verified by xtchd:
+//! its TSTInfo is not parsed, its nonce is not compared with the one sent, and its CMS signature is not checked against the 
+//! TSA's certificate. All that xtchd checks is that the MessageImprint for the hash appears somewhere in the token (see token_mentions_digest), 
+//! which proves nothing, as anyone can write those bytes. Verify a stored token with the TSA's certificate chain, 
+//! i.e. `openssl ts -verify -token_in -in token.der -digest <new_sha256> -CAfile tsa_ca.pem`.
+
+use std::fmt;
+use std::time::Duration;
+
+
+/// The DER for the sha256 AlgorithmIdentifier: OID 2.16.840.1.101.3.4.2.1 with NULL parameters
+const SHA256_ALGORITHM: [u8; 15] = [0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00];
+
+pub(crate) const SEQUENCE: u8 = 0x30;
+pub(crate) const INTEGER: u8 = 0x02;
+pub(crate) const OCTET_STRING: u8 = 0x04;
+const BOOLEAN: u8 = 0x01;
+
+/// How long to wait for the TSA to reply (including connecting) before giving up with TsaError::Http 
+pub const TSA_TIMEOUT: Duration = Duration::from_secs(30);
+
+
+/// A TsaError indicates the timestamp authority could not be reached, refused the request, or replied with something
+/// other than a TimeStampResp granting a token for the hash submitted
+#[derive(Debug, PartialEq)]
+pub enum TsaError {
+    /// The request failed, or the TSA replied with an HTTP status other than 200
+    Http(String),
+    /// The TSA replied with a PKIStatus other than granted (0) or grantedWithMods (1), i.e. 2 for rejection
+    Rejected{status: i64},
+    /// The reply is not valid DER for a TimeStampResp, or its token does not mention the hash submitted
+    Malformed(&'static str),
+    /// A new_sha256 is not 64 hex characters
+    InvalidDigest(String),
+}
+
+impl fmt::Display for TsaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TsaError::Http(msg) => write!(f, "timestamp authority request failed: {}", msg),
+            TsaError::Rejected{status} => write!(f, "timestamp authority rejected the request with PKIStatus {}", status),
+            TsaError::Malformed(msg) => write!(f, "malformed timestamp authority response: {}", msg),
+            TsaError::InvalidDigest(digest) => write!(f, "'{}' is not a hex sha256 digest", digest),
+        }
+    }
+}
+
+impl std::error::Error for TsaError {}
+
+
+/// Decode a new_sha256 to the 32 bytes submitted to the TSA, so the token can be checked against the stored hash as-is
+pub fn digest_from_hex(new_sha256: &str) -> Result<[u8; 32], TsaError> {
+    let invalid = || TsaError::InvalidDigest(new_sha256.to_string());
+    if new_sha256.len() != 64 || !new_sha256.is_ascii() {
+        return Err(invalid())
+    }
+    let mut digest = [0u8; 32];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&new_sha256[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(digest)
+}
+
+/// Encode one DER value: the tag, the length (in short or long form), then the content
+pub(crate) fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    match content.len() {
+        len if len < 0x80 => out.push(len as u8),
+        len => {
+            let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+            out.push(0x80 | bytes.len() as u8);
+            out.extend(bytes);
+        },
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+/// The DER content of a non-negative INTEGER: big-endian with no leading zeros, except one where the high bit is set
+fn der_uint(n: u64) -> Vec<u8> {
+    let mut bytes: Vec<u8> = n.to_be_bytes().into_iter().skip_while(|&b| b == 0).collect();
+    if !matches!(bytes.first(), Some(&b) if b & 0x80 == 0) {
+        bytes.insert(0, 0);
+    }
+    bytes
+}
+
+/// The DER MessageImprint for a sha256 digest, which appears verbatim in any token covering that digest
+pub fn message_imprint(digest: &[u8; 32]) -> Vec<u8> {
+    let mut content = SHA256_ALGORITHM.to_vec();
+    content.extend(der(OCTET_STRING, digest));
+    der(SEQUENCE, &content)
+}
+
+/// The DER TimeStampReq (version 1) for a sha256 digest, asking the TSA to include its certificate in the token
+pub fn timestamp_request(digest: &[u8; 32], nonce: u64) -> Vec<u8> {
+    let mut content = der(INTEGER, &[1]);
+    content.extend(message_imprint(digest));
+    content.extend(der(INTEGER, &der_uint(nonce)));
+    content.extend(der(BOOLEAN, &[0xff]));
+    der(SEQUENCE, &content)
+}
+
+/// Split the first DER value from the front of der, returning its tag, its content, and the DER remaining after it
+fn read_der(der: &[u8]) -> Result<(u8, &[u8], &[u8]), TsaError> {
+    let (&tag, rest) = der.split_first().ok_or(TsaError::Malformed("expected a DER value"))?;
+    let (&first, rest) = rest.split_first().ok_or(TsaError::Malformed("truncated DER"))?;
+    let (len, rest) = match first {
+        len if len < 0x80 => (len as usize, rest),
+        0x81..=0x84 => {
+            let n = (first & 0x7f) as usize;
+            if rest.len() < n {
+                return Err(TsaError::Malformed("truncated DER"))
+            }
+            (rest[..n].iter().fold(0usize, |len, &b| (len << 8) | b as usize), &rest[n..])
+        },
+        _ => return Err(TsaError::Malformed("unsupported DER length")),
+    };
+    if rest.len() < len {
+        return Err(TsaError::Malformed("truncated DER"))
+    }
+    Ok((tag, &rest[..len], &rest[len..]))
+}
+
+/// The DER TimeStampToken from a DER TimeStampResp, if the TSA granted one
+pub fn token_from_response(response: &[u8]) -> Result<Vec<u8>, TsaError> {
+    let (tag, resp, _) = read_der(response)?;
+    if tag != SEQUENCE {
+        return Err(TsaError::Malformed("TimeStampResp is not a SEQUENCE"))
+    }
+    let (tag, status_info, token) = read_der(resp)?;
+    let (status_tag, status, _) = read_der(status_info)?;
+    if tag != SEQUENCE || status_tag != INTEGER || status.is_empty() || status.len() > 8 {
+        return Err(TsaError::Malformed("PKIStatusInfo does not start with a PKIStatus"))
+    }
+    let status = status.iter().fold(0i64, |n, &b| (n << 8) | b as i64);
+    if status > 1 {
+        return Err(TsaError::Rejected{status})
+    }
+    let (tag, _, after) = read_der(token).map_err(|_| TsaError::Malformed("the TSA granted the request but sent no token"))?;
+    if tag != SEQUENCE {
+        return Err(TsaError::Malformed("TimeStampToken is not a SEQUENCE"))
+    }
+    Ok(token[..token.len() - after.len()].to_vec())
+}
+
+/// Whether the MessageImprint for the sha256 digest appears anywhere in the bytes of a DER TimeStampToken.
+/// This is a byte search, not verification: the TSTInfo is not parsed, nor the nonce or the TSA's signature checked, 
+/// so a token anyone could have written passes. It only catches a TSA replying with a token for some other hash 
+pub fn token_mentions_digest(token: &[u8], digest: &[u8; 32]) -> bool {
+    let imprint = message_imprint(digest);
+    token.windows(imprint.len()).any(|window| window == imprint.as_slice())
+}
+
+/// POST a DER TimeStampReq to the TSA and return the body of its reply, the DER TimeStampResp.
+/// A TSA which has not replied within TSA_TIMEOUT gives TsaError::Http 
+pub async fn submit(tsa_url: &str, request: Vec<u8>) -> Result<Vec<u8>, TsaError> {
+    let http = |e: reqwest::Error| TsaError::Http(e.to_string());
+    let client = reqwest::Client::builder().timeout(TSA_TIMEOUT).build().map_err(http)?;
+    let response = client.post(tsa_url)
+        .header(reqwest::header::CONTENT_TYPE, "application/timestamp-query")
+        .body(request)
+        .send().await.map_err(http)?;
+    if !response.status().is_success() {
+        return Err(TsaError::Http(format!("{} replied with HTTP {}", tsa_url, response.status())))
+    }
+    Ok(response.bytes().await.map_err(http)?.to_vec())
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::granted_tsa_response as granted_response;
+
+    #[test]
+    fn test_timestamp_request() {
+        let digest = digest_from_hex(&"ab".repeat(32)).unwrap();
+        let request = timestamp_request(&digest, 0x80);
+        assert_eq!(&request[..5], &[0x30, 0x3d, 0x02, 0x01, 0x01]);
+        assert_eq!(&request[5..56], message_imprint(&digest).as_slice());
+        // the nonce has its high bit set so is padded to stay positive, then certReq is TRUE
+        assert_eq!(&request[56..], &[0x02, 0x02, 0x00, 0x80, 0x01, 0x01, 0xff]);
+        assert_eq!(der(OCTET_STRING, &[7; 200])[..3], [0x04, 0x81, 0xc8]);
+        assert_eq!(digest_from_hex("abc"), Err(TsaError::InvalidDigest("abc".to_string())));
+        assert!(digest_from_hex(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn test_token_from_response() {
+        let digest = [1u8; 32];
+        let token = token_from_response(&granted_response(&digest)).unwrap();
+        assert!(token_mentions_digest(&token, &digest));
+        assert!(!token_mentions_digest(&token, &[2u8; 32]));
+        let rejected = der(SEQUENCE, &der(SEQUENCE, &der(INTEGER, &[2])));
+        assert_eq!(token_from_response(&rejected), Err(TsaError::Rejected{status: 2}));
+        let no_token = der(SEQUENCE, &der(SEQUENCE, &der(INTEGER, &[0])));
+        assert!(matches!(token_from_response(&no_token), Err(TsaError::Malformed(_))));
+        let response = granted_response(&digest);
+        assert!(matches!(token_from_response(&response[..response.len() - 1]), Err(TsaError::Malformed(_))));
+        assert!(matches!(token_from_response(b"<html>"), Err(TsaError::Malformed(_))));
+    }
+}
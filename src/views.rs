@@ -2,8 +2,9 @@ use std::{vec::Vec};
 use serde::{Serialize, Deserialize};
 use serde_json;
 use tokio_postgres;
-use pachydurable::{autocomplete::{AutoComp, WhoWhatWhere}, redis::{Cacheable, CachedAutoComp, PreWarmDepth}};
-use crate::{integrity::{XtchdContent, XtchdSQL}, xrows};
+use chrono::{DateTime, offset::Utc};
+use pachydurable::{autocomplete::{AutoComp, WhoWhatWhere}, fulltext::FullText, redis::{Cacheable, CachedAutoComp, PreWarmDepth}};
+use crate::{integrity::{self, Xtchable, XtchdContent, XtchdSQL}, xrows};
 
 
 
@@ -30,7 +31,7 @@ impl AutoComp<String> for Topic {
     fn query_autocomp() ->  &'static str {
         "SELECT tkey, name
         FROM nlp_topics 
-        WHERE ac @@ to_tsquery('simple', $1)
+        WHERE ac @@ prefix_tsquery('simple', $1)
         ORDER BY count DESC 
         LIMIT 10 "
     }
@@ -80,6 +81,17 @@ impl<'a> tokio_postgres::types::FromSql<'a> for Topic {
 
 
 
+/// This struct gives details for one topic, 
+/// listing the articles and videos mentioning it (most mentions first)
+/// It is typically returned when the user clicks on a topic for more information
+#[derive(Serialize)]
+pub struct TopicDetail {
+    pub topic: Topic,
+    pub articles: Vec<NameId>,
+    pub videos: Vec<NameId>,
+}
+
+
 /// This struct captures properties associated with a video node
 #[derive(Serialize, Deserialize)]
 pub struct VideoProps {
@@ -89,8 +101,8 @@ pub struct VideoProps {
 
 #[derive(Serialize, Deserialize)]
 pub struct NameId {
-    id: i32,
-    name: String,
+    pub id: i32,
+    pub name: String,
 }
 
 impl<'a> tokio_postgres::types::FromSql<'a> for NameId {
@@ -108,12 +120,364 @@ impl<'a> tokio_postgres::types::FromSql<'a> for NameId {
 /// It is typically returned when the user clicks on an author for more information
 #[derive(Serialize)]
 pub struct AuthorDetail {
+    /// the author as first written, which is what verifies
     pub author: XtchdContent<xrows::Author>,
+    /// the author's current name, which differs from author.content.name once they have been renamed (see Xtchr::rename_author)
+    pub name: String,
     pub articles: Vec<NameId>,
+    /// Some if the author has been redacted, in which case the name is replaced with xrows::REDACTED
+    pub redacted: Option<xrows::Tombstone>,
+}
+
+
+/// Every name an author has had, as returned by Xtchr::author_history()
+#[derive(Serialize)]
+pub struct AuthorHistory {
+    pub author: XtchdContent<xrows::Author>,
+    /// each rename of the author, oldest first: the last is their current name 
+    pub renames: Vec<XtchdContent<xrows::AuthorName>>,
+}
+
+/// One page of an article as returned by article_detail.
+/// When the page cites a prior xtchd article (PageSrc::Xtchd), that article's title and new_sha256 are included 
+/// so the citation can be rendered (and its hash shown) without a second query.
+/// Likewise the thumbnail is included when the source is a screenshot (PageSrc::WpTxYt)
+#[derive(Serialize)]
+pub struct PageDetail {
+    pub page: XtchdContent<xrows::ArticlePage>,
+    /// the cited article: None unless the source is PageSrc::Xtchd
+    pub cited: Option<NameId>,
+    /// the new_sha256 of the cited article: None unless the source is PageSrc::Xtchd 
+    pub cited_sha256: Option<String>,
+    /// the thumbnail of the screenshot: None unless the source is PageSrc::WpTxYt
+    pub source_image: Option<xrows::ImageThumbnail>,
+}
+
+
+/// This struct gives the title and all pages for one article, 
+/// each wrapped in XtchdContent so they can be independently verified 
+#[derive(Serialize)]
+pub struct ArticleDetail {
+    pub title: XtchdContent<xrows::ArticleTitle>,
+    pub pages: Vec<PageDetail>,
+    /// Some if the article has been redacted, in which case the title is replaced with xrows::REDACTED and no pages are returned 
+    pub redacted: Option<xrows::Tombstone>,
+}
+
+
+
+/// A whole article as one self-contained document, i.e. for archival or sharing: the title, author, 
+/// and latest revision of every page, each independently verifiable, with a doc_sha256 over all of them.
+/// Verify it with integrity::verify_article_document()
+#[derive(Serialize, Deserialize)]
+pub struct ArticleDocument {
+    pub title: XtchdContent<xrows::ArticleTitle>,
+    pub author: XtchdContent<xrows::Author>,
+    pub pages: Vec<XtchdContent<xrows::ArticlePage>>,
+    /// integrity::document_sha256() over the new_sha256 of the title, the author, then each page 
+    pub doc_sha256: String,
+}
+
+impl ArticleDocument {
+    /// The doc_sha256 for the title, author, and pages as they are now 
+    pub fn compute_sha256(&self) -> String {
+        let parts: Vec<&str> = [&self.title.new_sha256, &self.author.new_sha256].into_iter()
+            .chain(self.pages.iter().map(|page| &page.new_sha256))
+            .map(String::as_str).collect();
+        integrity::document_sha256(&parts)
+    }
+}
+
+
+/// One article paragraph matching a full-text search.
+/// The snippet is taken from the plaintext of the paragraph (see xrows::strip_markdown)
+/// so Markdown syntax such as '##' or '[link](url)' doesn't show up in results
+#[derive(Serialize, Deserialize)]
+pub struct ParaSearchResult {
+    pub apara_id: i32,
+    pub art_id: i32,
+    /// the matching portion of the paragraph with search terms wrapped in <b></b>
+    pub snippet: String,
+}
+
+impl FullText for ParaSearchResult {
+    fn query_fulltext() -> &'static str {
+        "SELECT apara_id, art_id, ts_headline('english', plain, prefix_tsquery('english', $1)) AS snippet
+        FROM article_para
+        WHERE ts @@ prefix_tsquery('english', $1)
+        AND NOT redacted('article_para', apara_id) AND NOT redacted('titles_immut', art_id)
+        LIMIT 20;"
+    }
+
+    fn rowfunc_fulltext(row: &tokio_postgres::Row) -> Self {
+        let apara_id: i32 = row.get(0);
+        let art_id: i32 = row.get(1);
+        let snippet: String = row.get(2);
+        ParaSearchResult{apara_id, art_id, snippet}
+    }
 }
 
 
+/// One page of full-text search results from Xtchr::search_paragraphs_page(), best match first 
+#[derive(Serialize, Deserialize)]
+pub struct ParaSearchPage {
+    pub results: Vec<ParaSearchResult>,
+    /// the (rank, apara_id) of the last result, to pass as after_rank for the next page: None if this is the last page
+    pub next: Option<(f32, i32)>,
+}
+
 
+/// The number of rows of each type of content, i.e. for showing "N authors, M articles, K videos" on a dashboard
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ContentCounts {
+    pub authors: i64,
+    pub articles: i64,
+    pub pages: i64,
+    pub paragraphs: i64,
+    pub channels: i64,
+    pub videos: i64,
+    pub images: i64,
+}
 
 
 
+/// The integrity of one table with hash chain integrity, as reported by Xtchr::integrity_status()
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TableStatus {
+    pub name: String,
+    /// the number of rows: counted by a deep check, otherwise the head's id + 1 
+    pub rows: i64,
+    /// the new_sha256 of the latest row: None if the table is empty 
+    pub head_sha256: Option<String>,
+    pub verified: bool,
+    pub last_checked: DateTime<Utc>,
+}
+
+/// The integrity of every table with hash chain integrity, i.e. for a public /integrity endpoint
+#[derive(Serialize, Deserialize, Debug)]
+pub struct IntegrityStatus {
+    pub per_table: Vec<TableStatus>,
+}
+
+
+/// An Ed25519 signature over the head of a table, as written by Xtchr::sign_head(): the message signed binds 
+/// the table and head_id as well as the new_sha256 (see signed_message), so a signature cannot be moved to another row 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HeadSignature {
+    pub table: String,
+    /// the id of the row that was the head of the table when signed 
+    pub head_id: i32,
+    /// the new_sha256 of that row 
+    pub head_sha256: String,
+    /// the 64-byte signature 
+    pub signature: Vec<u8>,
+    /// the 32-byte public key of the signer 
+    pub pubkey: Vec<u8>,
+}
+
+impl HeadSignature {
+    /// The message signed for the head of a table: 'xtchd-head|{table}|{head_id}|{head_sha256}'
+    pub fn signed_message(table: &str, head_id: i32, head_sha256: &str) -> String {
+        format!("xtchd-head|{}|{}|{}", table, head_id, head_sha256)
+    }
+
+    /// Whether the signature is valid for the table, head_id and head_sha256 under the expected key. 
+    /// A signature made with any other key (whatever the pubkey field says), or of the wrong length, is not valid 
+    #[cfg(feature = "signatures")]
+    pub fn verify(&self, expected: &ed25519_dalek::VerifyingKey) -> bool {
+        use ed25519_dalek::{Signature, Verifier};
+        if self.pubkey.as_slice() != expected.as_bytes() {
+            return false
+        }
+        let Ok(signature) = <[u8; 64]>::try_from(self.signature.as_slice()) else {
+            return false
+        };
+        let message = HeadSignature::signed_message(&self.table, self.head_id, &self.head_sha256);
+        expected.verify(message.as_bytes(), &Signature::from_bytes(&signature)).is_ok()
+    }
+}
+
+
+/// An RFC 3161 timestamp token over the new_sha256 of the head of a table, as stored by Xtchr::timestamp_head()
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TsaToken {
+    pub table: String,
+    /// the id of the row that was the head of the table when timestamped 
+    pub head_id: i32,
+    /// the new_sha256 of that row, which is the hash the token covers 
+    pub head_sha256: String,
+    /// the timestamp authority which granted the token 
+    pub tsa_url: String,
+    /// the DER TimeStampToken 
+    pub token: Vec<u8>,
+}
+
+impl TsaToken {
+    /// Whether the head_sha256 appears in the token. This proves nothing about the token: its nonce and the TSA's 
+    /// signature are not checked (see tsa::token_mentions_digest), so verify it with the TSA's certificate chain 
+    #[cfg(feature = "tsa")]
+    pub fn mentions_head(&self) -> bool {
+        match crate::tsa::digest_from_hex(&self.head_sha256) {
+            Ok(digest) => crate::tsa::token_mentions_digest(&self.token, &digest),
+            Err(_) => false,
+        }
+    }
+}
+
+
+/// A row referencing an id that does not exist, as reported by Xtchr::audit_author_articles()
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ReferentialAnomaly {
+    /// the table containing the row with the dangling reference, i.e. 'titles_immut'
+    pub table: String,
+    /// the id of that row 
+    pub id: i32,
+    /// the column holding the reference, i.e. 'auth_id'
+    pub column: String,
+    /// the referenced id, which has no matching row 
+    pub missing_id: i32,
+}
+
+
+/// A row whose prior_sha256 is not the new_sha256 of the row at its prior_id, as reported by Xtchr::audit_prior_links().
+/// Such a row can hash correctly on its own, so this is the check that it is really linked to its prior 
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct LinkAnomaly {
+    /// the table containing the row, i.e. 'authors'
+    pub table: String,
+    /// the id of the row
+    pub id: i32,
+    /// the prior_id of the row: None for the first row, whose prior_sha256 should be GENESIS_SHA256
+    pub prior_id: Option<i32>,
+    /// the prior_sha256 stored in the row
+    pub prior_sha256: String,
+    /// the new_sha256 of the row at prior_id (or GENESIS_SHA256): None if no row has that id 
+    pub expected: Option<String>,
+}
+
+
+/// The classes of content that show up in the recent activity feed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ContentClass {
+    Author,
+    ArticleTitle,
+    YoutubeChannel,
+    YoutubeVideo,
+    Image,
+}
+
+impl ContentClass {
+    /// The dtype of the Xtchable struct for this class, which is also how the class is labeled in SQL
+    pub fn dtype(&self) -> &'static str {
+        match self {
+            ContentClass::Author => <xrows::Author as Xtchable>::dtype(),
+            ContentClass::ArticleTitle => <xrows::ArticleTitle as Xtchable>::dtype(),
+            ContentClass::YoutubeChannel => <xrows::YoutubeChannel as Xtchable>::dtype(),
+            ContentClass::YoutubeVideo => <xrows::YoutubeVideo as Xtchable>::dtype(),
+            ContentClass::Image => <xrows::ImmutableImage as Xtchable>::dtype(),
+        }
+    }
+
+    /// The inverse of .dtype()
+    pub fn from_dtype(dtype: &str) -> Option<Self> {
+        [ContentClass::Author, ContentClass::ArticleTitle, ContentClass::YoutubeChannel, ContentClass::YoutubeVideo, ContentClass::Image]
+            .into_iter().find(|class| class.dtype() == dtype)
+    }
+}
+
+
+/// The result of Xtchr::verify_channel(): the channel's own link in the youtube_channels chain 
+/// and the link of each of its videos in the youtube_videos chain (see Xtchr::verify_row)
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct ChannelVerification {
+    pub chan_id: i32,
+    /// the check of the channel row itself 
+    pub channel: integrity::RowVerification,
+    /// the number of videos of the channel that were checked
+    pub videos_checked: usize,
+    /// the vid_id of each video that failed verification, in ascending order 
+    pub failed_videos: Vec<i32>,
+}
+
+impl ChannelVerification {
+    /// True only if the channel and all of its videos passed 
+    pub fn is_valid(&self) -> bool {
+        self.channel.is_valid() && self.failed_videos.is_empty()
+    }
+}
+
+
+/// One item in the recent activity feed, i.e. something recently etched 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ActivityItem {
+    pub class: ContentClass,
+    /// the id within the table for this class, i.e. auth_id for an Author 
+    pub id: i32,
+    /// the name, title, or alt text
+    pub name: String,
+    pub write_timestamp: DateTime<Utc>,
+}
+
+impl ActivityItem {
+    /// The cursor to pass to Xtchr::recent_activity() for the items after this one 
+    pub fn cursor(&self) -> ActivityCursor {
+        ActivityCursor{write_timestamp: self.write_timestamp, class: self.class, id: self.id}
+    }
+}
+
+/// A position in the recent activity feed (see ActivityItem::cursor). Items are ordered by write_timestamp, 
+/// then class and id, so items written at the same instant (i.e. in different tables) are neither skipped nor repeated across pages 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActivityCursor {
+    pub write_timestamp: DateTime<Utc>,
+    pub class: ContentClass,
+    pub id: i32,
+}
+
+
+/// The result of Xtchr::import_channel_videos()
+#[derive(Serialize)]
+pub struct ImportSummary {
+    /// the videos written, in the order they were chained
+    pub imported: Vec<xrows::YoutubeVideo>,
+    /// the vid_pk of each video that already existed (or was repeated in the import) and so was skipped
+    pub skipped: Vec<String>,
+}
+
+
+/// One paragraph of a video transcript, i.e. a full-text search hit within a video 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TranscriptPara {
+    /// the YouTube video id, i.e. the 'v' query parameter in a watch URL
+    pub vid_pk: String,
+    /// seconds from the start of the video at which this paragraph is spoken
+    pub timestamp: f32,
+    pub text: String,
+}
+
+impl TranscriptPara {
+    /// A link to the video starting at this paragraph, rounded down to the whole second
+    pub fn embed_url(&self) -> String {
+        format!("https://www.youtube.com/watch?v={}&t={}s", self.vid_pk, self.timestamp.max(0.0).floor() as u32)
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript_para(timestamp: f32) -> TranscriptPara {
+        TranscriptPara{vid_pk: "dQw4w9WgXcQ".to_string(), timestamp, text: "Never gonna give you up".to_string()}
+    }
+
+    #[test]
+    fn test_embed_url() {
+        assert_eq!(transcript_para(0.0).embed_url(), "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=0s");
+        assert_eq!(transcript_para(65.9).embed_url(), "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=65s");
+        let url = transcript_para(12.0).embed_url();
+        assert!(url.starts_with("https://www.youtube.com/watch?v=dQw4w9WgXcQ&"));
+    }
+
+}
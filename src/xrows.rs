@@ -4,22 +4,160 @@
 //! and implement serialization to aid in passing them over http. 
 
 use std::fmt;
+use bytes::{BufMut, BytesMut};
 use chrono::NaiveDate;
 use serde::{Serialize, Deserialize};
 use serde_json;
 use tokio_postgres;
 use pachydurable::{autocomplete::{AutoComp, WhoWhatWhere}, fulltext::FullText, redis::{CachedAutoComp, PreWarmDepth}};
-use crate::integrity::{Xtchable, nonefmt};
+use crate::integrity::{Xtchable, ToRow, nonefmt, sha256};
 
 
 
 
 
+/// An XrowError indicates content that cannot be written because it fails validation 
+#[derive(Debug, PartialEq)]
+pub enum XrowError {
+    /// The decoded size of an image exceeds the configured limit (see ImageLimits)
+    ImageTooLarge{bytes: usize, limit: usize},
+    /// Exactly one of img_id, image_file, and refs_a_id_immut must be set for a page
+    InvalidPageSrc{columns_set: usize},
+    /// A draft id (or MutableImage id) does not have the number of characters of its CHAR(n) column
+    InvalidDraftId{expected: usize, got: usize},
+    /// A YouTube video id is not 11 characters of [A-Za-z0-9_-], i.e. a full URL was given instead of the id
+    InvalidVidPk{vid_pk: String},
+    /// A text search configuration name is not one of TsConfig
+    UnknownTsConfig{name: String},
+    /// An ArticlePageBuilder is missing a required field, i.e. 'paragraphs'
+    IncompletePage{missing: &'static str},
+    /// An image is SVG, which is rejected because it can contain script
+    SvgRejected,
+    /// An image does not start with the magic bytes of any ImgFormat
+    UnknownImageFormat,
+    /// A write violates a unique constraint, i.e. an author with this name already exists
+    /// (but not the position of a row in a chain: see XtchdError::ChainContended)
+    Conflict{constraint: String},
+    /// An image is not valid base64, or its bytes could not be decoded as an image 
+    UndecodableImage,
+    /// A page has more paragraphs, or a paragraph more characters, than the configured limit (see PageLimits)
+    PageTooLarge{what: &'static str, size: usize, limit: usize},
+    /// A video upload date is in neither YYYY-MM-DD nor YYYYMMDD form, or is not a real date (see parse_upload_date)
+    InvalidUploadDate{date: String},
+    /// An image source starts with "data:" but not with a MIME type and ";base64," (see ImagePair::canonical_data_uri)
+    InvalidDataUri{prefix: String},
+}
+
+impl fmt::Display for XrowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XrowError::ImageTooLarge{bytes, limit} => write!(f, "image is {} bytes, exceeding the limit of {} bytes", bytes, limit),
+            XrowError::InvalidPageSrc{columns_set} => write!(f, "exactly one page source column must be set, found {}", columns_set),
+            XrowError::InvalidDraftId{expected, got} => write!(f, "id must be {} characters, found {}", expected, got),
+            XrowError::InvalidVidPk{vid_pk} => write!(f, "'{}' is not an 11-character YouTube video id", vid_pk),
+            XrowError::UnknownTsConfig{name} => write!(f, "'{}' is not a supported text search configuration", name),
+            XrowError::IncompletePage{missing} => write!(f, "an ArticlePage requires {}", missing),
+            XrowError::SvgRejected => write!(f, "SVG images are not accepted as they can contain script"),
+            XrowError::UnknownImageFormat => write!(f, "the image is not PNG, JPEG, WebP, or AVIF"),
+            XrowError::Conflict{constraint} => write!(f, "a row already exists violating the unique constraint '{}'", constraint),
+            XrowError::UndecodableImage => write!(f, "the image could not be decoded"),
+            XrowError::PageTooLarge{what, size, limit} => write!(f, "a page has {} {}, exceeding the limit of {}", size, what, limit),
+            XrowError::InvalidUploadDate{date} => write!(f, "'{}' is not an upload date in YYYY-MM-DD or YYYYMMDD form", date),
+            XrowError::InvalidDataUri{prefix} => write!(f, "'{}' is not a data URI prefix of the form data:image/png;base64,", prefix),
+        }
+    }
+}
+
+impl std::error::Error for XrowError {}
+
+
+/// The Postgres text search configurations (see pg_ts_config) that may be used for full-text search.
+/// Only these can be passed to Postgres, so a configuration name from user input is never interpolated into SQL
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum TsConfig {
+    /// no stemming or stop words: used for autocomplete 
+    Simple,
+    #[default]
+    English,
+    Spanish,
+    German,
+    French,
+    Italian,
+    Portuguese,
+    Dutch,
+    Russian,
+}
+
+impl TsConfig {
+    const ALL: [TsConfig; 9] = [TsConfig::Simple, TsConfig::English, TsConfig::Spanish, TsConfig::German, TsConfig::French, 
+        TsConfig::Italian, TsConfig::Portuguese, TsConfig::Dutch, TsConfig::Russian];
+
+    /// The name of the configuration in Postgres, i.e. 'english'
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TsConfig::Simple => "simple",
+            TsConfig::English => "english",
+            TsConfig::Spanish => "spanish",
+            TsConfig::German => "german",
+            TsConfig::French => "french",
+            TsConfig::Italian => "italian",
+            TsConfig::Portuguese => "portuguese",
+            TsConfig::Dutch => "dutch",
+            TsConfig::Russian => "russian",
+        }
+    }
+
+    /// Parse the name of a configuration, i.e. 'Spanish' or 'spanish'
+    pub fn parse(name: &str) -> Result<Self, XrowError> {
+        let lower = name.to_lowercase();
+        TsConfig::ALL.into_iter().find(|config| config.as_str() == lower)
+            .ok_or_else(|| XrowError::UnknownTsConfig{name: name.to_string()})
+    }
+}
+
+
+/// How autocomplete results are ordered (see Xtchr::autocomplete_authors)
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum AutoCompOrder {
+    /// shortest name first, so the closest match to a short phrase comes first
+    #[default]
+    Length,
+    /// most cited first (see the author_popularity table), then shortest name first 
+    Popularity,
+}
+
+
+/// The length of the CHAR(21) nanoIDs used for a_id_draft and p_id_draft 
+pub const DRAFT_ID_LEN: usize = 21;
+/// The length of the CHAR(16) nanoIDs used for MutableImage.id
+pub const MUTABLE_IMAGE_ID_LEN: usize = 16;
+
+/// Generate a new draft id, i.e. a 21-character nanoID from the URL-safe alphabet [A-Za-z0-9_-].
+/// This is used by Xtchr::add_article_title and add_article_page when no draft id is provided 
+pub fn new_draft_id() -> String {
+    nanoid::nanoid!(DRAFT_ID_LEN)
+}
+
+/// Confirm an id has exactly the number of characters of its CHAR(n) column,
+/// as Postgres would otherwise reject (or pad) it with a less helpful error 
+pub fn check_draft_id(id: &str, expected: usize) -> Result<(), XrowError> {
+    let got = id.chars().count();
+    match got == expected {
+        true => Ok(()),
+        false => Err(XrowError::InvalidDraftId{expected, got}),
+    }
+}
+
+
+
 /// The PageSrc enum gives the various sources that can be used for a page 
 /// Recall that the ArticlePage is a struct designed to be written but not read- 
 /// This is reflected in the fact that Webpage, TwitterX, and YouTube sourcs all get lumped into
 /// the WpTxYt struct which simply contains an img_id. 
-/// On read, the src_type is inferred from the images table 
+/// On read, the src_type is inferred from the images_immut table 
+/// It is serialized with a tag, i.e. {"type": "Xtchd", "value": 3}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
 pub enum PageSrc {
     /// The page is the arthors's opinion, perhaps a preamble or conclusion.
     /// It contains a string referencing an image_file, typically a 'splash' page for the article 
@@ -45,10 +183,105 @@ impl PageSrc {
         }
         (img_id, image_file, refs_a_id_immut)
     }
+
+    /// The inverse of src_columns(): rebuild the PageSrc from the columns of an article_pages_immut row.
+    /// Exactly one of the columns must be set, otherwise XrowError::InvalidPageSrc is returned 
+    pub fn from_columns(img_id: Option<i32>, image_file: Option<String>, refs_a_id_immut: Option<i32>) -> Result<Self, XrowError> {
+        match (img_id, image_file, refs_a_id_immut) {
+            (None, Some(image_file), None) => Ok(PageSrc::Author(image_file)),
+            (None, None, Some(a_id_immut)) => Ok(PageSrc::Xtchd(a_id_immut)),
+            (Some(img_id), None, None) => Ok(PageSrc::WpTxYt(img_id)),
+            (img_id, image_file, refs_a_id_immut) => {
+                let columns_set = img_id.is_some() as usize + image_file.is_some() as usize + refs_a_id_immut.is_some() as usize;
+                Err(XrowError::InvalidPageSrc{columns_set})
+            },
+        }
+    }
+}
+
+
+/// Read one length-prefixed value from the binary encoding of a Postgres composite 
+fn read_i32(buf: &mut &[u8]) -> Result<i32, Box<dyn std::error::Error + Sync + Send>> {
+    if buf.len() < 4 {
+        return Err("truncated page_src composite".into())
+    }
+    let (head, rest) = buf.split_at(4);
+    *buf = rest;
+    Ok(i32::from_be_bytes(head.try_into()?))
+}
+
+
+/// PageSrc is read from the page_src composite type (see public.sql), i.e.
+/// SELECT ROW(img_id, image_file, refs_a_id_immut)::page_src FROM pages_immut 
+/// so the invariant that exactly one column is set is checked in from_columns() on every read 
+impl<'a> tokio_postgres::types::FromSql<'a> for PageSrc {
+    fn from_sql(_ty: &tokio_postgres::types::Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let mut buf = raw;
+        let count = read_i32(&mut buf)?;
+        let mut fields: Vec<Option<&[u8]>> = Vec::new();
+        for _ in 0..count {
+            let _oid = read_i32(&mut buf)?;
+            let len = read_i32(&mut buf)?;
+            if len < 0 {
+                fields.push(None); // NULL 
+                continue;
+            }
+            if buf.len() < len as usize {
+                return Err("truncated page_src composite".into())
+            }
+            let (val, rest) = buf.split_at(len as usize);
+            fields.push(Some(val));
+            buf = rest;
+        }
+        if fields.len() != 3 {
+            return Err(format!("page_src composite has {} fields, expected 3", fields.len()).into())
+        }
+        let int4 = |field: Option<&[u8]>| field.map(|mut val| read_i32(&mut val)).transpose();
+        let img_id = int4(fields[0])?;
+        let image_file = fields[1].map(|val| String::from_utf8(val.to_vec())).transpose()?;
+        let refs_a_id_immut = int4(fields[2])?;
+        Ok(PageSrc::from_columns(img_id, image_file, refs_a_id_immut)?)
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+}
+
+
+/// PageSrc is written as the page_src composite type, using the same columns as src_columns()
+impl tokio_postgres::types::ToSql for PageSrc {
+    fn to_sql(&self, _ty: &tokio_postgres::types::Type, out: &mut BytesMut) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        let (img_id, image_file, refs_a_id_immut) = self.src_columns();
+        let int4 = tokio_postgres::types::Type::INT4.oid();
+        let varchar = tokio_postgres::types::Type::VARCHAR.oid();
+        out.put_i32(3);
+        for (oid, val) in [
+                (int4, img_id.map(|v| v.to_be_bytes().to_vec())), 
+                (varchar, image_file.map(String::into_bytes)), 
+                (int4, refs_a_id_immut.map(|v| v.to_be_bytes().to_vec()))] {
+            out.put_u32(oid);
+            match val {
+                Some(bytes) => {
+                    out.put_i32(bytes.len() as i32);
+                    out.put_slice(&bytes);
+                },
+                None => out.put_i32(-1), // NULL
+            }
+        }
+        Ok(tokio_postgres::types::IsNull::No)
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+
+    tokio_postgres::types::to_sql_checked!();
 }
 
 
 /// The ArticlePage struct captures the text and image for one page of one article 
+#[derive(Serialize, Deserialize)]
 pub struct ArticlePage {
     /// the id for the article this page is associated with 
     pub a_id_immut: i32, 
@@ -63,13 +296,38 @@ pub struct ArticlePage {
     pub paragraphs: Vec<String>,
     /// The source descibes where the information from the page was taken from 
     pub source: PageSrc,
+    /// The p_id_immut of the page this is a revision of (within the same article), or None for the first revision 
+    #[serde(default)]
+    pub supersedes: Option<i32>,
+    /// which state_string the page was hashed with (see PAGE_HASH_V), as stored in the hash_v column of pages_immut
+    #[serde(default = "page_hash_v")]
+    pub hash_v: i16,
+}
+
+/// Pages written before the hash_v column were hashed with their paragraphs joined by ' | ' 
+pub const PAGE_HASH_V_JOINED: i16 = 1;
+/// The version of ArticlePage::state_string used for new pages: the paragraphs are a JSON array 
+pub const PAGE_HASH_V: i16 = 2;
+
+fn page_hash_v() -> i16 {
+    PAGE_HASH_V
 }
 
 impl Xtchable for ArticlePage {
+    /// From PAGE_HASH_V, the paragraphs are encoded as a JSON array so that a paragraph containing a delimiter
+    /// cannot be confused with two paragraphs (i.e. ["a | b"] and ["a", "b"] have different state strings).
+    /// This matches ARRAY_TO_JSON(paragraphs)::VARCHAR in the page_verify_sha256 constraint, as Postgres and serde_json
+    /// both emit compact JSON with the same escaping. Pages written before then (PAGE_HASH_V_JOINED) still hash as written, so they still verify.
+    /// ' supersedes=' is only included for a revision, so the state string of a first revision is unchanged from before revisions existed
     fn state_string(&self) -> String {
         let (img_id, image_file, refs_a_id_immut) = &self.source.src_columns();
-        format!("a_id_immut={} p_id_immut={} paragraphs={} img_id={} image_file={} refs_a_id_immut={}",
-            &self.a_id_immut, &self.p_id_immut, &self.paragraphs.join(" | "), nonefmt(&img_id), nonefmt(&image_file), nonefmt(&refs_a_id_immut))
+        let paragraphs = match self.hash_v {
+            PAGE_HASH_V_JOINED => self.paragraphs.join(" | "),
+            _ => serde_json::to_string(&self.paragraphs).unwrap(), // serializing a Vec<String> cannot fail
+        };
+        let supersedes = self.supersedes.map_or(String::new(), |p_id| format!(" supersedes={}", p_id));
+        format!("a_id_immut={} p_id_immut={} paragraphs={} img_id={} image_file={} refs_a_id_immut={}{}",
+            &self.a_id_immut, &self.p_id_immut, &paragraphs, nonefmt(&img_id), nonefmt(&image_file), nonefmt(&refs_a_id_immut), &supersedes)
     }
 
     fn dtype() -> &'static str {
@@ -78,11 +336,217 @@ impl Xtchable for ArticlePage {
 }
 
 impl ArticlePage {
+    /// Build an ArticlePage by named fields rather than positionally: see ArticlePageBuilder 
+    pub fn builder() -> ArticlePageBuilder {
+        ArticlePageBuilder::default()
+    }
+
     pub fn prior_id(&self) -> i32 {
         self.p_id_immut - 1
     }
+
+    /// Confirm the number of paragraphs, and the number of characters in each, are within the limits
+    pub fn check_size(&self, limits: &PageLimits) -> Result<(), XrowError> {
+        if self.paragraphs.len() > limits.max_paragraphs {
+            return Err(XrowError::PageTooLarge{what: "paragraphs", size: self.paragraphs.len(), limit: limits.max_paragraphs})
+        }
+        match self.paragraphs.iter().map(|para| para.chars().count()).max() {
+            Some(chars) if chars > limits.max_paragraph_chars => 
+                Err(XrowError::PageTooLarge{what: "characters in a paragraph", size: chars, limit: limits.max_paragraph_chars}),
+            _ => Ok(()),
+        }
+    }
+
+    /// Render the paragraphs as HTML, each escaped and wrapped in <p></p>, in order  
+    pub fn to_html(&self) -> String {
+        self.paragraphs.iter()
+            .map(|para| format!("<p>{}</p>", escape_html(para)))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+
+/// A builder for an ArticlePage, which requires a_id_immut, at least one paragraph, and a source.
+/// The p_id_draft is generated with new_draft_id() unless given, and p_id_immut is left as 0 
+/// as it is only known when the page is written (see Xtchr::add_article_page)
+#[derive(Default)]
+pub struct ArticlePageBuilder {
+    a_id_immut: Option<i32>,
+    p_id_draft: Option<String>,
+    paragraphs: Vec<String>,
+    source: Option<PageSrc>,
+    supersedes: Option<i32>,
+}
+
+impl ArticlePageBuilder {
+    /// the article this page belongs to 
+    pub fn a_id_immut(mut self, a_id_immut: i32) -> Self {
+        self.a_id_immut = Some(a_id_immut);
+        self
+    }
+
+    pub fn p_id_draft(mut self, p_id_draft: &str) -> Self {
+        self.p_id_draft = Some(p_id_draft.to_string());
+        self
+    }
+
+    /// set all the paragraphs, replacing any already added 
+    pub fn paragraphs(mut self, paragraphs: Vec<String>) -> Self {
+        self.paragraphs = paragraphs;
+        self
+    }
+
+    /// add one paragraph after any already added 
+    pub fn paragraph(mut self, paragraph: &str) -> Self {
+        self.paragraphs.push(paragraph.to_string());
+        self
+    }
+
+    pub fn source(mut self, source: PageSrc) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// the p_id_immut of the page this revises 
+    pub fn supersedes(mut self, p_id_immut: i32) -> Self {
+        self.supersedes = Some(p_id_immut);
+        self
+    }
+
+    pub fn build(self) -> Result<ArticlePage, XrowError> {
+        let a_id_immut = self.a_id_immut.ok_or(XrowError::IncompletePage{missing: "a_id_immut"})?;
+        if self.paragraphs.is_empty() {
+            return Err(XrowError::IncompletePage{missing: "paragraphs"})
+        }
+        let source = self.source.ok_or(XrowError::IncompletePage{missing: "source"})?;
+        let p_id_draft = self.p_id_draft.unwrap_or_else(new_draft_id);
+        check_draft_id(&p_id_draft, DRAFT_ID_LEN)?;
+        Ok(ArticlePage{a_id_immut, p_id_draft, p_id_immut: 0, paragraphs: self.paragraphs, source, supersedes: self.supersedes, hash_v: PAGE_HASH_V})
+    }
+}
+
+
+/// Escape the characters that are significant in HTML so plaintext can be safely rendered 
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+
+/// Strip Markdown syntax from text, leaving the words a reader would see.
+/// Headings, blockquotes, list markers, emphasis and code fences are removed and links/images
+/// are replaced by their text. This is used to build the full-text index and search snippets for 
+/// ArticlePara rows, while the original Markdown is what gets stored (and hashed).
+pub fn strip_markdown(md: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in md.lines() {
+        let mut text = line.trim_start();
+        if text.starts_with("```") || text.starts_with("~~~") {
+            // the fence itself is dropped but the code within it is kept verbatim
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_string());
+            continue;
+        }
+        let hashes = text.len() - text.trim_start_matches('#').len();
+        if hashes > 0 && hashes <= 6 && text[hashes..].starts_with(' ') {
+            text = text[hashes..].trim_start();
+        }
+        while let Some(rest) = text.strip_prefix('>') {
+            text = rest.trim_start();
+        }
+        for marker in ["- ", "* ", "+ "] {
+            if let Some(rest) = text.strip_prefix(marker) {
+                text = rest;
+                break;
+            }
+        }
+        let stripped = strip_inline_markdown(text);
+        if !stripped.trim().is_empty() {
+            lines.push(stripped.trim_end().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Strip the inline Markdown syntax (links, images, emphasis, code spans) from one line
+fn strip_inline_markdown(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let find = |from: usize, target: char| chars[from..].iter().position(|&ch| ch == target).map(|pos| pos + from);
+    let mut out = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            // ![alt](url) is treated like a link, leaving the alt text
+            '!' if chars.get(i+1) == Some(&'[') => {},
+            '[' => {
+                // [text](url) becomes text
+                let link = find(i+1, ']')
+                    .filter(|&close| chars.get(close+1) == Some(&'('))
+                    .and_then(|close| find(close+2, ')').map(|paren| (close, paren)));
+                match link {
+                    Some((close, paren)) => {
+                        let text: String = chars[i+1..close].iter().collect();
+                        out.push_str(&strip_inline_markdown(&text));
+                        i = paren;
+                    },
+                    None => out.push('['),
+                }
+            },
+            '*' | '`' => {},
+            '~' | '_' if chars.get(i+1) == Some(&chars[i]) => { i += 1; },
+            ch => out.push(ch),
+        }
+        i += 1;
+    }
+    out
+}
+
+
+/// An ArticlePara is one paragraph of an article as written by the author in Markdown 
+#[derive(Serialize, Deserialize)]
+pub struct ArticlePara {
+    /// the globally unique id for this paragraph
+    pub apara_id: i32,
+    /// the id for the article this paragraph belongs to 
+    pub art_id: i32,
+    /// Markdown for this paragraph as provided by the author
+    pub md: String,
+}
+
+impl Xtchable for ArticlePara {
+    fn state_string(&self) -> String {
+        format!("apara_id={} art_id={} md={}", &self.apara_id, &self.art_id, &self.md)
+    }
+    fn dtype() -> &'static str {
+        "ArticlePara"
+    }
 }
 
+impl ArticlePara {
+    /// The paragraph with Markdown syntax removed, which is what gets indexed for full-text search 
+    pub fn plaintext(&self) -> String {
+        strip_markdown(&self.md)
+    }
+}
+
+
+/// The name of the genesis author, auth_id 0, which is the first row of the authors chain (see Xtchr::ensure_genesis)
+pub const GENESIS_AUTHOR: &str = "Xtchd Admins";
 
 #[derive(Serialize, Deserialize)]
 pub struct Author {
@@ -99,12 +563,28 @@ impl Xtchable for Author {
     }
 }
 
+impl ToRow for Author {
+    fn id_column() -> &'static str {
+        "auth_id"
+    }
+    fn columns() -> &'static [&'static str] {
+        &["auth_id", "name"]
+    }
+    fn values(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![&self.auth_id, &self.name]
+    }
+    fn set_id(&mut self, id: i32) {
+        self.auth_id = id;
+    }
+}
+
 impl AutoComp<i32> for Author {
     fn query_autocomp() ->  & 'static str {
         "SELECT auth_id, name  
-        FROM authors
-        WHERE ac @@ to_tsquery('simple', $1)
+        FROM author_current
+        WHERE ac @@ prefix_tsquery('simple', $1)
         AND LOWER(name) LIKE '%' || LOWER($2) || '%'
+        AND NOT redacted('authors', auth_id)
         ORDER BY LENGTH(name) ASC 
         LIMIT 10;"
     }
@@ -130,6 +610,40 @@ impl CachedAutoComp<i32> for Author {
 }
 
 
+/// A new name for an author, appended to the author_names chain rather than rewriting the author (see Xtchr::rename_author).
+/// An author's current name is that of their latest AuthorName, or their Author.name if they have never been renamed 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuthorName {
+    pub name_id: i32,   // the primary key for this rename
+    pub auth_id: i32,   // the author being renamed
+    pub name: String,
+}
+
+impl Xtchable for AuthorName {
+    fn state_string(&self) -> String {
+        format!("name_id={} auth_id={} name={}", self.name_id, self.auth_id, &self.name)
+    }
+    fn dtype() -> &'static str {
+        "AuthorName"
+    }
+}
+
+impl ToRow for AuthorName {
+    fn id_column() -> &'static str {
+        "name_id"
+    }
+    fn columns() -> &'static [&'static str] {
+        &["name_id", "auth_id", "name"]
+    }
+    fn values(&self) -> Vec<&(dyn tokio_postgres::types::ToSql + Sync)> {
+        vec![&self.name_id, &self.auth_id, &self.name]
+    }
+    fn set_id(&mut self, id: i32) {
+        self.name_id = id;
+    }
+}
+
+
 /// The ArticleTitle shows the title of an article
 #[derive(Serialize, Deserialize)]
 pub struct ArticleTitle {
@@ -175,6 +689,38 @@ impl Xtchable for YoutubeChannel {
 
 
 
+/// Confirm a YouTube video id is exactly 11 characters of [A-Za-z0-9_-], as stored in the CHAR(11) vid_pk column 
+pub fn check_vid_pk(vid_pk: &str) -> Result<(), XrowError> {
+    let valid = vid_pk.len() == 11 && vid_pk.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match valid {
+        true => Ok(()),
+        false => Err(XrowError::InvalidVidPk{vid_pk: vid_pk.to_string()}),
+    }
+}
+
+
+/// Parse a video upload date for YoutubeVideo.date_uploaded, as either YYYY-MM-DD (i.e. from the YouTube Data API)
+/// or YYYYMMDD (i.e. the upload_date from yt-dlp). Surrounding whitespace is ignored 
+pub fn parse_upload_date(date: &str) -> Result<NaiveDate, XrowError> {
+    let trimmed = date.trim();
+    let format = match trimmed.len() {
+        8 => "%Y%m%d",
+        10 => "%Y-%m-%d",
+        _ => return Err(XrowError::InvalidUploadDate{date: date.to_string()}),
+    };
+    NaiveDate::parse_from_str(trimmed, format).map_err(|_| XrowError::InvalidUploadDate{date: date.to_string()})
+}
+
+
+/// One video from a channel listing, to be written with Xtchr::import_channel_videos() 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VideoImport {
+    pub vid_pk: String,     // the CHAR(11) url/id for this video 
+    pub title: String,
+    pub date_uploaded: NaiveDate,
+}
+
+
 #[derive(Serialize, Deserialize)]
 pub struct YoutubeVideo {
     pub chan_id: i32,       // The id for the channel,
@@ -193,7 +739,7 @@ impl Xtchable for YoutubeVideo {
     }
 }
 
-/// Images can be saved to either the images table (where they are immutable and have a sha256 value calculated)
+/// Images can be saved to either the images_immut table (where they are immutable and have a sha256 value calculated)
 /// or the images_mut table(where they are mutable and have not sha256 calculated).
 /// In either case, they are provided as both a full image and a thumbnail, with a 
 /// src/caption value and optional URL where they came from 
@@ -213,6 +759,303 @@ pub struct ImagePair {
 
 
 
+impl ImagePair {
+    /// The canonical form of an image source, so the same image hashes the same however a client wrote it:
+    /// a data URI prefix is lowercased with whitespace removed (i.e. "DATA:Image/PNG; Base64," becomes "data:image/png;base64,")
+    /// and all whitespace is removed from the base64. A bare base64 source (with no "data:" prefix) only has whitespace removed. 
+    /// Returns XrowError::InvalidDataUri for a data URI without a MIME type and ";base64,".
+    /// Images are canonicalized when written (see Xtchr::add_image_immutable and Xtchr::add_image_mutable), but rows written 
+    /// before that were stored as given: re-upsert images_mut rows to migrate them. images_immut rows cannot be rewritten without 
+    /// breaking the chain, so they keep (and verify against) the source as first written 
+    pub fn canonical_data_uri(src: &str) -> Result<String, XrowError> {
+        let src = src.trim();
+        let (prefix, b64) = match src.get(..5).is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:")) {
+            true => src.split_once(',').ok_or_else(|| XrowError::InvalidDataUri{prefix: src.chars().take(64).collect()})?,
+            false => ("", src),
+        };
+        let mut canonical: String = prefix.chars().filter(|c| !c.is_ascii_whitespace()).collect::<String>().to_ascii_lowercase();
+        if !canonical.is_empty() {
+            let mime = canonical.strip_prefix("data:").and_then(|header| header.strip_suffix(";base64")).unwrap_or("");
+            let valid = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+            if !matches!(mime.split_once('/'), Some((kind, subtype)) if valid(kind) && valid(subtype)) {
+                return Err(XrowError::InvalidDataUri{prefix: prefix.to_string()})
+            }
+            canonical.push(',');
+        }
+        canonical.extend(b64.chars().filter(|c| !c.is_ascii_whitespace()));
+        Ok(canonical)
+    }
+
+    /// This pair with the full image and thumbnail in canonical form (see canonical_data_uri)
+    pub fn canonicalized(self) -> Result<Self, XrowError> {
+        let src_full = ImagePair::canonical_data_uri(&self.src_full)?;
+        let src_thmb = ImagePair::canonical_data_uri(&self.src_thmb)?;
+        Ok(ImagePair{src_full, src_thmb, ..self})
+    }
+
+    /// Reject the pair if the full image or thumbnail is SVG (see reject_svg)
+    pub fn check_format(&self) -> Result<(), XrowError> {
+        reject_svg(&self.src_full)?;
+        reject_svg(&self.src_thmb)
+    }
+
+    /// Confirm the decoded size of the full image and thumbnail are within the limits
+    pub fn check_size(&self, limits: &ImageLimits) -> Result<(), XrowError> {
+        for (src, limit) in [(&self.src_full, limits.max_full_bytes), (&self.src_thmb, limits.max_thmb_bytes)] {
+            let bytes = decoded_len(src);
+            if bytes > limit {
+                return Err(XrowError::ImageTooLarge{bytes, limit})
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// The number of bytes a base64 image source decodes to, i.e. "data:image/png;base64, iVBORw0KGgoA..."
+/// This is calculated from the length of the base64 text so the image need not actually be decoded
+pub fn decoded_len(src: &str) -> usize {
+    let b64 = match src.starts_with("data:") {
+        true => src.split_once(',').map(|(_prefix, b64)| b64).unwrap_or(""),
+        false => src,
+    };
+    let chars = b64.bytes().filter(|b| !b.is_ascii_whitespace()).count();
+    let padding = b64.bytes().rev().filter(|b| !b.is_ascii_whitespace()).take_while(|&b| b == b'=').count();
+    (chars * 3 / 4).saturating_sub(padding)
+}
+
+
+/// The image formats accepted for full images and thumbnails, detected from the magic bytes at the start of the image 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ImgFormat {
+    Png,
+    Jpeg,
+    WebP,
+    Avif,
+}
+
+impl ImgFormat {
+    /// The MIME type, i.e. for the prefix of a data URI 
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ImgFormat::Png => "image/png",
+            ImgFormat::Jpeg => "image/jpeg",
+            ImgFormat::WebP => "image/webp",
+            ImgFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// Detect the format of a (decoded) image from its magic bytes. 
+/// SVG is rejected with XrowError::SvgRejected rather than XrowError::UnknownImageFormat, as it is a text format 
+/// which can embed script, so it is never accepted however it is labeled 
+pub fn detect_img_format(bytes: &[u8]) -> Result<ImgFormat, XrowError> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Ok(ImgFormat::Png)
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Ok(ImgFormat::Jpeg)
+    }
+    if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return Ok(ImgFormat::WebP)
+    }
+    // an ISO base media file whose major brand is AVIF (a still image) or AVIS (an image sequence) 
+    if bytes.len() >= 12 && &bytes[4..8] == b"ftyp" && (&bytes[8..12] == b"avif" || &bytes[8..12] == b"avis") {
+        return Ok(ImgFormat::Avif)
+    }
+    // SVG may start with a BOM, whitespace, an XML declaration, or comments before the <svg> element 
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_ascii_lowercase();
+    let text = head.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with('<') && text.contains("<svg") {
+        return Err(XrowError::SvgRejected)
+    }
+    Err(XrowError::UnknownImageFormat)
+}
+
+
+/// Return XrowError::SvgRejected for a base64 image source (in canonical form, see ImagePair::canonical_data_uri) 
+/// which is SVG, whether by its data URI's MIME type or by its bytes (see detect_img_format). 
+/// Formats which detect_img_format() does not recognize are not rejected, as images are stored as given 
+pub fn reject_svg(src: &str) -> Result<(), XrowError> {
+    let (prefix, b64) = src.split_once(',').filter(|(prefix, _)| prefix.starts_with("data:")).unwrap_or(("", src));
+    if prefix.contains("svg") {
+        return Err(XrowError::SvgRejected)
+    }
+    match detect_img_format(&decoded_head(b64, 1024)) {
+        Err(XrowError::SvgRejected) => Err(XrowError::SvgRejected),
+        _ => Ok(()),
+    }
+}
+
+/// Up to the first max_bytes bytes that base64 text decodes to, without decoding the rest (i.e. to detect its format).
+/// Decoding stops at the first character which is not base64, such as padding 
+fn decoded_head(b64: &str, max_bytes: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(max_bytes);
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for c in b64.bytes() {
+        let val = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => break,
+        };
+        acc = (acc << 6) | val as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+            if bytes.len() == max_bytes {
+                break
+            }
+        }
+    }
+    bytes
+}
+
+
+/// Split a base64 data URI (i.e. "data:image/png;base64,iVBORw0KGgoA...") into its prefix (up to and including the comma) 
+/// and the decoded bytes, so the image can be stored as binary. Returns None unless re-encoding the bytes gives back 
+/// exactly the same source (i.e. there is no whitespace), as the source read back must hash the same as the source written
+#[cfg(feature = "compress-images")]
+pub fn split_data_uri(src: &str) -> Option<(&str, Vec<u8>)> {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    if !src.starts_with("data:") {
+        return None
+    }
+    let (prefix, b64) = src.split_at(src.find(";base64,")? + ";base64,".len());
+    let bytes = STANDARD.decode(b64).ok()?;
+    match STANDARD.encode(&bytes) == b64 {
+        true => Some((prefix, bytes)),
+        false => None,
+    }
+}
+
+
+/// The most bits in which the dHash of a thumbnail may differ from that of its full image (see thumbnail_matches)
+#[cfg(feature = "image-audit")]
+pub const THUMBNAIL_TOLERANCE: u32 = 10;
+
+/// Whether a thumbnail looks like a downsized copy of its full image, i.e. an uploader has not given a thumbnail 
+/// which misrepresents the full image. Both are given as base64 sources as in an ImagePair.
+/// Thumbnails are made by the uploader, not by xtchd, so rather than being compared pixel by pixel, 
+/// both images are reduced to a perceptual difference hash (see dhash) which survives resizing and re-encoding,
+/// and match if the hashes differ in at most THUMBNAIL_TOLERANCE bits 
+#[cfg(feature = "image-audit")]
+pub fn thumbnail_matches(src_full: &str, src_thmb: &str) -> Result<bool, XrowError> {
+    let distance = (dhash(&decode_src(src_full)?)? ^ dhash(&decode_src(src_thmb)?)?).count_ones();
+    Ok(distance <= THUMBNAIL_TOLERANCE)
+}
+
+/// The largest width or height of an image decoded by dhash(). The decoded size of an image is not bounded by its
+/// encoded size (a small PNG can claim enormous dimensions), so larger images are rejected before their pixels are allocated 
+#[cfg(feature = "image-audit")]
+pub const MAX_DECODE_DIMENSION: u32 = 10_000;
+
+/// The 64-bit difference hash of an image: the image is reduced to 9x8 grayscale pixels, 
+/// and each bit is set where a pixel is darker than the pixel to its right.
+/// Returns XrowError::UndecodableImage for an image wider or taller than MAX_DECODE_DIMENSION 
+#[cfg(feature = "image-audit")]
+pub fn dhash(bytes: &[u8]) -> Result<u64, XrowError> {
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_DECODE_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODE_DIMENSION);
+    let mut reader = image::io::Reader::new(std::io::Cursor::new(bytes)).with_guessed_format()
+        .map_err(|_| XrowError::UndecodableImage)?;
+    reader.limits(limits);
+    let img = reader.decode().map_err(|_| XrowError::UndecodableImage)?;
+    let small = img.resize_exact(9, 8, image::imageops::FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = (hash << 1) | (small.get_pixel(x, y)[0] < small.get_pixel(x + 1, y)[0]) as u64;
+        }
+    }
+    Ok(hash)
+}
+
+/// The perceptual hash stored for an immutable image, to find visually similar images (see Xtchr::similar_images).
+/// This is the dhash of the decoded image, so similar images have hashes differing in few bits 
+#[cfg(feature = "image-audit")]
+pub fn image_phash(src: &str) -> Result<u64, XrowError> {
+    dhash(&decode_src(src)?)
+}
+
+/// The bytes of a base64 image source, with or without the data URI prefix (see decoded_len)
+#[cfg(feature = "image-audit")]
+fn decode_src(src: &str) -> Result<Vec<u8>, XrowError> {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    let b64 = match src.starts_with("data:") {
+        true => src.split_once(',').map(|(_prefix, b64)| b64).unwrap_or(""),
+        false => src,
+    };
+    let b64: String = b64.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    STANDARD.decode(b64).map_err(|_| XrowError::UndecodableImage)
+}
+
+
+/// Limits on the decoded size (in bytes) of images, as they are stored inline in Postgres and cached in Redis
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImageLimits {
+    /// the maximum size for the full image 
+    pub max_full_bytes: usize,
+    /// the maximum size for the thumbnail 
+    pub max_thmb_bytes: usize,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        ImageLimits{max_full_bytes: 5*1024*1024, max_thmb_bytes: 100*1024}
+    }
+}
+
+impl ImageLimits {
+    /// Read the limits from these environment variables, using the defaults for any that are not set:
+    /// XTCHD_MAX_IMAGE_BYTES,  max_full_bytes  defaults to 5 MB
+    /// XTCHD_MAX_THUMB_BYTES,  max_thmb_bytes  defaults to 100 KB
+    pub fn from_env() -> Self {
+        let default = ImageLimits::default();
+        let var = |key: &str, default: usize| std::env::var(key).ok().and_then(|val| val.parse().ok()).unwrap_or(default);
+        ImageLimits{
+            max_full_bytes: var("XTCHD_MAX_IMAGE_BYTES", default.max_full_bytes),
+            max_thmb_bytes: var("XTCHD_MAX_THUMB_BYTES", default.max_thmb_bytes),
+        }
+    }
+}
+
+
+/// Limits on the size of article pages, as each page is one row and is hashed as a whole 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageLimits {
+    /// the maximum number of paragraphs on one page 
+    pub max_paragraphs: usize,
+    /// the maximum number of characters in one paragraph 
+    pub max_paragraph_chars: usize,
+}
+
+impl Default for PageLimits {
+    fn default() -> Self {
+        PageLimits{max_paragraphs: 500, max_paragraph_chars: 50_000}
+    }
+}
+
+impl PageLimits {
+    /// Read the limits from these environment variables, using the defaults for any that are not set:
+    /// XTCHD_MAX_PARAGRAPHS,       max_paragraphs       defaults to 500
+    /// XTCHD_MAX_PARAGRAPH_CHARS,  max_paragraph_chars  defaults to 50,000
+    pub fn from_env() -> Self {
+        let default = PageLimits::default();
+        let var = |key: &str, default: usize| std::env::var(key).ok().and_then(|val| val.parse().ok()).unwrap_or(default);
+        PageLimits{
+            max_paragraphs: var("XTCHD_MAX_PARAGRAPHS", default.max_paragraphs),
+            max_paragraph_chars: var("XTCHD_MAX_PARAGRAPH_CHARS", default.max_paragraph_chars),
+        }
+    }
+}
+
+
 /// MutableImages are typically used for article thumbnails:
 /// i.e. they are a bit arbitrary and only need to roughly indicate the content of the article
 #[derive(Deserialize)]
@@ -223,6 +1066,17 @@ pub struct MutableImage {
     pub pair: ImagePair
 }
 
+/// What Xtchr::add_image_mutable() did with a MutableImage 
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MutImageOutcome {
+    /// there was no image with the id, so it was written 
+    Inserted,
+    /// the image with the id differed, so it was overwritten 
+    Updated,
+    /// the image with the id was identical, so the row was not touched 
+    Unchanged,
+}
+
 
 /// An ImmutableImage is used for images within an article. The assumption is that 
 /// the image "matters" and needs to "prove a point" (in contrast to MutableImages),
@@ -233,13 +1087,27 @@ pub struct ImmutableImage {
     pub img_id: i32,
     /// the image pair being saved 
     pub pair: ImagePair,
+    /// which state_string the row was hashed with (see IMAGE_HASH_V), as stored in the hash_v column of images_immut
+    pub hash_v: i16,
 }
 
+/// Images written before the hash_v column were hashed with the full image inline in the state string 
+pub const IMAGE_HASH_V_INLINE: i16 = 1;
+/// The version of ImmutableImage::state_string used for new images: the full image is folded in as its sha256 
+pub const IMAGE_HASH_V: i16 = 2;
+
 
 impl Xtchable for ImmutableImage {
+    /// From IMAGE_HASH_V, the full image is folded in as its sha256 rather than inline, so the state string stays short however large
+    /// the image (a full image is often megabytes of base64). This is no weaker: the image cannot change without its digest changing.
+    /// Images written before then (IMAGE_HASH_V_INLINE) still hash as written, so they still verify 
     fn state_string(&self) -> String {
-        format!("img_id={} src_full={} src_thmb={} alt={} url={} archive={}",
-            &self.img_id, &self.pair.src_full, &self.pair.src_thmb, &self.pair.alt, nonefmt(&self.pair.url), nonefmt(&self.pair.archive))
+        match self.hash_v {
+            IMAGE_HASH_V_INLINE => format!("img_id={} src_full={} src_thmb={} alt={} url={} archive={}",
+                &self.img_id, &self.pair.src_full, &self.pair.src_thmb, &self.pair.alt, nonefmt(&self.pair.url), nonefmt(&self.pair.archive)),
+            _ => format!("img_id={} src_full_sha256={} src_thmb={} alt={} url={} archive={}",
+                &self.img_id, sha256(&self.pair.src_full), &self.pair.src_thmb, &self.pair.alt, nonefmt(&self.pair.url), nonefmt(&self.pair.archive)),
+        }
     }
     fn dtype() -> &'static str {
         "Image"
@@ -247,6 +1115,31 @@ impl Xtchable for ImmutableImage {
 }
 
 
+/// Shown in place of the content of a row that has been tombstoned 
+pub const REDACTED: &str = "[redacted]";
+
+/// A Tombstone marks one row of a table with hash chain integrity as redacted (i.e. for a legal takedown).
+/// Tombstones are appended to their own chain, so redacting content never breaks verification of the chain it was in
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tombstone {
+    pub tomb_id: i32,
+    /// the table containing the redacted row, i.e. 'titles_immut'
+    pub target_table: String,
+    /// the id of the redacted row within target_table 
+    pub target_id: i32,
+    pub reason: String,
+}
+
+impl Xtchable for Tombstone {
+    fn state_string(&self) -> String {
+        format!("tomb_id={} target_table={} target_id={} reason={}", self.tomb_id, &self.target_table, self.target_id, &self.reason)
+    }
+    fn dtype() -> &'static str {
+        "Tombstone"
+    }
+}
+
+
 /// This struct is useful for autocompletion of results for immutable images 
 #[derive(Serialize, Deserialize)]
 pub struct ImageThumbnail {
@@ -259,7 +1152,8 @@ impl AutoComp<ImageThumbnail> for ImmutableImage {
     fn query_autocomp() ->  &'static str {
         "SELECT img_id, CONCAT(COALESCE(archive,''), ' ', alt) AS alt, src_thmb
         FROM images_immut
-        WHERE ac @@ to_tsquery('simple', $1) AND CONCAT(COALESCE(archive,''), ' ', alt) ILIKE '%' || $2 || '%'
+        WHERE ac @@ prefix_tsquery('simple', $1) AND CONCAT(COALESCE(archive,''), ' ', alt) ILIKE '%' || $2 || '%'
+        AND NOT redacted('images_immut', img_id)
         ORDER BY LENGTH(alt) ASC 
         LIMIT 10;"
     }
@@ -303,9 +1197,9 @@ pub struct Thumbnail {
 
 impl FullText for Thumbnail {
     fn query_fulltext() -> &'static str {
-        "SELECT img_id, thumb_src, atl
+        "SELECT img_id, src_thmb, alt
         FROM images_immut
-        WHERE ts @@ to_tsquery('english', $1)
+        WHERE ts @@ prefix_tsquery('english', $1) AND NOT redacted('images_immut', img_id)
         LIMIT 20;"
     }
 
@@ -319,3 +1213,262 @@ impl FullText for Thumbnail {
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_article_page_builder() {
+        let page = ArticlePage::builder().a_id_immut(3).paragraph("One").paragraph("Two").source(PageSrc::Xtchd(1)).build().unwrap();
+        assert_eq!((page.a_id_immut, page.paragraphs.len(), page.supersedes), (3, 2, None));
+        assert_eq!(page.p_id_draft.len(), DRAFT_ID_LEN);
+        let no_paragraphs = ArticlePage::builder().a_id_immut(3).source(PageSrc::Xtchd(1)).build();
+        assert!(matches!(no_paragraphs, Err(XrowError::IncompletePage{missing: "paragraphs"})));
+        let no_source = ArticlePage::builder().a_id_immut(3).paragraph("One").build();
+        assert!(matches!(no_source, Err(XrowError::IncompletePage{missing: "source"})));
+        let bad_draft = ArticlePage::builder().a_id_immut(3).paragraph("One").source(PageSrc::Xtchd(1)).p_id_draft("short").build();
+        assert!(matches!(bad_draft, Err(XrowError::InvalidDraftId{..})));
+    }
+
+    #[test]
+    fn test_new_draft_id() {
+        let id = new_draft_id();
+        assert_eq!(id.len(), DRAFT_ID_LEN);
+        assert!(check_draft_id(&id, DRAFT_ID_LEN).is_ok());
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-'));
+        assert_ne!(id, new_draft_id());
+    }
+
+    #[test]
+    fn test_ts_config() {
+        assert_eq!(TsConfig::parse("Spanish"), Ok(TsConfig::Spanish));
+        assert_eq!(TsConfig::parse("german").unwrap().as_str(), "german");
+        assert_eq!(TsConfig::default(), TsConfig::English);
+        assert!(matches!(TsConfig::parse("english'); DROP TABLE authors; --"), Err(XrowError::UnknownTsConfig{..})));
+    }
+
+    #[test]
+    fn test_strip_markdown_headings() {
+        assert_eq!(strip_markdown("## Background\nSome text"), "Background\nSome text");
+        assert_eq!(strip_markdown("#hashtag stays"), "#hashtag stays");
+        assert_eq!(strip_markdown("> **Quoted** and _kept_"), "Quoted and _kept_");
+    }
+
+    #[test]
+    fn test_strip_markdown_links() {
+        let md = "See [the report](https://example.com/report) and ![a chart](chart.png) for __details__.";
+        assert_eq!(strip_markdown(md), "See the report and a chart for details.");
+        assert_eq!(strip_markdown("[not a link] (really)"), "[not a link] (really)");
+    }
+
+    #[test]
+    fn test_strip_markdown_code_fences() {
+        let md = "Run this:\n```rust\nlet x = 1;\n```\nThen `cargo test`.";
+        assert_eq!(strip_markdown(md), "Run this:\nlet x = 1;\nThen cargo test.");
+    }
+
+    fn image_pair(src_full: &str, src_thmb: &str) -> ImagePair {
+        ImagePair{src_full: src_full.to_string(), src_thmb: src_thmb.to_string(), alt: "test".to_string(), url: None, archive: None}
+    }
+
+    #[test]
+    fn test_image_size_limits() {
+        let limits = ImageLimits{max_full_bytes: 3, max_thmb_bytes: 3};
+        // "QUJD" decodes to 3 bytes (at the limit), "QUI=" to 2 (below), "QUJDRA==" to 4 (above)
+        assert_eq!(image_pair("data:image/png;base64, QUJD", "QUJD").check_size(&limits), Ok(()));
+        assert_eq!(image_pair("data:image/png;base64,QUI=", "QUI=").check_size(&limits), Ok(()));
+        assert_eq!(image_pair("data:image/png;base64,QUJDRA==", "QUI=").check_size(&limits), Err(XrowError::ImageTooLarge{bytes: 4, limit: 3}));
+        assert_eq!(image_pair("QUI=", "QUJD\nRA==").check_size(&limits), Err(XrowError::ImageTooLarge{bytes: 4, limit: 3}));
+    }
+
+    #[test]
+    fn test_page_size_limits() {
+        let limits = PageLimits{max_paragraphs: 3, max_paragraph_chars: 4};
+        let page = |paragraphs: &[&str]| ArticlePage::builder().a_id_immut(0).source(PageSrc::Xtchd(0))
+            .paragraphs(paragraphs.iter().map(|para| para.to_string()).collect()).build().unwrap();
+        // at each limit (counting characters, not bytes), then beyond it 
+        assert_eq!(page(&["a", "b", "ñañá"]).check_size(&limits), Ok(()));
+        assert_eq!(page(&["a", "b", "c", "d"]).check_size(&limits), Err(XrowError::PageTooLarge{what: "paragraphs", size: 4, limit: 3}));
+        assert_eq!(page(&["a", "abcde"]).check_size(&limits), Err(XrowError::PageTooLarge{what: "characters in a paragraph", size: 5, limit: 4}));
+        let defaults = PageLimits::default();
+        assert_eq!(page(&["x".repeat(50_000).as_str()]).check_size(&defaults), Ok(()));
+        assert!(page(&vec!["x"; 501]).check_size(&defaults).is_err());
+    }
+
+    #[test]
+    fn test_page_src_round_trip() {
+        use tokio_postgres::types::{FromSql, ToSql, Type};
+        for src in [PageSrc::Author("wiki/800px-Merkava-Mk4m-whiteback01.jpg".to_string()), PageSrc::Xtchd(7), PageSrc::WpTxYt(42)] {
+            let mut buf = BytesMut::new();
+            src.to_sql(&Type::RECORD, &mut buf).unwrap();
+            let read_back = PageSrc::from_sql(&Type::RECORD, &buf).unwrap();
+            assert_eq!(read_back.src_columns(), src.src_columns());
+        }
+        assert_eq!(PageSrc::from_columns(Some(1), None, Some(2)).err(), Some(XrowError::InvalidPageSrc{columns_set: 2}));
+        assert_eq!(PageSrc::from_columns(None, None, None).err(), Some(XrowError::InvalidPageSrc{columns_set: 0}));
+    }
+
+    #[test]
+    fn test_page_to_html() {
+        let paragraphs = vec![
+            "Fish & chips <b>not bold</b>".to_string(), 
+            "He said \"it's fine\"".to_string(),
+        ];
+        let page = ArticlePage{a_id_immut: 0, p_id_draft: String::new(), p_id_immut: 0, paragraphs, source: PageSrc::Xtchd(0), supersedes: None, hash_v: PAGE_HASH_V};
+        assert_eq!(page.to_html(), "<p>Fish &amp; chips &lt;b&gt;not bold&lt;/b&gt;</p>\n<p>He said &quot;it&#39;s fine&quot;</p>");
+    }
+
+    #[test]
+    fn test_paragraphs_unambiguous() {
+        let page = |paragraphs: Vec<&str>| ArticlePage{a_id_immut: 0, p_id_draft: String::new(), p_id_immut: 0, 
+            paragraphs: paragraphs.into_iter().map(String::from).collect(), source: PageSrc::Xtchd(0), supersedes: None, hash_v: PAGE_HASH_V};
+        let one = page(vec!["a | b"]);
+        let two = page(vec!["a", "b"]);
+        assert_eq!(one.state_string(), "a_id_immut=0 p_id_immut=0 paragraphs=[\"a | b\"] img_id= image_file= refs_a_id_immut=0");
+        assert_ne!(one.state_string(), two.state_string());
+        let prior_sha256 = crate::integrity::GENESIS_SHA256;
+        let written = crate::integrity::now();
+        let one_hcl = crate::integrity::HashChainLink::from_timestamp(prior_sha256, written, &one);
+        let two_hcl = crate::integrity::HashChainLink::from_timestamp(prior_sha256, written, &two);
+        assert_ne!(one_hcl.new_sha256(), two_hcl.new_sha256());
+        // pages written before hash_v still hash with their paragraphs joined 
+        let joined = ArticlePage{hash_v: PAGE_HASH_V_JOINED, ..two};
+        assert_eq!(joined.state_string(), "a_id_immut=0 p_id_immut=0 paragraphs=a | b img_id= image_file= refs_a_id_immut=0");
+    }
+
+    #[test]
+    fn test_check_draft_id() {
+        assert_eq!(check_draft_id("V1StGXR8_Z5jdHi6B-myT", DRAFT_ID_LEN), Ok(()));
+        assert_eq!(check_draft_id("V1StGXR8_Z5jdHi6", DRAFT_ID_LEN), Err(XrowError::InvalidDraftId{expected: 21, got: 16}));
+        assert_eq!(check_draft_id("V1StGXR8_Z5jdHi6B-myT-x", DRAFT_ID_LEN), Err(XrowError::InvalidDraftId{expected: 21, got: 23}));
+        assert_eq!(check_draft_id("V1StGXR8_Z5jdHi6", MUTABLE_IMAGE_ID_LEN), Ok(()));
+    }
+
+    #[test]
+    fn test_page_src_serde() {
+        for (src, json) in [
+            (PageSrc::Author("splash.png".to_string()), r#"{"type":"Author","value":"splash.png"}"#),
+            (PageSrc::Xtchd(3), r#"{"type":"Xtchd","value":3}"#),
+            (PageSrc::WpTxYt(7), r#"{"type":"WpTxYt","value":7}"#),
+        ] {
+            assert_eq!(serde_json::to_string(&src).unwrap(), json);
+            assert_eq!(serde_json::from_str::<PageSrc>(json).unwrap(), src);
+        }
+    }
+
+    #[cfg(feature = "compress-images")]
+    #[test]
+    fn test_split_data_uri() {
+        let (prefix, bytes) = split_data_uri("data:image/png;base64,aGVsbG8=").unwrap();
+        assert_eq!((prefix, bytes.as_slice()), ("data:image/png;base64,", "hello".as_bytes()));
+        // sources which would not re-encode to exactly the same text are stored as-is 
+        assert!(split_data_uri("data:image/png;base64, aGVsbG8=").is_none());
+        assert!(split_data_uri("aGVsbG8=").is_none());
+        assert!(split_data_uri("data:image/png;base64,not base64!").is_none());
+    }
+
+    #[test]
+    fn test_detect_img_format() {
+        assert_eq!(detect_img_format(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Ok(ImgFormat::Png));
+        assert_eq!(detect_img_format(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F']), Ok(ImgFormat::Jpeg));
+        // the RIFF header of a lossy WebP: the chunk size, then WEBP and the VP8 chunk
+        assert_eq!(detect_img_format(b"RIFF\x24\0\0\0WEBPVP8 \x18\0\0\0"), Ok(ImgFormat::WebP));
+        // the ftyp box of an AVIF: box size, ftyp, then the major brand
+        assert_eq!(detect_img_format(b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf"), Ok(ImgFormat::Avif));
+        assert_eq!(ImgFormat::Avif.mime(), "image/avif");
+        let svg = "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"><script>alert(1)</script></svg>";
+        assert_eq!(detect_img_format(svg.as_bytes()), Err(XrowError::SvgRejected));
+        assert_eq!(detect_img_format(b"\xEF\xBB\xBF  <SVG></SVG>"), Err(XrowError::SvgRejected));
+        assert_eq!(detect_img_format(b"RIFF\x24\0\0\0WAVEfmt "), Err(XrowError::UnknownImageFormat));
+        assert_eq!(detect_img_format(b"GIF89a"), Err(XrowError::UnknownImageFormat));
+        assert_eq!(detect_img_format(b""), Err(XrowError::UnknownImageFormat));
+    }
+
+    #[test]
+    fn test_reject_svg() {
+        assert_eq!(decoded_head("iVBORw0KGgo=", 1024), b"\x89PNG\r\n\x1a\n");
+        assert_eq!(decoded_head("QUJDRA==", 2), b"AB");
+        assert_eq!(reject_svg("data:image/png;base64,iVBORw0KGgo="), Ok(()));
+        // formats that are not recognized are still accepted 
+        assert_eq!(reject_svg("data:image/gif;base64,R0lGODlh"), Ok(()));
+        // SVG is rejected by its bytes however it is labeled, and by its label 
+        assert_eq!(reject_svg("data:image/png;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciPjwvc3ZnPg=="), Err(XrowError::SvgRejected));
+        assert_eq!(reject_svg("PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciPjwvc3ZnPg=="), Err(XrowError::SvgRejected));
+        assert_eq!(reject_svg("data:image/svg+xml;base64,iVBORw0KGgo="), Err(XrowError::SvgRejected));
+    }
+
+    #[cfg(feature = "image-audit")]
+    #[test]
+    fn test_thumbnail_matches() {
+        use crate::test_harness::gradient_png;
+        let full = gradient_png(640, 480, false);
+        assert_eq!(thumbnail_matches(&full, &gradient_png(64, 48, false)), Ok(true));
+        assert_eq!(thumbnail_matches(&full, &gradient_png(64, 48, true)), Ok(false));
+        assert_eq!(thumbnail_matches(&full, "data:image/png;base64,QUJD"), Err(XrowError::UndecodableImage));
+        assert_eq!(thumbnail_matches(&full, "not base64!"), Err(XrowError::UndecodableImage));
+    }
+
+    #[cfg(feature = "image-audit")]
+    #[test]
+    fn test_phash_decode_limits() {
+        // an image wider than MAX_DECODE_DIMENSION is rejected however small it is encoded 
+        use crate::test_harness::gradient_png;
+        assert!(image_phash(&gradient_png(MAX_DECODE_DIMENSION, 1, false)).is_ok());
+        assert_eq!(image_phash(&gradient_png(MAX_DECODE_DIMENSION + 1, 1, false)), Err(XrowError::UndecodableImage));
+    }
+
+    #[test]
+    fn test_check_vid_pk() {
+        assert_eq!(check_vid_pk("dQw4w9WgXcQ"), Ok(()));
+        assert_eq!(check_vid_pk("a-_0123456Z"), Ok(()));
+        let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        assert_eq!(check_vid_pk(url), Err(XrowError::InvalidVidPk{vid_pk: url.to_string()}));
+        assert!(check_vid_pk("dQw4w9WgXc").is_err());
+        assert!(check_vid_pk("dQw4w9WgXc!").is_err());
+    }
+
+    #[test]
+    fn test_image_state_string_bounded() {
+        let src_full = format!("data:image/png;base64,{}", "QUJD".repeat(1_000_000));
+        let image = ImmutableImage{img_id: 7, pair: ImagePair{src_full: src_full.clone(), src_thmb: "data:image/png;base64,QUI=".to_string(), 
+            alt: "big".to_string(), url: None, archive: None}, hash_v: IMAGE_HASH_V};
+        let state = image.state_string();
+        assert!(state.len() < 200, "{}", state);
+        assert_eq!(state, format!("img_id=7 src_full_sha256={} src_thmb=data:image/png;base64,QUI= alt=big url= archive=", sha256(&src_full)));
+        assert_eq!(state, image.state_string());
+        // pinned: a change here breaks verification of every image already written 
+        let small = ImmutableImage{img_id: 0, pair: ImagePair{src_full: "hello world!".to_string(), ..image.pair}, hash_v: IMAGE_HASH_V};
+        assert!(small.state_string().contains("src_full_sha256=7509e5bda0c762d2bac7f90d758b5b2263fa01ccbc542ab5e3df163be08e6ca9 "));
+        // images written before hash_v still hash with the full image inline 
+        let inline = ImmutableImage{hash_v: IMAGE_HASH_V_INLINE, ..small};
+        assert_eq!(inline.state_string(), "img_id=0 src_full=hello world! src_thmb=data:image/png;base64,QUI= alt=big url= archive=");
+    }
+
+    #[test]
+    fn test_canonical_data_uri() {
+        let canonical = "data:image/png;base64,iVBORw0KGgoA";
+        assert_eq!(ImagePair::canonical_data_uri(canonical), Ok(canonical.to_string()));
+        assert_eq!(ImagePair::canonical_data_uri("DATA:Image/PNG;Base64,iVBORw0KGgoA"), Ok(canonical.to_string()));
+        assert_eq!(ImagePair::canonical_data_uri(" data: image/png ; base64 ,iVBOR\nw0KG\r\ngoA\n"), Ok(canonical.to_string()));
+        assert_eq!(ImagePair::canonical_data_uri("data:image/svg+xml;base64,PHN2Zz4="), Ok("data:image/svg+xml;base64,PHN2Zz4=".to_string()));
+        assert_eq!(ImagePair::canonical_data_uri("QUJD\nRA=="), Ok("QUJDRA==".to_string()));
+        for invalid in ["data:image/png,iVBORw0KGgoA", "data:;base64,iVBORw0KGgoA", "data:image/png;charset=utf-8;base64,QUI=", "data:image/png;base64"] {
+            assert!(matches!(ImagePair::canonical_data_uri(invalid), Err(XrowError::InvalidDataUri{..})), "{}", invalid);
+        }
+        let pair = ImagePair{src_full: "Data:Image/Jpeg;base64,/9j/\n4AAQ".to_string(), ..image_pair("", "QU I=")}.canonicalized().unwrap();
+        assert_eq!((pair.src_full.as_str(), pair.src_thmb.as_str()), ("data:image/jpeg;base64,/9j/4AAQ", "QUI="));
+    }
+
+    #[test]
+    fn test_parse_upload_date() {
+        let date = NaiveDate::from_ymd_opt(2009, 10, 25).unwrap();
+        assert_eq!(parse_upload_date("2009-10-25"), Ok(date));
+        assert_eq!(parse_upload_date("20091025"), Ok(date));
+        assert_eq!(parse_upload_date(" 20091025\n"), Ok(date));
+        for garbage in ["25/10/2009", "2009-02-30", "20091325", "2009-1-25", "yesterday", ""] {
+            assert_eq!(parse_upload_date(garbage), Err(XrowError::InvalidUploadDate{date: garbage.to_string()}));
+        }
+    }
+
+}
@@ -3,11 +3,22 @@
 //! with cryptographic verification. 
 
 use chrono::{NaiveDate, DateTime, offset::Utc};
-use pachydurable::{connect::{ConnPoolNoTLS, ClientNoTLS, pool_no_tls_from_env}, err::{PachyDarn, MissingRowError}};
+use pachydurable::{connect::{ConnPoolNoTLS, ClientNoTLS}, err::{PachyDarn, MissingRowError}};
 use pachydurable::redis as predis;
-use crate::{xrows, views, integrity::{XtchdContent, HashChainLink}};
+use pachydurable::{autocomplete::{AutoComp, WhoWhatWhere}, err::GenericError, redis::CachedAutoComp};
+use std::{collections::{HashMap, HashSet}, future::Future};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::field::Empty;
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use deadpool_postgres::GenericClient;
+use tokio_postgres::IsolationLevel;
+use crate::{xrows, views, err::XtchdError, integrity::{self, Xtchable, ToRow, XtchdContent, XtchdSQL, HashChainLink, VerifyMode, RowVerification, GENESIS_SHA256}};
+#[cfg(feature = "tsa")]
+use crate::tsa;
 
 
+/// The tail of a table with hash chain integrity, from which the next row follows on 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LastRow {
     /// This is the latest/highest id in the table. It will only be None for the very first entry 
     pub prior_id: Option<i32>,
@@ -15,6 +26,7 @@ pub struct LastRow {
 }
 
 impl LastRow {
+    /// The id the next row written to the table will have 
     pub fn next_id(&self) -> i32 {
         match self.prior_id {
             None => 0,
@@ -27,18 +39,619 @@ impl LastRow {
 /// This function is intended to get a query that sorts by id (returning the highest/latest)
 /// for use in tables with hash integrity.
 /// If no prior entry has been make, it will return a default value 
-async fn get_last_row(c: &ClientNoTLS, query: &'static str) -> Result<LastRow, PachyDarn> {
-    let rows = c.query(query, &[]).await?;
+async fn get_last_row(c: &ClientNoTLS, query: &str) -> Result<LastRow, PachyDarn> {
+    let stmt = c.prepare_cached(query).await?;
+    let rows = c.query(&stmt, &[]).await?;
     let (prior_id, prior_sha256) = match rows.get(0) {
         Some(row) => (Some(row.get(0)), row.get(1)),
-        None => (None, "0000000000000000000000000000000000000000000000000000000000000000".to_string()),
+        None => (None, GENESIS_SHA256.to_string()),
     };
     Ok(LastRow{prior_id, prior_sha256})
 }
 
+/// Take the advisory lock on a table for the rest of the transaction, which every write to a table with hash chain integrity holds 
+/// while it reads the tail and appends to it (see Xtchr::lock_tail), so concurrent writers queue rather than race for the same id 
+async fn lock_table(tx: &deadpool_postgres::Transaction<'_>, name: &str) -> Result<(), tokio_postgres::Error> {
+    tx.execute("SELECT pg_advisory_xact_lock(hashtext('xtchd.' || $1))", &[&name]).await?;
+    Ok(())
+}
+
+/// get_last_row() for the table within a transaction, i.e. once the transaction holds the table's advisory lock 
+async fn last_row_in(tx: &deadpool_postgres::Transaction<'_>, table: &ChainTable) -> Result<LastRow, tokio_postgres::Error> {
+    let query = format!("SELECT {}, new_sha256 FROM {} ORDER BY {} DESC LIMIT 1", table.id_column, table.name, table.id_column);
+    let rows = tx.query(&query, &[]).await?;
+    Ok(match rows.get(0) {
+        Some(row) => LastRow{prior_id: Some(row.get(0)), prior_sha256: row.get(1)},
+        None => LastRow{prior_id: None, prior_sha256: GENESIS_SHA256.to_string()},
+    })
+}
+
+
+/// An INSERT of the columns of T (see ToRow) along with the hash chain columns, 
+/// taking its parameters from insert_params()
+fn insert_query<T: ToRow>(table: &str) -> String {
+    let columns = T::columns();
+    let placeholders: Vec<String> = (1..=columns.len() + 4).map(|i| format!("${}", i)).collect();
+    format!("INSERT INTO {} (prior_id, {}, prior_sha256, write_timestamp, new_sha256) VALUES ({})", 
+        table, columns.join(", "), placeholders.join(", "))
+}
+
+/// Reject the write_timestamp of a presigned write unless it is no more than PRESIGNED_BACKDATE_SECS before now()
+/// and no more than PRESIGNED_WINDOW_SECS after it. Milliseconds are compared, as a timestamp 1.9s old is already too old for Postgres 
+fn check_presigned_timestamp(write_timestamp: DateTime<Utc>) -> Result<(), XtchdError> {
+    let ahead_ms = (write_timestamp - integrity::now()).num_milliseconds();
+    if ahead_ms < -PRESIGNED_BACKDATE_SECS * 1000 || ahead_ms > PRESIGNED_WINDOW_SECS * 1000 {
+        return Err(XtchdError::InvalidArgument(format!("write_timestamp {} is {}ms from the server's clock, outside the {}s before to {}s after allowed", 
+            write_timestamp, ahead_ms, PRESIGNED_BACKDATE_SECS, PRESIGNED_WINDOW_SECS)))
+    }
+    Ok(())
+}
+
+/// INSERT the content (with its id already set) and the HashChainLink following on from last_row, within the transaction holding the table's lock 
+async fn insert_row<T: Xtchable + ToRow>(tx: &deadpool_postgres::Transaction<'_>, table: &str, content: &T, last_row: &LastRow, hclink: &HashChainLink) -> Result<(), tokio_postgres::Error> {
+    let new_sha256 = hclink.new_sha256();
+    let stmt = tx.prepare_cached(&insert_query::<T>(table)).await?;
+    tx.execute(&stmt, &insert_params(content, last_row, hclink, &new_sha256)).await?;
+    Ok(())
+}
+
+/// Append the content following on from last_row (see Xtchr::lock_tail), setting its id, and commit 
+async fn append_in<T: Xtchable + ToRow>(tx: deadpool_postgres::Transaction<'_>, table: &ChainTable, mut content: T, last_row: &LastRow) -> Result<(T, HashChainLink), XtchdError> {
+    let id = last_row.next_id();
+    content.set_id(id);
+    let hclink = HashChainLink::new(&last_row.prior_sha256, &content);
+    insert_row(&tx, table.name, &content, last_row, &hclink).await?;
+    tx.commit().await?;
+    record_etched(id, &last_row.prior_sha256, &hclink);
+    Ok((content, hclink))
+}
+
+/// The parameters for insert_query(), where the content follows on from last_row 
+fn insert_params<'a, T: ToRow>(content: &'a T, last_row: &'a LastRow, hclink: &'a HashChainLink, new_sha256: &'a String) -> Vec<&'a (dyn tokio_postgres::types::ToSql + Sync)> {
+    let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = vec![&last_row.prior_id];
+    params.extend(content.values());
+    params.extend([&last_row.prior_sha256 as &(dyn tokio_postgres::types::ToSql + Sync), &hclink.write_timestamp, new_sha256]);
+    params
+}
+
+
+/// The next row of a table, reserved by Xtchr::reserve_next(): no other reservation of the table can be made
+/// until this is written or dropped 
+pub struct Reservation<'a> {
+    tx: deadpool_postgres::Transaction<'a>,
+    table: &'static ChainTable,
+    /// the tail of the table, from which the reserved row follows on 
+    pub last_row: LastRow,
+}
+
+impl<'a> Reservation<'a> {
+    /// Write content whose new_sha256 was computed by the client from .last_row, and commit, releasing the reservation.
+    /// As with Xtchr::append_presigned(), the id is set to the next id and the hash is recomputed server-side, 
+    /// returning an IntegrityError (and rolling back) if it does not match, and the write_timestamp must be within the presigned window (see check_presigned_timestamp) 
+    pub async fn append_presigned<T: Xtchable + ToRow>(self, mut content: T, write_timestamp: DateTime<Utc>, new_sha256: &str) -> Result<(T, HashChainLink), XtchdError> {
+        check_presigned_timestamp(write_timestamp)?;
+        content.set_id(self.last_row.next_id());
+        let hclink = HashChainLink::from_timestamp(&self.last_row.prior_sha256, write_timestamp, &content);
+        let computed = hclink.new_sha256();
+        if computed != new_sha256 {
+            return Err(XtchdError::from(integrity::IntegrityError::HashMismatch{dtype: <T as Xtchable>::dtype().to_string(), 
+                stored: new_sha256.to_string(), computed}))
+        }
+        insert_row(&self.tx, self.table.name, &content, &self.last_row, &hclink).await?;
+        self.tx.commit().await?;
+        record_etched(self.last_row.next_id(), &self.last_row.prior_sha256, &hclink);
+        Ok((content, hclink))
+    }
+}
+
+
+/// Record the row written by an Xtchr write method on the method's span (see its tracing::instrument attribute)
+/// and emit a debug event, so each write can be traced when a subscriber is enabled 
+fn record_etched(id: i32, prior_sha256: &str, hclink: &HashChainLink) {
+    // new_sha256() hashes the row again, so nothing is recorded unless a subscriber wants debug spans and events 
+    if !tracing::enabled!(tracing::Level::DEBUG) {
+        return
+    }
+    let span = tracing::Span::current();
+    span.record("id", id);
+    span.record("prior_sha256", prior_sha256);
+    span.record("new_sha256", hclink.new_sha256().as_str());
+    tracing::debug!("etched");
+}
+
+
+/// A table with hash chain integrity, and how to read its rows back as the JSON for an XtchdSQL<T>
+pub struct ChainTable {
+    /// the name of the table 
+    pub name: &'static str,
+    /// the id column, which increases by one for each row
+    pub id_column: &'static str,
+    /// the dtype of the Xtchable struct for each row 
+    pub dtype: &'static str,
+    /// a SQL expression building the JSON that deserializes to the content, i.e. the Xtchable struct for this table 
+    pub content_json: &'static str,
+}
+
+impl ChainTable {
+    /// A query returning each row (in id order) as JSON deserializable to XtchdSQL<T>, 
+    /// with the clause (i.e. "WHERE auth_id = $1") inserted before the ORDER BY
+    pub fn xtchd_sql_query(&self, clause: &str) -> String {
+        format!("SELECT JSON_BUILD_OBJECT('prior_id', prior_id, 'prior_sha256', prior_sha256, 'content', {}, 
+                'write_timestamp', write_timestamp, 'new_sha256', new_sha256)
+            FROM {} {} ORDER BY {} ASC", self.content_json, self.name, clause, self.id_column)
+    }
+}
+
+/// The tables with hash chain integrity built in to xtchd: others can be added with Xtchr::register_content_table()
+pub static CHAIN_TABLES: [ChainTable; 9] = [
+    ChainTable{name: "authors", id_column: "auth_id", dtype: "Author", 
+        content_json: "JSON_BUILD_OBJECT('auth_id', auth_id, 'name', name)"},
+    ChainTable{name: "author_names", id_column: "name_id", dtype: "AuthorName", 
+        content_json: "JSON_BUILD_OBJECT('name_id', name_id, 'auth_id', auth_id, 'name', name)"},
+    ChainTable{name: "titles_immut", id_column: "a_id_immut", dtype: "ArticleTitle", 
+        content_json: "JSON_BUILD_OBJECT('a_id_draft', a_id_draft, 'a_id_immut', a_id_immut, 'auth_id', auth_id, 'title', title)"},
+    ChainTable{name: "pages_immut", id_column: "p_id_immut", dtype: "ArticlePage", 
+        content_json: "JSON_BUILD_OBJECT('a_id_immut', a_id_immut, 'p_id_draft', p_id_draft, 'p_id_immut', p_id_immut, 'paragraphs', paragraphs, 
+            'source', CASE WHEN img_id IS NOT NULL THEN JSON_BUILD_OBJECT('type', 'WpTxYt', 'value', img_id)
+                WHEN refs_a_id_immut IS NOT NULL THEN JSON_BUILD_OBJECT('type', 'Xtchd', 'value', refs_a_id_immut)
+                ELSE JSON_BUILD_OBJECT('type', 'Author', 'value', image_file) END, 'supersedes', supersedes, 'hash_v', hash_v)"},
+    ChainTable{name: "article_para", id_column: "apara_id", dtype: "ArticlePara", 
+        content_json: "JSON_BUILD_OBJECT('apara_id', apara_id, 'art_id', art_id, 'md', md)"},
+    ChainTable{name: "youtube_channels", id_column: "chan_id", dtype: "YoutubeChannel", 
+        content_json: "JSON_BUILD_OBJECT('chan_id', chan_id, 'url', url, 'name', name)"},
+    ChainTable{name: "youtube_videos", id_column: "vid_id", dtype: "YoutubeVideo", 
+        content_json: "JSON_BUILD_OBJECT('chan_id', chan_id, 'vid_id', vid_id, 'vid_pk', vid_pk, 'title', title, 'date_uploaded', date_uploaded)"},
+    ChainTable{name: "images_immut", id_column: "img_id", dtype: "Image", 
+        content_json: "JSON_BUILD_OBJECT('img_id', img_id, 'pair', JSON_BUILD_OBJECT('src_full', image_src(src_full, full_prefix, full_bytes), 'src_thmb', src_thmb, 'alt', alt, 'url', url, 'archive', archive), 'hash_v', hash_v)"},
+    ChainTable{name: "tombstones", id_column: "tomb_id", dtype: "Tombstone", 
+        content_json: "JSON_BUILD_OBJECT('tomb_id', tomb_id, 'target_table', target_table, 'target_id', target_id, 'reason', reason)"},
+];
+
+/// Whether a unique constraint fixes the position of a row in one of the CHAIN_TABLES: its primary key (the id column) or prior_id,
+/// so a violation means another writer took that position first (see XtchdError::ChainContended) 
+pub fn is_chain_position(constraint: &str) -> bool {
+    CHAIN_TABLES.iter().any(|table| constraint == format!("{}_pkey", table.name) || constraint == format!("{}_prior_id_key", table.name))
+}
+
+/// Look up one of the CHAIN_TABLES by name (see Xtchr::content_table to include registered tables). 
+/// Table names cannot be passed as query parameters, so this also guards against SQL injection
+pub fn chain_table(name: &str) -> Result<&'static ChainTable, XtchdError> {
+    CHAIN_TABLES.iter().find(|table| table.name == name)
+        .ok_or_else(|| XtchdError::InvalidArgument(format!("'{}' is not a table with hash chain integrity", name)))
+}
+
+
+/// An Xtchable type stored in its own table with hash chain integrity. 
+/// Registering a type (see Xtchr::register_content_table) plugs its table into the generic verification methods, 
+/// i.e. verify_row(), verify_chain(), verify_all(), integrity_status(), and chain_heads(), without editing them
+pub trait ContentTable: Xtchable + DeserializeOwned + Send + Sync + 'static {
+    /// The table holding the rows, which must have the columns prior_id, prior_sha256, write_timestamp, and new_sha256
+    /// along with those read by its content_json 
+    fn chain_table() -> &'static ChainTable;
+}
+
+/// The ChainTable for a built-in content type 
+fn builtin_table<T: Xtchable>() -> &'static ChainTable {
+    CHAIN_TABLES.iter().find(|table| table.dtype == T::dtype()).expect("every built-in content type has a table in CHAIN_TABLES")
+}
+
+impl ContentTable for xrows::Author { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::AuthorName { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::ArticleTitle { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::ArticlePage { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::ArticlePara { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::YoutubeChannel { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::YoutubeVideo { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::ImmutableImage { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+impl ContentTable for xrows::Tombstone { fn chain_table() -> &'static ChainTable { builtin_table::<Self>() } }
+
+type BoxFuture<'a, T> = std::pin::Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A registered ContentTable, with the generic verification methods instantiated for its content type 
+#[derive(Clone, Copy)]
+struct RegisteredTable {
+    table: &'static ChainTable,
+    verify_row: for<'a> fn(&'a Xtchr, &'static ChainTable, i32) -> BoxFuture<'a, Result<RowVerification, XtchdError>>,
+    verify_chain: for<'a> fn(&'a Xtchr, &'static ChainTable, Option<i64>) -> BoxFuture<'a, Result<(), XtchdError>>,
+}
+
+impl RegisteredTable {
+    fn of<T: ContentTable>() -> Self {
+        RegisteredTable{table: T::chain_table(), verify_row: verify_row_of::<T>, verify_chain: verify_chain_of::<T>}
+    }
+}
+
+fn verify_row_of<T: ContentTable>(x: &Xtchr, table: &'static ChainTable, id: i32) -> BoxFuture<'_, Result<RowVerification, XtchdError>> {
+    Box::pin(x.verify_row_as::<T>(table, id))
+}
+
+fn verify_chain_of<T: ContentTable>(x: &Xtchr, table: &'static ChainTable, batch_size: Option<i64>) -> BoxFuture<'_, Result<(), XtchdError>> {
+    Box::pin(x.verify_chain_as::<T>(table, batch_size))
+}
+
+/// The ContentTables known to a Pool and each Xtchr from it, in the order they were registered: 
+/// the CHAIN_TABLES come first 
+#[derive(Clone)]
+pub struct ContentTables(Vec<RegisteredTable>);
+
+impl Default for ContentTables {
+    fn default() -> Self {
+        ContentTables(vec![RegisteredTable::of::<xrows::Author>(), RegisteredTable::of::<xrows::AuthorName>(), 
+            RegisteredTable::of::<xrows::ArticleTitle>(), 
+            RegisteredTable::of::<xrows::ArticlePage>(), RegisteredTable::of::<xrows::ArticlePara>(), 
+            RegisteredTable::of::<xrows::YoutubeChannel>(), RegisteredTable::of::<xrows::YoutubeVideo>(), 
+            RegisteredTable::of::<xrows::ImmutableImage>(), RegisteredTable::of::<xrows::Tombstone>()])
+    }
+}
+
+impl ContentTables {
+    /// Add the table for T, replacing any table registered with the same name 
+    pub fn register<T: ContentTable>(&mut self) {
+        let registered = RegisteredTable::of::<T>();
+        self.0.retain(|entry| entry.table.name != registered.table.name);
+        self.0.push(registered);
+    }
+
+    fn get(&self, name: &str) -> Result<RegisteredTable, XtchdError> {
+        self.0.iter().find(|entry| entry.table.name == name).copied()
+            .ok_or_else(|| XtchdError::InvalidArgument(format!("'{}' is not a table with hash chain integrity", name)))
+    }
+
+    /// Every registered table 
+    pub fn tables(&self) -> Vec<&'static ChainTable> {
+        self.0.iter().map(|entry| entry.table).collect()
+    }
+}
+
+
+/// The default number of rows written by each INSERT in bulk imports, i.e. Xtchr::import_channel_videos()
+pub const DEFAULT_IMPORT_BATCH: usize = 1000;
+
+/// The most parameters Postgres accepts for one statement, which limits the rows in a multi-row INSERT 
+const MAX_QUERY_PARAMS: usize = 65535;
+
+
+/// The constraint reported by XrowError::Conflict when a name is already another author's current name 
+/// (see Xtchr::rename_author): this is checked by xtchd rather than Postgres, as the current name is read from the author_current view 
+pub const AUTHOR_CURRENT_NAME: &str = "author_current_name";
+
+/// How far (in seconds) after the server's clock the write_timestamp of a presigned write may be 
+/// (see Xtchr::append_presigned), so a client cannot postdate what it etches. 
+pub const PRESIGNED_WINDOW_SECS: i64 = 5;
+
+/// How far (in seconds) before the server's clock the write_timestamp of a presigned write may be, so a client cannot backdate what it etches.
+/// This is no more than the *_no_rewrite_later constraints allow, as Postgres rejects a row written more than a second after its write_timestamp 
+pub const PRESIGNED_BACKDATE_SECS: i64 = 1;
+
+/// The batch_size with which Xtchr::integrity_status(deep = true) verifies each table (see verify_chain_batched)
+const INTEGRITY_BATCH_SIZE: i64 = 1000;
+
+/// The minimum pg_trgm word_similarity() for a fuzzy autocomplete match (see Xtchr::autocomplete_authors)
+const FUZZY_THRESHOLD: f32 = 0.3;
+
+
+/// The columns of pages_immut read by page_from_row()
+const PAGE_COLUMNS: &str = "prior_id, p_id_draft, p_id_immut, a_id_immut, paragraphs, 
+    ROW(img_id, image_file, refs_a_id_immut)::page_src AS source, prior_sha256, write_timestamp, new_sha256, supersedes, hash_v";
+
+/// A CTE selecting the latest revision of each page of the article $1 (see Xtchr::revise_article_page),
+/// with the p_id_immut of the first revision as root, which orders the pages as they were first written 
+const LATEST_PAGES: &str = "WITH RECURSIVE lineage AS (
+        SELECT p_id_immut, p_id_immut AS root FROM pages_immut WHERE a_id_immut = $1 AND supersedes IS NULL
+        UNION ALL
+        SELECT later.p_id_immut, lineage.root FROM pages_immut later INNER JOIN lineage ON later.supersedes = lineage.p_id_immut
+    ), latest AS (
+        SELECT p_id_immut, root FROM lineage 
+        WHERE NOT EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = lineage.p_id_immut)
+    )";
+
+/// Build an ArticlePage wrapped in XtchdContent from a pages_immut row selecting PAGE_COLUMNS.
+/// This fails if the page does not have exactly one source column set 
+fn page_from_row(row: &tokio_postgres::Row) -> Result<XtchdContent<xrows::ArticlePage>, tokio_postgres::Error> {
+    let source: xrows::PageSrc = row.try_get(5)?;
+    let content = xrows::ArticlePage{p_id_draft: row.get(1), p_id_immut: row.get(2), a_id_immut: row.get(3), paragraphs: row.get(4), source, 
+        supersedes: row.get(9), hash_v: row.get(10)};
+    Ok(XtchdContent::new(row.get(0), row.get(6), row.get(7), content, row.get(8)))
+}
+
+
+/// Build XtchdContent from the first column of a row returned by a ChainTable::xtchd_sql_query() without checking its hash,
+/// so verification can report a tampered row rather than failing to read it (see XtchdContent::from_sql)
+fn unchecked_from_row<T: Xtchable + DeserializeOwned>(row: &tokio_postgres::Row) -> Result<XtchdContent<T>, tokio_postgres::Error> {
+    row.try_get::<_, XtchdSQL<T>>(0).map(XtchdContent::from_sql)
+}
+
+
+/// An article title as written, with its HashChainLink rebuilt from the stored prior_sha256 and write_timestamp 
+async fn article_title_link<C: GenericClient>(c: &C, a_id_immut: i32) -> Result<Option<(xrows::ArticleTitle, HashChainLink)>, tokio_postgres::Error> {
+    let query = "SELECT a_id_draft, a_id_immut, auth_id, title, prior_sha256, write_timestamp
+        FROM titles_immut WHERE a_id_immut = $1";
+    let rows = c.query(query, &[&a_id_immut]).await?;
+    let row = match rows.get(0) {
+        Some(val) => val,
+        None => return Ok(None),
+    };
+    let a_id_draft: String = row.get(0);
+    let a_id_immut: i32 = row.get(1);
+    let auth_id: i32 = row.get(2);
+    let title: String = row.get(3);
+    let prior_sha256: String = row.get(4);
+    let write_timestamp: DateTime<Utc> = row.get(5);
+    let art_title = xrows::ArticleTitle{a_id_draft, a_id_immut, auth_id, title};
+    let hclink = HashChainLink::from_timestamp(&prior_sha256, write_timestamp, &art_title);
+    Ok(Some((art_title, hclink)))
+}
+
+/// Xtchr::tombstone_for() on any client, i.e. within a snapshot 
+async fn tombstone_in<C: GenericClient>(c: &C, table: &str, id: i32) -> Result<Option<xrows::Tombstone>, tokio_postgres::Error> {
+    let query = "SELECT tomb_id, reason FROM tombstones WHERE target_table = $1 AND target_id = $2";
+    let rows = c.query(query, &[&table, &id]).await?;
+    Ok(rows.get(0).map(|row| xrows::Tombstone{tomb_id: row.get(0), target_table: table.to_string(), target_id: id, reason: row.get(1)}))
+}
+
+/// Xtchr::author_detail() on any client, i.e. within a snapshot (see Xtchr::snapshot) 
+async fn author_detail_in<C: GenericClient>(c: &C, auth_id: i32, mode: VerifyMode) -> Result<views::AuthorDetail, XtchdError> {
+    let query = "SELECT prior_id, name, prior_sha256, write_timestamp, new_sha256, authored, current_name
+        FROM author_detail WHERE auth_id = $1";
+    let stmt = c.prepare_cached(query).await?;
+    let rows = c.query(&stmt, &[&auth_id]).await?;
+    let row = match rows.get(0) {
+        Some(val) => val,
+        None => return Err(XtchdError::NotFound("missing row in query for author_detail()".to_string())),
+    };
+    let prior_id: Option<i32> = row.get(0);
+    let name: String = row.get(1);
+    let prior_sha256: String = row.get(2);
+    let write_timestamp: DateTime<Utc> = row.get(3);
+    let new_sha256: String = row.get(4);
+    let articles:  Vec<views::NameId>  = row.get(5);
+    let current_name: String = row.get(6);
+    let redacted = tombstone_in(c, "authors", auth_id).await?;
+    let (name, current_name) = match redacted {
+        Some(_) => (xrows::REDACTED.to_string(), xrows::REDACTED.to_string()),
+        None => (name, current_name),
+    };
+    let content = xrows::Author{auth_id, name};
+    let author = XtchdContent::new(prior_id, prior_sha256, write_timestamp, content, new_sha256);
+    if mode == VerifyMode::Recompute && redacted.is_none() {
+        author.verify()?;
+    }
+    Ok(views::AuthorDetail{author, name: current_name, articles, redacted})
+}
+
+/// Xtchr::article_detail() on any client, i.e. within a snapshot (see Xtchr::snapshot) 
+async fn article_detail_in<C: GenericClient>(c: &C, a_id_immut: i32, mode: VerifyMode) -> Result<views::ArticleDetail, XtchdError> {
+    let query = "SELECT prior_id, a_id_draft, auth_id, title, prior_sha256, write_timestamp, new_sha256
+        FROM titles_immut WHERE a_id_immut = $1";
+    let rows = c.query(query, &[&a_id_immut]).await?;
+    let row = match rows.get(0) {
+        Some(val) => val,
+        None => return Err(XtchdError::NotFound("missing row in query for article_detail()".to_string())),
+    };
+    let redacted = tombstone_in(c, "titles_immut", a_id_immut).await?;
+    if redacted.is_some() {
+        let content = xrows::ArticleTitle{a_id_draft: row.get(1), a_id_immut, auth_id: row.get(2), title: xrows::REDACTED.to_string()};
+        let title = XtchdContent::new(row.get(0), row.get(4), row.get(5), content, row.get(6));
+        return Ok(views::ArticleDetail{title, pages: Vec::new(), redacted})
+    }
+    let content = xrows::ArticleTitle{a_id_draft: row.get(1), a_id_immut, auth_id: row.get(2), title: row.get(3)};
+    let title = XtchdContent::new(row.get(0), row.get(4), row.get(5), content, row.get(6));
+    // the cited article and screenshot (if any) are joined with renamed columns, so PAGE_COLUMNS remain unambiguous 
+    let query = format!("{} SELECT {}, cited_id, cited_title, cited_sha256, thumb_img_id, thumb_src FROM pages_immut 
+        INNER JOIN latest USING (p_id_immut)
+        LEFT JOIN (SELECT a_id_immut AS cited_id, new_sha256 AS cited_sha256,
+            CASE WHEN redacted('titles_immut', a_id_immut) 
+            THEN $2 ELSE title END AS cited_title
+            FROM titles_immut) cited ON cited.cited_id = refs_a_id_immut
+        LEFT JOIN (SELECT img_id AS thumb_img_id, src_thmb AS thumb_src FROM images_immut) thumb ON thumb.thumb_img_id = img_id
+        ORDER BY latest.root ASC", LATEST_PAGES, PAGE_COLUMNS);
+    let rows = c.query(&query, &[&a_id_immut, &xrows::REDACTED]).await?;
+    let mut pages = Vec::new();
+    for row in rows.iter() {
+        let page = page_from_row(row)?;
+        let cited_id: Option<i32> = row.get(11);
+        let cited = cited_id.map(|id| views::NameId{id, name: row.get(12)});
+        let thumb_img_id: Option<i32> = row.get(14);
+        let source_image = thumb_img_id.map(|img_id| xrows::ImageThumbnail{img_id, src_thmb: row.get(15)});
+        pages.push(views::PageDetail{page, cited, cited_sha256: row.get(13), source_image});
+    }
+    if mode == VerifyMode::Recompute {
+        title.verify()?;
+        for page in pages.iter() {
+            page.page.verify()?;
+        }
+    }
+    Ok(views::ArticleDetail{title, pages, redacted})
+}
+
+/// Xtchr::article_document() on any client, i.e. within a snapshot (see Xtchr::snapshot) 
+async fn article_document_in<C: GenericClient>(c: &C, a_id_immut: i32) -> Result<views::ArticleDocument, XtchdError> {
+    let detail = article_detail_in(c, a_id_immut, VerifyMode::Trust).await?;
+    if detail.redacted.is_some() {
+        return Err(XtchdError::InvalidArgument(format!("article {} is redacted", a_id_immut)))
+    }
+    let author = author_detail_in(c, detail.title.content.auth_id, VerifyMode::Trust).await?.author;
+    let pages = detail.pages.into_iter().map(|page| page.page).collect();
+    let mut doc = views::ArticleDocument{title: detail.title, author, pages, doc_sha256: String::new()};
+    doc.doc_sha256 = doc.compute_sha256();
+    Ok(doc)
+}
+
+/// End a snapshot (see Xtchr::snapshot), passing through the result of the reads made within it.
+/// If a read failed the snapshot is rolled back, and that error returned even if the rollback fails too 
+async fn end_snapshot<R>(tx: deadpool_postgres::Transaction<'_>, read: Result<R, XtchdError>) -> Result<R, XtchdError> {
+    match read {
+        Ok(val) => {
+            tx.commit().await?;
+            Ok(val)
+        },
+        Err(e) => {
+            if let Err(rollback) = tx.rollback().await {
+                tracing::warn!(error = %rollback, "failed to roll back a snapshot after a failed read");
+            }
+            Err(e)
+        },
+    }
+}
+
+
+/// Whether connections to Postgres are encrypted, set by the PSQL_SSLMODE environment variable 
+/// using the same names as libpq: 'require', 'verify-ca', and 'verify-full' use TLS (which requires the "tls" feature),
+/// anything else (including leaving it unset, typical for local development) does not.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SslMode {
+    Disable,
+    Require,
+}
+
+impl SslMode {
+    pub fn from_env() -> Self {
+        SslMode::parse(&std::env::var("PSQL_SSLMODE").unwrap_or_default())
+    }
+
+    /// Parse a libpq sslmode, i.e. 'require'
+    pub fn parse(sslmode: &str) -> Self {
+        match sslmode.to_lowercase().as_str() {
+            "require" | "verify-ca" | "verify-full" => SslMode::Require,
+            _ => SslMode::Disable,
+        }
+    }
+}
+
+
+/// Everything needed to build a Pool, for setting one up programmatically (see Pool::from_config) 
+/// rather than from environment variables 
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolConfig {
+    pub host: String,
+    pub port: u16,
+    pub password: Option<String>,
+    pub user: String,
+    pub dbname: String,
+    pub ssl_mode: SslMode,
+    pub image_limits: xrows::ImageLimits,
+    pub page_limits: xrows::PageLimits,
+    /// Postgres cancels any statement running longer than this, so one slow query cannot hold a pooled connection 
+    /// indefinitely: the cancelled statement returns XtchdError::Timeout from methods returning XtchdError.
+    /// Methods returning PachyDarn (i.e. the list and search reads) are cancelled the same way, but report it as any other database error 
+    pub statement_timeout: std::time::Duration,
+    /// The most connections the pool will open: i.e. larger for a high-concurrency ingest job than for a small read service.
+    /// None leaves it to deadpool's default (four per CPU core) 
+    pub max_size: Option<usize>,
+}
+
+/// The default PoolConfig.statement_timeout 
+pub const DEFAULT_STATEMENT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+impl PoolConfig {
+    /// Read the config from the same environment variables as Pool::new_from_env(), each preceded by the prefix:
+    /// i.e. with prefix "XTCHD_RO_" the host is read from XTCHD_RO_PSQL_HOST. 
+    /// The statement_timeout is read from PSQL_STATEMENT_TIMEOUT_MS in milliseconds (see DEFAULT_STATEMENT_TIMEOUT), 
+    /// and the max_size from PSQL_POOL_SIZE (None if unset).
+    /// Image and page size limits are not prefixed: see xrows::ImageLimits::from_env() and xrows::PageLimits::from_env()
+    pub fn from_env_prefixed(prefix: &str) -> Self {
+        let var = |name: &str| std::env::var(format!("{}{}", prefix, name)).ok();
+        PoolConfig{
+            host: var("PSQL_HOST").unwrap_or_else(|| "127.0.0.1".to_string()),
+            port: var("PSQL_PORT").and_then(|port| port.parse().ok()).unwrap_or(5432),
+            password: var("PSQL_PW"),
+            user: var("PSQL_USER").unwrap_or_else(|| "postgres".to_string()),
+            dbname: var("PSQL_DB").unwrap_or_else(|| "postgres".to_string()),
+            ssl_mode: SslMode::parse(&var("PSQL_SSLMODE").unwrap_or_default()),
+            image_limits: xrows::ImageLimits::from_env(),
+            page_limits: xrows::PageLimits::from_env(),
+            statement_timeout: var("PSQL_STATEMENT_TIMEOUT_MS").and_then(|ms| ms.parse().ok())
+                .map_or(DEFAULT_STATEMENT_TIMEOUT, std::time::Duration::from_millis),
+            max_size: var("PSQL_POOL_SIZE").and_then(|size| size.parse().ok()).filter(|&size| size > 0),
+        }
+    }
+
+    /// The equivalent deadpool_postgres::Config.
+    /// Every connection uses the UTC time zone, as the hash CHECK constraints format write_timestamp in the session time zone,
+    /// and has the statement_timeout. The pool opens at most max_size connections, if it is set 
+    pub fn deadpool_config(&self) -> deadpool_postgres::Config {
+        let mut cfg = deadpool_postgres::Config::new();
+        cfg.pool = self.max_size.map(deadpool_postgres::PoolConfig::new);
+        cfg.options = Some(format!("-c TimeZone=UTC -c statement_timeout={}", self.statement_timeout.as_millis()));
+        cfg.host = Some(self.host.clone());
+        cfg.port = Some(self.port);
+        cfg.password = self.password.clone();
+        cfg.user = Some(self.user.clone());
+        cfg.dbname = Some(self.dbname.clone());
+        cfg
+    }
+}
+
+
+/// A snapshot of connection pool saturation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PoolStats {
+    /// the maximum number of connections the pool will open 
+    pub max_size: usize,
+    /// the number of connections currently open
+    pub size: usize,
+    /// open connections which are idle, waiting to be used
+    pub available: usize,
+    /// open connections which are currently held by an Xtchr
+    pub in_use: usize,
+    /// callers waiting for a connection because none are available 
+    pub waiting: usize,
+}
+
+
+/// Warms the autocomplete cache for one CachedAutoComp type to its prewarm_depth. 
+/// RedisCache is the implementation used in production: tests can substitute a fake
+pub trait CacheWarmer {
+    /// Warm the cache for T, returning the number of keys warmed
+    fn warm<U, T: CachedAutoComp<U>>(&mut self, c: &ClientNoTLS) -> impl Future<Output = Result<usize, PachyDarn>>;
+}
+
+/// Looks up autocomplete results for one CachedAutoComp type from a cache.
+/// RedisCache is the implementation used in production: tests can substitute a fake 
+pub trait AutoCompCache {
+    /// The cached results for the phrase: an Err indicates the cache itself failed (i.e. Redis is down)
+    fn autocomp<U, T: CachedAutoComp<U>>(&mut self, c: &ClientNoTLS, phrase: &str) -> impl Future<Output = Result<Vec<WhoWhatWhere<U>>, GenericError>>;
+}
+
+/// The autocomplete caches in Redis, warmed with pachydurable::redis::warm_the_cache()
+/// and read with pachydurable::redis::cached_autocomp()
+pub struct RedisCache<'a> {
+    pub conn: &'a mut predis::RedisConn,
+}
+
+impl<'a> CacheWarmer for RedisCache<'a> {
+    async fn warm<U, T: CachedAutoComp<U>>(&mut self, c: &ClientNoTLS) -> Result<usize, PachyDarn> {
+        predis::warm_the_cache::<U, T>(c, self.conn).await
+    }
+}
+
+impl<'a> AutoCompCache for RedisCache<'a> {
+    async fn autocomp<U, T: CachedAutoComp<U>>(&mut self, c: &ClientNoTLS, phrase: &str) -> Result<Vec<WhoWhatWhere<U>>, GenericError> {
+        predis::cached_autocomp::<U, T>(c, self.conn, phrase).await
+    }
+}
+
+/// The former name of RedisCache, from before it also read the cache 
+#[deprecated(note = "renamed to RedisCache")]
+pub type RedisWarmer<'a> = RedisCache<'a>;
+
+/// The number of keys warmed for each cached type by Xtchr::warm_all_caches()
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct WarmSummary {
+    pub authors: usize,
+    pub images: usize,
+    pub topics: usize,
+}
+
 
 pub struct Pool {
     pub pool: ConnPoolNoTLS,
+    /// limits on the size of images written by any Xtchr from this pool 
+    pub image_limits: xrows::ImageLimits,
+    /// limits on the size of pages written by any Xtchr from this pool 
+    pub page_limits: xrows::PageLimits,
+    /// the tables with hash chain integrity known to each Xtchr from this pool 
+    pub content_tables: ContentTables,
 }
 
 impl Pool {
@@ -48,16 +661,76 @@ impl Pool {
     /// PSQL_PW,    password 
     /// PSQL_USER,  user        defaults to 'postgres'
     /// PSQL_DB,    database    defaults to 'postgres'
+    /// PSQL_SSLMODE,           defaults to no TLS: see SslMode
+    /// PSQL_STATEMENT_TIMEOUT_MS,  defaults to 30 seconds: see PoolConfig.statement_timeout
+    /// PSQL_POOL_SIZE,         defaults to deadpool's default: see PoolConfig.max_size
+    /// Image and page size limits are read from the environment as well: see xrows::ImageLimits::from_env() and xrows::PageLimits::from_env()
     pub async fn new_from_env() -> Self {
-        let pool = pool_no_tls_from_env().await.unwrap();
+        Pool::new_from_env_prefixed("").await
+    }
+
+    /// Like new_from_env(), but with each variable preceded by the prefix: i.e. XTCHD_RO_PSQL_HOST for prefix "XTCHD_RO_".
+    /// This allows one process to use pools for more than one database, i.e. a read replica and a primary 
+    pub async fn new_from_env_prefixed(prefix: &str) -> Self {
+        Pool::from_config(PoolConfig::from_env_prefixed(prefix)).await
+    }
+
+    /// Instantiate a new pool from a PoolConfig
+    pub async fn from_config(config: PoolConfig) -> Self {
+        let pool = match config.ssl_mode {
+            SslMode::Disable => config.deadpool_config().create_pool(Some(deadpool_postgres::Runtime::Tokio1), tokio_postgres::NoTls).unwrap(),
+            #[cfg(feature = "tls")]
+            SslMode::Require => crate::tls::pool_tls(config.deadpool_config()).unwrap(),
+            #[cfg(not(feature = "tls"))]
+            SslMode::Require => panic!("PSQL_SSLMODE requires TLS but xtchd was built without the \"tls\" feature"),
+        };
         let _c = pool.get().await.unwrap(); // ensure you can connect
-        Pool{pool}
+        Pool{pool, image_limits: config.image_limits, page_limits: config.page_limits, content_tables: ContentTables::default()}
+    }
+
+    /// Override the image size limits for this pool 
+    pub fn with_image_limits(mut self, image_limits: xrows::ImageLimits) -> Self {
+        self.image_limits = image_limits;
+        self
+    }
+
+    /// Override the page size limits for this pool 
+    pub fn with_page_limits(mut self, page_limits: xrows::PageLimits) -> Self {
+        self.page_limits = page_limits;
+        self
+    }
+
+    /// Register the table for a content type defined outside xtchd with each Xtchr from this pool (see ContentTable)
+    pub fn with_content_table<T: ContentTable>(mut self) -> Self {
+        self.content_tables.register::<T>();
+        self
+    }
+
+
+    /// Report how saturated the pool is, i.e. to diagnose callers waiting on (or failing to get) a connection
+    pub fn stats(&self) -> PoolStats {
+        let status = self.pool.status();
+        // a negative number of available connections indicates callers waiting for one 
+        let available = status.available.max(0) as usize;
+        let waiting = (-status.available).max(0) as usize;
+        PoolStats{max_size: status.max_size, size: status.size, available, in_use: status.size - available, waiting}
+    }
+
+    /// Record the pool stats as gauges with the metrics crate, i.e. call this periodically or before each scrape 
+    #[cfg(feature = "metrics")]
+    pub fn record_metrics(&self) {
+        let stats = self.stats();
+        metrics::gauge!("xtchd_pool_max_size", stats.max_size as f64);
+        metrics::gauge!("xtchd_pool_size", stats.size as f64);
+        metrics::gauge!("xtchd_pool_available", stats.available as f64);
+        metrics::gauge!("xtchd_pool_in_use", stats.in_use as f64);
+        metrics::gauge!("xtchd_pool_waiting", stats.waiting as f64);
     }
 
 
     pub async fn get(&self) -> Result<Xtchr, PachyDarn> {
         let c = self.pool.get().await.unwrap();
-        Ok(Xtchr{c})
+        Ok(Xtchr{c, image_limits: self.image_limits, page_limits: self.page_limits, content_tables: self.content_tables.clone()})
     }
 
 }
@@ -65,158 +738,3710 @@ impl Pool {
 /// The Xtrcr struct is essentially a Postgres client with special methods implemented on it
 /// To write rows with hash chained integrity
 pub struct Xtchr {
-    pub c: ClientNoTLS
+    pub c: ClientNoTLS,
+    pub image_limits: xrows::ImageLimits,
+    pub page_limits: xrows::PageLimits,
+    pub content_tables: ContentTables,
 }
 
 impl Xtchr {
+    /// Register the table for a content type defined outside xtchd with this Xtchr only: see Pool::with_content_table()
+    pub fn register_content_table<T: ContentTable>(&mut self) {
+        self.content_tables.register::<T>();
+    }
+
+    /// Look up a registered table by name, i.e. one of the CHAIN_TABLES. 
+    /// Table names cannot be passed as query parameters, so this also guards against SQL injection
+    pub fn content_table(&self, name: &str) -> Result<&'static ChainTable, XtchdError> {
+        self.content_tables.get(name).map(|entry| entry.table)
+    }
+
+    /// content_table(), checking the table holds content of type T, so a write cannot hash one type into another's chain 
+    fn content_table_of<T: Xtchable>(&self, name: &str) -> Result<&'static ChainTable, XtchdError> {
+        let table = self.content_table(name)?;
+        if T::dtype() != table.dtype {
+            return Err(XtchdError::InvalidArgument(format!("'{}' holds {} rather than {}", table.name, table.dtype, T::dtype())))
+        }
+        Ok(table)
+    }
+
+
+
+    /// Return the exact string that is hashed when content is written with the given prior_sha256 and write_timestamp.
+    /// When a CHECK CONSTRAINT rejects a write, diff this against the CONCAT(...) expression evaluated in Postgres 
+    /// to see which field is formatted differently.
+    pub fn debug_state_string<T: Xtchable>(&self, content: &T, prior_sha256: &str, ts: DateTime<Utc>) -> String {
+        HashChainLink::from_timestamp(prior_sha256, ts, content).string_to_hash
+    }
+
+    /// Get the detail for one author, specified by auth_id.
+    /// With VerifyMode::Recompute, the author's hash is recomputed and an IntegrityError returned if it does not match 
+    pub async fn author_detail(&self, auth_id: i32, mode: VerifyMode) -> Result<views::AuthorDetail, XtchdError> {
+        author_detail_in(&*self.c, auth_id, mode).await
+    }
+
+
+    /// Get the title and all pages for one article, specified by a_id_immut.
+    /// Only the latest revision of each page is returned, in the order the pages were first written
+    /// With VerifyMode::Recompute, the hash of the title and every page is recomputed and an IntegrityError returned
+    /// for the first one that does not match.
+    /// A redacted article is returned with a placeholder title (which is not recomputed) and no pages 
+    pub async fn article_detail(&self, a_id_immut: i32, mode: VerifyMode) -> Result<views::ArticleDetail, XtchdError> {
+        article_detail_in(&*self.c, a_id_immut, mode).await
+    }
+
 
+    /// A whole article (its title, author, and the latest revision of every page) as one document with a doc_sha256 
+    /// over all of them, i.e. for archival or sharing: see integrity::verify_article_document().
+    /// A redacted article cannot be made into a document, returning XtchdError::InvalidArgument
+    pub async fn article_document(&self, a_id_immut: i32) -> Result<views::ArticleDocument, XtchdError> {
+        article_document_in(&*self.c, a_id_immut).await
+    }
+
+
+    /// As article_detail(), but every query sees one snapshot of the database (see snapshot()), 
+    /// so a page written part way through cannot appear alongside a title read before it 
+    pub async fn article_detail_consistent(&mut self, a_id_immut: i32, mode: VerifyMode) -> Result<views::ArticleDetail, XtchdError> {
+        let tx = self.snapshot().await?;
+        let detail = article_detail_in(&tx, a_id_immut, mode).await;
+        end_snapshot(tx, detail).await
+    }
+
+    /// As article_document(), but every query sees one snapshot of the database (see snapshot()), 
+    /// so the doc_sha256 is computed over a title, author, and pages which all existed together 
+    pub async fn article_document_consistent(&mut self, a_id_immut: i32) -> Result<views::ArticleDocument, XtchdError> {
+        let tx = self.snapshot().await?;
+        let doc = article_document_in(&tx, a_id_immut).await;
+        end_snapshot(tx, doc).await
+    }
+
+    /// A read-only REPEATABLE READ transaction, in which every query sees the database as of the first of them.
+    /// The transaction borrows the connection, so no other query can run on it (and so inside the snapshot) until it ends 
+    async fn snapshot(&mut self) -> Result<deadpool_postgres::Transaction<'_>, tokio_postgres::Error> {
+        self.c.build_transaction().isolation_level(IsolationLevel::RepeatableRead).read_only(true).start().await
+    }
 
 
+    /// Get the detail for the content with this data_type and id, i.e. from a WhoWhatWhere returned by autocomplete, 
+    /// serialized to JSON so the caller need not branch on the type. The data_type may be the WhoWhatWhere data_type
+    /// or the dtype of the Xtchable struct, i.e. 'author' or 'Author'. Images, YouTube channels and videos are given by 
+    /// their dtype ('Image', 'YoutubeChannel', 'YoutubeVideo') and yield their single checked row. Content is read with VerifyMode::Trust 
+    pub async fn detail(&self, data_type: &str, id: i32) -> Result<serde_json::Value, XtchdError> {
+        let value = match data_type {
+            "author" | "Author" => serde_json::to_value(self.author_detail(id, VerifyMode::Trust).await?),
+            "article" | "ArticleTitle" => serde_json::to_value(self.article_detail(id, VerifyMode::Trust).await?),
+            "Image" => serde_json::to_value(self.content_row::<xrows::ImmutableImage>(id).await?),
+            "YoutubeChannel" => serde_json::to_value(self.content_row::<xrows::YoutubeChannel>(id).await?),
+            "YoutubeVideo" => serde_json::to_value(self.content_row::<xrows::YoutubeVideo>(id).await?),
+            _ => return Err(XtchdError::InvalidArgument(format!("detail() does not support data_type '{}'", data_type))),
+        };
+        Ok(value.expect("details always serialize to JSON"))
+    }
+
+    /// The row of a built-in table with this id (see chain_segment), for detail(): a missing or redacted row is XtchdError::NotFound 
+    async fn content_row<T: Xtchable + ContentTable + DeserializeOwned>(&self, id: i32) -> Result<XtchdContent<T>, XtchdError> {
+        let table = T::chain_table();
+        self.chain_segment::<T>(table.name, id, id).await?.pop()
+            .ok_or_else(|| XtchdError::NotFound(format!("no row of {} with {} = {}", table.name, table.id_column, id)))
+    }
 
-    /// Get the detail for one author, specified by auth_id
-    pub async fn author_detail(&self, auth_id: i32) -> Result<views::AuthorDetail, PachyDarn> {
-        let query = "SELECT prior_id, name, prior_sha256, write_timestamp, new_sha256, authored
-            FROM author_detail WHERE auth_id = $1";
-        let rows = self.c.query(query, &[&auth_id]).await?;
+
+    /// Get the detail for one topic, specified by tkey, with the articles and videos that mention it.
+    /// Content is ordered by the number of mentions, then most recent first  
+    pub async fn topic_detail(&self, tkey: &str) -> Result<views::TopicDetail, PachyDarn> {
+        let query = "SELECT t.pos, t.name, t.count,
+            COALESCE((SELECT ARRAY_AGG(JSON_BUILD_OBJECT('id', a_id_immut, 'name', title) ORDER BY mentions DESC, a_id_immut DESC)
+                FROM (SELECT ti.a_id_immut, ti.title, COUNT(*) AS mentions
+                    FROM apara_ment_topic amt
+                    INNER JOIN titles_immut ti ON amt.art_id = ti.a_id_immut
+                    WHERE amt.tkey = t.tkey
+                    GROUP BY ti.a_id_immut, ti.title) arts), '{}') AS articles,
+            COALESCE((SELECT ARRAY_AGG(JSON_BUILD_OBJECT('id', vid_id, 'name', title) ORDER BY mentions DESC, vid_id DESC)
+                FROM (SELECT yv.vid_id, yv.title, COUNT(*) AS mentions
+                    FROM vid_ment_topic vmt
+                    INNER JOIN youtube_videos yv ON vmt.vid_id = yv.vid_id
+                    WHERE vmt.tkey = t.tkey
+                    GROUP BY yv.vid_id, yv.title) vids), '{}') AS videos
+            FROM nlp_topics t WHERE t.tkey = $1";
+        let rows = self.c.query(query, &[&tkey]).await?;
         let row = match rows.get(0) {
             Some(val) => val,
-            None => return Err(PachyDarn::from(MissingRowError::from_str("missing row in query for author_detail()"))),
+            None => return Err(PachyDarn::from(MissingRowError::from_str("missing row in query for topic_detail()"))),
         };
-        let prior_id: Option<i32> = row.get(0);
-        let name: String = row.get(1);
-        let prior_sha256: String = row.get(2);
-        let write_timestamp: DateTime<Utc> = row.get(3);
-        let new_sha256: String = row.get(4);
-        let articles:  Vec<views::NameId>  = row.get(5);
-        let content = xrows::Author{auth_id, name};
-        let author = XtchdContent::new(prior_id, prior_sha256, write_timestamp, content, new_sha256);
-        Ok(views::AuthorDetail{author, articles})
+        let topic = views::Topic{tkey: tkey.to_string(), pos: row.get(0), name: row.get(1), count: row.get(2)};
+        let articles: Vec<views::NameId> = row.get(3);
+        let videos: Vec<views::NameId> = row.get(4);
+        Ok(views::TopicDetail{topic, articles, videos})
     }
 
-    // add an author
-    pub async fn add_author(&self, name: &str) -> Result<(xrows::Author, HashChainLink), PachyDarn> {
-        let last_author = get_last_row(&self.c, "SELECT auth_id, new_sha256 FROM authors ORDER BY auth_id DESC LIMIT 1").await.unwrap();
-        let auth_id = last_author.next_id();
-        let name = name.to_string();
-        let author = xrows::Author{auth_id, name};
-        let hclink = HashChainLink::new(&last_author.prior_sha256, &author);
-        let _x = self.c.execute("INSERT INTO authors
-            (                     prior_id,         auth_id,        name,               prior_sha256,         write_timestamp,         new_sha256) 
-                VALUES ($1, $2, $3, $4, $5, $6)", 
-            &[&last_author.prior_id, &author.auth_id, &author.name, &last_author.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
-        ).await.unwrap();
-        Ok((author, hclink))
+
+    /// Get up to count pages of an article, starting from the page at from_ordinal (where 0 is the first page).
+    /// This lets a reader fetch a long article page-by-page. Each page remains independently verifiable.
+    /// As with article_detail(), only the latest revision of each page is returned, and a redacted article has no pages
+    pub async fn article_pages_range(&self, a_id_immut: i32, from_ordinal: i32, count: i32) -> Result<Vec<XtchdContent<xrows::ArticlePage>>, XtchdError> {
+        if count <= 0 || from_ordinal < 0 {
+            return Err(XtchdError::InvalidArgument(format!("article_pages_range() requires count > 0 and from_ordinal >= 0, got count={} from_ordinal={}", count, from_ordinal)))
+        }
+        let query = format!("{} SELECT {} FROM pages_immut INNER JOIN latest USING (p_id_immut) 
+            WHERE NOT redacted('titles_immut', $1) AND NOT redacted('pages_immut', p_id_immut)
+            ORDER BY latest.root ASC OFFSET $2 LIMIT $3", LATEST_PAGES, PAGE_COLUMNS);
+        let rows = self.c.query(&query, &[&a_id_immut, &(from_ordinal as i64), &(count as i64)]).await?;
+        Ok(rows.iter().map(page_from_row).collect::<Result<Vec<_>, _>>()?)
     }
 
 
-    // add an article (but not the text thereof)
-    pub async fn add_article_title(&self, auth_id: i32, a_id_draft: &str, title: &str) -> Result<(xrows::ArticleTitle, HashChainLink), PachyDarn> {
-        let last_article = get_last_row(&self.c, "SELECT a_id_immut, new_sha256 FROM titles_immut ORDER BY a_id_immut DESC LIMIT 1").await.unwrap();
-        let a_id_immut = last_article.next_id();
-        let title = title.to_string();
-        let art_title = xrows::ArticleTitle{a_id_immut, auth_id, title, a_id_draft: a_id_draft.to_owned()};
-        let hclink = HashChainLink::new(&last_article.prior_sha256, &art_title);
-        let _x = self.c.execute("INSERT INTO titles_immut
-            (                   prior_id,  a_id_draft, a_id_immut, auth_id,            title,               prior_sha256,         write_timestamp,          new_sha256)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ",
-        &[&last_article.prior_id, &a_id_draft, &a_id_immut, &auth_id, &art_title.title, &last_article.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256() ]
-        ).await.unwrap();
-        Ok((art_title, hclink))
+    /// The full text of an article, i.e. the input to the topic extractor: every paragraph of the latest revision of each page, 
+    /// in order, separated by a blank line. Paragraphs are already plain text so nothing is stripped.
+    /// As with article_detail(), a redacted article has no pages, so its text is empty 
+    pub async fn article_plaintext(&self, a_id_immut: i32) -> Result<String, PachyDarn> {
+        let query = format!("{} SELECT paragraphs FROM pages_immut INNER JOIN latest USING (p_id_immut)
+            WHERE NOT redacted('titles_immut', $1) AND NOT redacted('pages_immut', p_id_immut)
+            ORDER BY latest.root ASC", LATEST_PAGES);
+        let stmt = self.c.prepare_cached(&query).await?;
+        let rows = self.c.query(&stmt, &[&a_id_immut]).await?;
+        let paragraphs: Vec<String> = rows.iter().flat_map(|row| row.get::<_, Vec<String>>(0)).collect();
+        Ok(paragraphs.join("\n\n"))
     }
 
 
-    /// add a (new) page to an article 
-    pub async fn add_article_page(&self, a_id_immut: i32, p_id_draft: &str, paragraphs: Vec<String>, source: xrows::PageSrc) -> Result<(xrows::ArticlePage, HashChainLink), PachyDarn> {
-        let last_page = get_last_row(&self.c, "SELECT p_id_immut, new_sha256 FROM pages_immut ORDER BY p_id_immut DESC LIMIT 1").await.unwrap();
-        let p_id_immut = last_page.next_id();
-        let page = xrows::ArticlePage{a_id_immut, p_id_immut, paragraphs, source, p_id_draft: p_id_draft.to_owned()};
-        let hclink = HashChainLink::new(&last_page.prior_sha256, &page);
-        let (img_id, image_file, refs_a_id_immut) = &page.source.src_columns();
-        let _x = self.c.execute("INSERT INTO pages_immut
-            (               prior_id,  p_id_draft,  p_id_immut, a_id_immut,        paragraphs, img_id, image_file, refs_a_id_immut,                prior_sha256,         write_timestamp,           new_sha256)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ",
-        &[&last_page.prior_id, &p_id_draft, &p_id_immut, &a_id_immut, &page.paragraphs, &img_id, &image_file, &refs_a_id_immut, &last_page.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256() ]
-        ).await.unwrap();
-        Ok((page, hclink))
+    /// Export every row of a table, in id order, wrapped in XtchdContent.
+    /// The result can be serialized and verified by a third party without database access: see integrity::verify_bundle().
+    /// Each row is checked as it is read, so this fails on a tampered row: use verify_chain() to find it.
+    /// A redacted row (see tombstone()) is absent, so the export has a gap there which verify_bundle() reports as OutOfSequence:
+    /// verify each run of rows after a gap with integrity::verify_batch() from the prior_id and prior_sha256 of its first row 
+    pub async fn export_chain<T: Xtchable + DeserializeOwned>(&self, table: &str) -> Result<Vec<XtchdContent<T>>, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = table.xtchd_sql_query(&format!("WHERE NOT redacted('{}', {})", table.name, table.id_column));
+        let rows = self.c.query(&query, &[]).await?;
+        let chain = rows.iter().map(|row| row.try_get(0)).collect::<Result<Vec<XtchdContent<T>>, _>>()?;
+        Ok(chain)
     }
 
 
-    // create a new record for a youtube channel
-    pub async fn add_youtube_channel(&self, url: &str, name: &str) -> Result<(xrows::YoutubeChannel, HashChainLink), PachyDarn> {
-        let last_chan = get_last_row(&self.c, "SELECT chan_id, new_sha256 FROM youtube_channels ORDER BY chan_id DESC LIMIT 1").await.unwrap();
-        let chan_id = last_chan.next_id();
-        let url = url.to_lowercase();
-        let name = name.to_string();
-        let chan = xrows::YoutubeChannel{chan_id, url, name};
-        let hclink = HashChainLink::new(&last_chan.prior_sha256, &chan);
-        let _x = self.c.execute("INSERT INTO youtube_channels 
-            (                    prior_id, chan_id,       url,       name,             prior_sha256,        write_timestamp,           new_sha256)
-                VALUES ($1, $2, $3, $4, $5, $6, $7) ",
-            &[&last_chan.prior_id, &chan_id, &chan.url, &chan.name, &last_chan.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
-        ).await.unwrap();
-        Ok((chan, hclink))
+    /// Export the rows of a table from from_id to to_id inclusive, in id order, i.e. the new tail after syncing. 
+    /// The first row carries the prior_sha256 of the row before from_id, so a client holding that row's new_sha256 
+    /// can verify the segment against it with integrity::verify_batch() without fetching the rest of the chain.
+    /// As with export_chain(), a redacted row is absent 
+    pub async fn chain_segment<T: Xtchable + DeserializeOwned>(&self, table: &str, from_id: i32, to_id: i32) -> Result<Vec<XtchdContent<T>>, XtchdError> {
+        if from_id > to_id {
+            return Err(XtchdError::InvalidArgument(format!("chain_segment() requires from_id <= to_id, got from_id={} to_id={}", from_id, to_id)))
+        }
+        let table = self.content_table(table)?;
+        let query = table.xtchd_sql_query(&format!("WHERE {} BETWEEN $1 AND $2 AND NOT redacted('{}', {})", 
+            table.id_column, table.name, table.id_column));
+        let rows = self.c.query(&query, &[&from_id, &to_id]).await?;
+        let segment = rows.iter().map(|row| row.try_get(0)).collect::<Result<Vec<XtchdContent<T>>, _>>()?;
+        Ok(segment)
     }
 
 
-    // create a new record for a youtube video 
-    pub async fn add_youtube_video(&self, chan_id: i32, vid_pk: &str, title: &str, date_uploaded: &NaiveDate) -> Result<(xrows::YoutubeVideo, HashChainLink), PachyDarn> {
-        let last_vid = get_last_row(&self.c, "SELECT vid_id, new_sha256 FROM youtube_videos ORDER BY vid_id DESC LIMIT 1").await.unwrap();
-        let vid_id = last_vid.next_id();
-        let vid_pk = vid_pk.to_string();
-        let title = title.to_string();
-        let date_uploaded = date_uploaded.clone();
-        let video = xrows::YoutubeVideo{vid_id, vid_pk, chan_id, title, date_uploaded};
-        let hclink = HashChainLink::new(&last_vid.prior_sha256, &video);
-        let _x = self.c.execute("INSERT INTO youtube_videos 
-            (                  prior_id,  vid_id,         vid_pk,       chan_id,        title,        date_uploaded,           prior_sha256,         write_timestamp,           new_sha256)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
-                ON CONFLICT (vid_pk) DO NOTHING",
-            &[&last_vid.prior_id, &vid_id, &video.vid_pk, &video.chan_id, &video.title, &video.date_uploaded, &last_vid.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
-        ).await.unwrap();
-        Ok((video, hclink))
+    /// Write every article paragraph, in id order, as newline-delimited JSON (one xrows::ArticlePara per line), 
+    /// i.e. for rebuilding a search index or a backup. Rows are read through a server-side cursor a batch at a time,
+    /// so the table is never held in memory. Paragraphs which are redacted, or whose article is, are not written.
+    /// Returns the number of paragraphs written 
+    pub async fn export_paragraphs_ndjson(&mut self, mut writer: impl AsyncWrite + Unpin) -> Result<usize, XtchdError> {
+        let tx = self.c.transaction().await?;
+        tx.batch_execute("DECLARE export_paragraphs NO SCROLL CURSOR FOR SELECT apara_id, art_id, md FROM article_para 
+            WHERE NOT redacted('article_para', apara_id) AND NOT redacted('titles_immut', art_id) ORDER BY apara_id").await?;
+        let mut written = 0;
+        loop {
+            let rows = tx.query("FETCH 1000 FROM export_paragraphs", &[]).await?;
+            if rows.is_empty() {
+                break
+            }
+            for row in rows.iter() {
+                let para = xrows::ArticlePara{apara_id: row.get(0), art_id: row.get(1), md: row.get(2)};
+                let mut line = serde_json::to_vec(&para).expect("an ArticlePara always serializes to JSON");
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+                written += 1;
+            }
+        }
+        writer.flush().await?;
+        tx.commit().await?;
+        Ok(written)
     }
 
 
-    /// add a new immutable image/thumbnail pair, returning the img_id
-    pub async fn add_image_immutable(&self, pair: xrows::ImagePair) -> Result<i32, PachyDarn> {
-        let last_ref = get_last_row(&self.c, "SELECT img_id, new_sha256 FROM images_immut ORDER BY img_id DESC LIMIT 1").await.unwrap();
-        let img_id = last_ref.next_id();
-        let ii = xrows::ImmutableImage{img_id, pair};
-        let hclink = HashChainLink::new(&last_ref.prior_sha256, &ii);
-        let _x = self.c.execute("INSERT INTO images_immut 
-            (                  prior_id,  img_id,          src_full,          src_thmb,          alt,          url,          archive,           prior_sha256,         write_timestamp,          new_sha256) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
-            &[&last_ref.prior_id, &img_id, &ii.pair.src_full, &ii.pair.src_thmb, &ii.pair.alt, &ii.pair.url, &ii.pair.archive, &last_ref.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]).await?;
-        Ok(img_id)
+    /// Spot check one row of a table, specified by its id, against its immediate predecessor (id - 1).
+    /// This confirms the row's prior_sha256 matches the predecessor's new_sha256 and that the row's new_sha256 
+    /// recomputes from its content, without walking the chain from the first row.
+    pub async fn verify_row(&self, table: &str, id: i32) -> Result<RowVerification, XtchdError> {
+        let entry = self.content_tables.get(table)?;
+        (entry.verify_row)(self, entry.table, id).await
     }
 
+    /// verify_row() once the content type for the table is known 
+    async fn verify_row_as<T: Xtchable + DeserializeOwned>(&self, table: &ChainTable, id: i32) -> Result<RowVerification, XtchdError> {
+        let query = table.xtchd_sql_query(&format!("WHERE {} = $1", table.id_column));
+        let rows = self.c.query(&query, &[&id]).await?;
+        let row: XtchdContent<T> = match rows.get(0) {
+            Some(val) => unchecked_from_row(val)?,
+            None => return Err(XtchdError::NotFound("missing row in query for verify_row()".to_string())),
+        };
+        let expected_prior = match id {
+            0 => Some(GENESIS_SHA256.to_string()),
+            _ => {
+                let query = format!("SELECT new_sha256 FROM {} WHERE {} = $1", table.name, table.id_column);
+                self.c.query(&query, &[&(id - 1)]).await?.get(0).map(|prior| prior.get(0))
+            },
+        };
+        let prior_matches = expected_prior.as_deref() == Some(row.prior_sha256.as_str());
+        let hash_matches = row.verify().is_ok();
+        Ok(RowVerification{prior_matches, hash_matches})
+    }
 
-    /// add or update a new mutable image/thumbnail pair 
-    pub async fn add_image_mutable(&self, mi: &xrows::MutableImage) -> Result<(), PachyDarn> {
-        let _x = self.c.execute("INSERT INTO images_mut
-            (            id,          src_full,          src_thmb,          alt,          url) VALUES ($1, $2, $3, $4, $5)
-                ON CONFLICT(id) DO UPDATE SET src_full = $2, src_thmb = $3, alt = $4, url = $5",
-            &[&mi.id, &mi.pair.src_full, &mi.pair.src_thmb, &mi.pair.alt, &mi.pair.url]).await?;
-        Ok(())
+
+    /// Verify every row of a table from the first row on, as integrity::verify_bundle() does for an exported chain.
+    /// This is O(n): use verify_row() for spot checks, or verify_chain_batched() for large tables 
+    pub async fn verify_chain(&self, table: &str) -> Result<(), XtchdError> {
+        self.verify_chain_with(table, None).await
     }
 
-}
+    /// The same checks as verify_chain(), returning the same first error, but for large tables:
+    /// rows are fetched batch_size at a time (rather than the whole table being held in memory) and each batch is hashed 
+    /// in parallel on the rayon thread pool (see integrity::verify_batch) while the next batch is fetched 
+    pub async fn verify_chain_batched(&self, table: &str, batch_size: i64) -> Result<(), XtchdError> {
+        if batch_size <= 0 {
+            return Err(XtchdError::InvalidArgument(format!("verify_chain_batched() requires batch_size > 0, got {}", batch_size)))
+        }
+        self.verify_chain_with(table, Some(batch_size)).await
+    }
 
+    /// verify_chain() or verify_chain_batched(), dispatched by table name to the content type of the table 
+    async fn verify_chain_with(&self, table: &str, batch_size: Option<i64>) -> Result<(), XtchdError> {
+        let entry = self.content_tables.get(table)?;
+        (entry.verify_chain)(self, entry.table, batch_size).await
+    }
 
+    /// verify_chain() for every registered table (see ContentTable), in the order they were registered. 
+    /// Every table is verified even if an earlier one fails, so this returns the result for each 
+    pub async fn verify_all(&self) -> Vec<(&'static str, Result<(), XtchdError>)> {
+        let mut results = Vec::new();
+        for table in self.content_tables.tables() {
+            results.push((table.name, self.verify_chain(table.name).await));
+        }
+        results
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use tokio::runtime::Runtime;
+    /// Audit one YouTube channel: verify_row() for the channel and for each of its videos.
+    /// Channels and videos are separate chains, so this checks each row's link within its own chain 
+    pub async fn verify_channel(&self, chan_id: i32) -> Result<views::ChannelVerification, XtchdError> {
+        let channel = self.verify_row("youtube_channels", chan_id).await?;
+        let rows = self.c.query("SELECT vid_id FROM youtube_videos WHERE chan_id = $1 ORDER BY vid_id", &[&chan_id]).await?;
+        let mut failed_videos = Vec::new();
+        for row in rows.iter() {
+            let vid_id: i32 = row.get(0);
+            if !self.verify_row("youtube_videos", vid_id).await?.is_valid() {
+                failed_videos.push(vid_id);
+            }
+        }
+        Ok(views::ChannelVerification{chan_id, channel, videos_checked: rows.len(), failed_videos})
+    }
 
-    #[test]
-    fn test_init_author() {
-        // Test the author_detail function by getting the initia "seed" author
-        let rt = Runtime::new().unwrap();
-        rt.block_on(async {
-            let pool = Pool::new_from_env().await;
+    /// verify_chain() once the content type for the table is known 
+    async fn verify_chain_as<T: Xtchable + DeserializeOwned + Send + Sync + 'static>(&self, table: &ChainTable, batch_size: Option<i64>) -> Result<(), XtchdError> {
+        let batch_size = match batch_size {
+            Some(val) => val,
+            None => {
+                let rows = self.c.query(&table.xtchd_sql_query(""), &[]).await?;
+                let chain = rows.iter().map(unchecked_from_row).collect::<Result<Vec<XtchdContent<T>>, _>>()?;
+                return Ok(integrity::verify_bundle(&chain)?)
+            },
+        };
+        let query = format!("{} LIMIT $2", table.xtchd_sql_query(&format!("WHERE {} > $1", table.id_column)));
+        let stmt = self.c.prepare_cached(&query).await?;
+        let (mut prior_id, mut prior_sha256): (Option<i32>, String) = (None, GENESIS_SHA256.to_string());
+        let mut hashing: Option<tokio::task::JoinHandle<Result<(), integrity::IntegrityError>>> = None;
+        loop {
+            let after = prior_id.unwrap_or(-1);
+            let rows = self.c.query(&stmt, &[&after, &batch_size]).await?;
+            let batch = rows.iter().map(unchecked_from_row).collect::<Result<Vec<XtchdContent<T>>, _>>()?;
+            // the prior batch was hashed while this one was fetched: its errors come first 
+            if let Some(handle) = hashing.take() {
+                handle.await.expect("hashing a batch panicked")?;
+            }
+            let last = match batch.last() {
+                Some(last) => (Some(integrity::row_id(last)), last.new_sha256.clone()),
+                None => return Ok(()),
+            };
+            let complete = (batch.len() as i64) < batch_size;
+            let (batch_prior_id, batch_prior_sha256) = (prior_id, std::mem::replace(&mut prior_sha256, last.1));
+            prior_id = last.0;
+            let handle = tokio::task::spawn_blocking(move || integrity::verify_batch(&batch, batch_prior_id, &batch_prior_sha256));
+            if complete {
+                return Ok(handle.await.expect("hashing a batch panicked")?)
+            }
+            hashing = Some(handle);
+        }
+    }
+
+
+    /// The integrity status of each registered table (see ContentTable), i.e. for a public status page.
+    /// By default only the head of each chain is checked, reading no other rows: the head must pass verify_row and its prior_id 
+    /// must be its own id - 1, and rows is taken to be the head's id + 1, as each row's prior constraint keeps the ids contiguous.
+    /// With deep, every row of every table is verified (see verify_chain_batched) and rows is counted.
+    /// Only an integrity error marks a table unverified: any other error (i.e. the database is unavailable) is returned 
+    pub async fn integrity_status(&self, deep: bool) -> Result<views::IntegrityStatus, XtchdError> {
+        let mut per_table = Vec::new();
+        for table in self.content_tables.tables() {
+            let query = format!("SELECT {0}, prior_id FROM {1} ORDER BY {0} DESC LIMIT 1", table.id_column, table.name);
+            let head: Option<(i32, Option<i32>)> = self.c.query_opt(&query, &[]).await?.map(|row| (row.get(0), row.get(1)));
+            let (rows, head_sha256, verified) = match head {
+                None => (0, None, true),
+                Some((id, prior_id)) => {
+                    let head_sha256 = self.find_head_sha256(table, id).await?;
+                    let in_sequence = prior_id == id.checked_sub(1).filter(|prior| *prior >= 0);
+                    let head_ok = in_sequence && self.verify_row(table.name, id).await?.is_valid();
+                    match deep {
+                        false => (id as i64 + 1, Some(head_sha256), head_ok),
+                        true => {
+                            let counted: i64 = self.c.query_one(&format!("SELECT COUNT(*) FROM {}", table.name), &[]).await?.get(0);
+                            // only a broken chain is unverified: a failure to read it (i.e. a Timeout) is not evidence of tampering 
+                            let chain_ok = match self.verify_chain_batched(table.name, INTEGRITY_BATCH_SIZE).await {
+                                Ok(()) => true,
+                                Err(XtchdError::Integrity(_)) => false,
+                                Err(e) => return Err(e),
+                            };
+                            (counted, Some(head_sha256), head_ok && chain_ok && counted == id as i64 + 1)
+                        },
+                    }
+                },
+            };
+            per_table.push(views::TableStatus{name: table.name.to_string(), rows, head_sha256, verified, last_checked: Utc::now()});
+        }
+        Ok(views::IntegrityStatus{per_table})
+    }
+
+    /// The new_sha256 of one row 
+    async fn find_head_sha256(&self, table: &ChainTable, id: i32) -> Result<String, PachyDarn> {
+        let query = format!("SELECT new_sha256 FROM {} WHERE {} = $1", table.name, table.id_column);
+        let row = self.c.query_one(&query, &[&id]).await?;
+        Ok(row.get(0))
+    }
+
+
+    /// The bytes on disk for each of the CHAIN_TABLES and images_mut, including indexes and TOAST (i.e. compressed images),
+    /// in the order of CHAIN_TABLES, i.e. for deciding when to move images to object storage 
+    pub async fn storage_sizes(&self) -> Result<Vec<(String, i64)>, PachyDarn> {
+        let names: Vec<&str> = CHAIN_TABLES.iter().map(|table| table.name).chain(["images_mut"]).collect();
+        let rows = self.c.query("SELECT name, pg_total_relation_size(name::regclass) 
+            FROM UNNEST($1::TEXT[]) WITH ORDINALITY AS t(name, ord) ORDER BY ord", &[&names]).await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+
+    /// Rebuild the full-text (GIN) indexes on the tsvector columns of a table, i.e. for operators to run after a bulk import
+    /// (see import_channel_videos) leaves them bloated. The ts and ac columns themselves are generated, so Postgres keeps 
+    /// them current on every write and they are never recomputed here: rows of tables with hash chain integrity cannot be 
+    /// rewritten anyway (see the *_no_rewrite_later constraints). Returns the number of rows indexed, 
+    /// or XtchdError::InvalidArgument if the table has no full-text indexes 
+    pub async fn reindex_fulltext(&self, table: &str) -> Result<u64, XtchdError> {
+        // regclass resolves the table (failing if it does not exist) and quotes it, so it is safe to interpolate
+        let rows = self.c.query("SELECT $1::TEXT::REGCLASS::TEXT, i.indexrelid::REGCLASS::TEXT 
+            FROM pg_index i INNER JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+            WHERE i.indrelid = $1::TEXT::REGCLASS AND a.atttypid = 'tsvector'::REGTYPE", &[&table]).await?;
+        let Some(first) = rows.get(0) else {
+            return Err(XtchdError::InvalidArgument(format!("'{}' has no full-text indexes", table)))
+        };
+        let relation: String = first.get(0);
+        for row in &rows {
+            let index: String = row.get(1);
+            self.c.batch_execute(&format!("REINDEX INDEX {}", index)).await?;
+        }
+        let count: i64 = self.c.query_one(&format!("SELECT COUNT(*) FROM {}", relation), &[]).await?.get(0);
+        Ok(count as u64)
+    }
+
+
+    /// images_immut is the only table of immutable images: it is both written by add_image_immutable() and read by 
+    /// autocomplete (xrows::ImmutableImage) and full-text search (xrows::Thumbnail). 
+    /// This returns the img_id of each image that was written but cannot be found by search, 
+    /// i.e. because a search vector is NULL, which should be empty 
+    pub async fn audit_image_tables(&self) -> Result<Vec<i32>, PachyDarn> {
+        let rows = self.c.query("SELECT img_id FROM images_immut WHERE ts IS NULL OR ac IS NULL ORDER BY img_id", &[]).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+
+    /// Compute the phash (see xrows::image_phash) of each immutable image without one, i.e. those written without the 
+    /// "image-audit" feature, so they can be found by similar_images(). Images are decoded one at a time, and those which 
+    /// cannot be decoded are skipped (so are tried again by the next backfill). Returns the number of phashes added 
+    #[cfg(feature = "image-audit")]
+    pub async fn backfill_phashes(&self) -> Result<u64, XtchdError> {
+        let rows = self.c.query("SELECT img_id FROM images_immut i 
+            WHERE NOT EXISTS (SELECT 1 FROM image_phashes p WHERE p.img_id = i.img_id) ORDER BY img_id", &[]).await?;
+        let mut added = 0;
+        for row in rows.iter() {
+            let img_id: i32 = row.get(0);
+            let src_full: String = self.c.query_one("SELECT image_src(src_full, full_prefix, full_bytes) FROM images_immut WHERE img_id = $1", &[&img_id]).await?.get(0);
+            if let Ok(phash) = xrows::image_phash(&src_full) {
+                added += self.c.execute("INSERT INTO image_phashes (img_id, phash) VALUES ($1, $2) ON CONFLICT (img_id) DO NOTHING", 
+                    &[&img_id, &(phash as i64)]).await?;
+            }
+        }
+        Ok(added)
+    }
+
+
+    /// Whether the thumbnail of an immutable image resembles its full image (see xrows::thumbnail_matches), 
+    /// i.e. to flag an upload whose thumbnail misrepresents the full image.
+    /// Returns XrowError::UndecodableImage if either image cannot be decoded 
+    #[cfg(feature = "image-audit")]
+    pub async fn audit_thumbnail_fidelity(&self, img_id: i32) -> Result<bool, XtchdError> {
+        let query = "SELECT image_src(src_full, full_prefix, full_bytes), src_thmb FROM images_immut WHERE img_id = $1";
+        let rows = self.c.query(query, &[&img_id]).await?;
+        let row = match rows.get(0) {
+            Some(val) => val,
+            None => return Err(XtchdError::NotFound("missing row in query for audit_thumbnail_fidelity()".to_string())),
+        };
+        let (src_full, src_thmb): (String, String) = (row.get(0), row.get(1));
+        Ok(xrows::thumbnail_matches(&src_full, &src_thmb)?)
+    }
+
+
+    /// Sign the latest row of a table (its table, id and new_sha256: see views::HeadSignature::signed_message) and store the 
+    /// signature in chain_signatures, so anyone with the public key can verify the server vouched for that head (see views::HeadSignature::verify).
+    /// Returns XtchdError::InvalidArgument if the table does not have hash chain integrity or is empty 
+    #[cfg(feature = "signatures")]
+    pub async fn sign_head(&self, table: &str, signing_key: &ed25519_dalek::SigningKey) -> Result<views::HeadSignature, XtchdError> {
+        use ed25519_dalek::Signer;
+        let table = self.content_table(table)?;
+        let query = format!("SELECT {}, new_sha256 FROM {} ORDER BY {} DESC LIMIT 1", table.id_column, table.name, table.id_column);
+        let rows = self.c.query(&query, &[]).await?;
+        let row = rows.get(0).ok_or_else(|| XtchdError::InvalidArgument(format!("'{}' has no rows to sign", table.name)))?;
+        let (head_id, head_sha256): (i32, String) = (row.get(0), row.get(1));
+        let message = views::HeadSignature::signed_message(table.name, head_id, &head_sha256);
+        let signature = signing_key.sign(message.as_bytes()).to_bytes().to_vec();
+        let pubkey = signing_key.verifying_key().to_bytes().to_vec();
+        // Ed25519 signatures are deterministic, so signing the same head with the same key again stores nothing new
+        self.c.execute("INSERT INTO chain_signatures (table_name, head_id, signature, pubkey) VALUES ($1, $2, $3, $4)
+            ON CONFLICT (table_name, head_id, pubkey) DO NOTHING", &[&table.name, &head_id, &signature, &pubkey]).await?;
+        Ok(views::HeadSignature{table: table.name.to_string(), head_id, head_sha256, signature, pubkey})
+    }
+
+    /// Whether the signature stored for a row by sign_head() with the expected key is valid for the new_sha256 the row has now, 
+    /// i.e. false if the row was rewritten after it was signed, or if no signature by that key is stored for it.
+    /// Signatures by any other key are ignored, so whoever can write to chain_signatures cannot vouch for a row 
+    #[cfg(feature = "signatures")]
+    pub async fn verify_head_signature(&self, table: &str, head_id: i32, expected: &ed25519_dalek::VerifyingKey) -> Result<bool, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT s.signature, s.pubkey, t.new_sha256 FROM chain_signatures s 
+            INNER JOIN {} t ON t.{} = s.head_id WHERE s.table_name = $1 AND s.head_id = $2 AND s.pubkey = $3", table.name, table.id_column);
+        let rows = self.c.query(&query, &[&table.name, &head_id, &expected.as_bytes().as_slice()]).await?;
+        let signatures: Vec<views::HeadSignature> = rows.iter().map(|row| views::HeadSignature{table: table.name.to_string(), head_id,
+            head_sha256: row.get(2), signature: row.get(0), pubkey: row.get(1)}).collect();
+        Ok(!signatures.is_empty() && signatures.iter().all(|signature| signature.verify(expected)))
+    }
+
+    /// Submit the new_sha256 of the head of a table to an RFC 3161 timestamp authority and store the token it grants,
+    /// anchoring the chain to trusted external time. Returns XtchdError::Tsa if the TSA cannot be reached within tsa::TSA_TIMEOUT, 
+    /// does not grant a token, or grants one which does not mention the head. The token itself is not verified (see tsa.rs), 
+    /// and XtchdError::InvalidArgument if the table does not have hash chain integrity or is empty 
+    #[cfg(feature = "tsa")]
+    pub async fn timestamp_head(&self, table: &str, tsa_url: &str) -> Result<views::TsaToken, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT {}, new_sha256 FROM {} ORDER BY {} DESC LIMIT 1", table.id_column, table.name, table.id_column);
+        let rows = self.c.query(&query, &[]).await?;
+        let row = rows.get(0).ok_or_else(|| XtchdError::InvalidArgument(format!("'{}' has no rows to timestamp", table.name)))?;
+        let (head_id, head_sha256): (i32, String) = (row.get(0), row.get(1));
+        let digest = tsa::digest_from_hex(&head_sha256)?;
+        let nonce = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_nanos() as u64);
+        let response = tsa::submit(tsa_url, tsa::timestamp_request(&digest, nonce)).await?;
+        let token = tsa::token_from_response(&response)?;
+        if !tsa::token_mentions_digest(&token, &digest) {
+            return Err(tsa::TsaError::Malformed("the token does not mention the hash submitted").into())
+        }
+        self.c.execute("INSERT INTO tsa_tokens (table_name, head_id, tsa_url, token) VALUES ($1, $2, $3, $4)", 
+            &[&table.name, &head_id, &tsa_url, &token]).await?;
+        Ok(views::TsaToken{table: table.name.to_string(), head_id, head_sha256, tsa_url: tsa_url.to_string(), token})
+    }
+
+    /// Whether every token stored for a row by timestamp_head() mentions the new_sha256 the row has now, 
+    /// i.e. false if the row was rewritten after it was timestamped, or if no token is stored for it.
+    /// This proves nothing on its own: the tokens' signatures and nonces are not checked (see tsa::token_mentions_digest) 
+    #[cfg(feature = "tsa")]
+    pub async fn tsa_token_mentions_head(&self, table: &str, head_id: i32) -> Result<bool, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT s.tsa_url, s.token, t.new_sha256 FROM tsa_tokens s 
+            INNER JOIN {} t ON t.{} = s.head_id WHERE s.table_name = $1 AND s.head_id = $2", table.name, table.id_column);
+        let rows = self.c.query(&query, &[&table.name, &head_id]).await?;
+        let tokens: Vec<views::TsaToken> = rows.iter().map(|row| views::TsaToken{table: table.name.to_string(), head_id,
+            head_sha256: row.get(2), tsa_url: row.get(0), token: row.get(1)}).collect();
+        Ok(!tokens.is_empty() && tokens.iter().all(views::TsaToken::mentions_head))
+    }
+
+
+    /// Get up to limit items recently etched across all classes of content, newest first.
+    /// Pass the cursor of the last item returned (see ActivityItem::cursor) as before to get the next (older) page:
+    /// only items after it are returned, so new items etched in the meantime don't shift the pages. 
+    /// If types is provided, only items of those classes are returned. Redacted items are not returned 
+    pub async fn recent_activity(&self, limit: i64, before: Option<views::ActivityCursor>, types: Option<Vec<views::ContentClass>>) -> Result<Vec<views::ActivityItem>, XtchdError> {
+        let types: Option<Vec<&str>> = types.map(|classes| classes.iter().map(|class| class.dtype()).collect());
+        let (before_timestamp, before_class, before_id) = match &before {
+            Some(cursor) => (Some(cursor.write_timestamp), Some(cursor.class.dtype()), Some(cursor.id)),
+            None => (None, None, None),
+        };
+        let query = "SELECT class, id, name, write_timestamp FROM (
+                SELECT 'Author' AS class, auth_id AS id, name, write_timestamp FROM authors WHERE NOT redacted('authors', auth_id)
+                UNION ALL SELECT 'ArticleTitle', a_id_immut, title, write_timestamp FROM titles_immut WHERE NOT redacted('titles_immut', a_id_immut)
+                UNION ALL SELECT 'YoutubeChannel', chan_id, name, write_timestamp FROM youtube_channels WHERE NOT redacted('youtube_channels', chan_id)
+                UNION ALL SELECT 'YoutubeVideo', vid_id, title, write_timestamp FROM youtube_videos WHERE NOT redacted('youtube_videos', vid_id)
+                UNION ALL SELECT 'Image', img_id, alt, write_timestamp FROM images_immut WHERE NOT redacted('images_immut', img_id)
+            ) activity
+            WHERE ($1::TIMESTAMPTZ IS NULL OR (write_timestamp, class, id) < ($1, $2::TEXT, $3::INTEGER))
+            AND ($4::VARCHAR[] IS NULL OR class = ANY($4))
+            ORDER BY write_timestamp DESC, class DESC, id DESC LIMIT $5";
+        let rows = self.c.query(query, &[&before_timestamp, &before_class, &before_id, &types, &limit]).await?;
+        rows.iter().map(|row| {
+            let class: String = row.get(0);
+            let class = views::ContentClass::from_dtype(&class)
+                .ok_or_else(|| XtchdError::InvalidArgument(format!("unknown content class '{}' in recent_activity()", class)))?;
+            Ok(views::ActivityItem{class, id: row.get(1), name: row.get(2), write_timestamp: row.get(3)})
+        }).collect()
+    }
+
+
+    /// The head (the highest id and its new_sha256) of each registered table (see ContentTable), in one query. 
+    /// Clients can poll this cheaply and compare against the heads they saw previously to detect new content.
+    /// Tables with no rows are omitted 
+    pub async fn chain_heads(&self) -> Result<HashMap<String, (i32, String)>, PachyDarn> {
+        let query = self.content_tables.tables().iter()
+            .map(|table| format!("(SELECT '{}'::VARCHAR, {}, new_sha256 FROM {} ORDER BY {} DESC LIMIT 1)", 
+                table.name, table.id_column, table.name, table.id_column))
+            .collect::<Vec<String>>().join(" UNION ALL ");
+        let rows = self.c.query(&query, &[]).await?;
+        Ok(rows.iter().map(|row| (row.get(0), (row.get(1), row.get(2)))).collect())
+    }
+
+    /// One value summarizing a whole table, i.e. to check "has anything been appended to this table since I last checked?":
+    /// the new_sha256 of its head (see chain_heads), or GENESIS_SHA256 for an empty table. This only detects appends: 
+    /// the stored new_sha256 of the head is read as-is, so a row edited in place (leaving its stored hashes alone), 
+    /// or a row deleted from before the head, leaves the fingerprint unchanged. Use verify_chain() to detect tampering.
+    /// Returns XtchdError::InvalidArgument if the table does not have hash chain integrity 
+    pub async fn chain_fingerprint(&self, table: &str) -> Result<String, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT new_sha256 FROM {} ORDER BY {} DESC LIMIT 1", table.name, table.id_column);
+        let head = self.c.query_opt(&query, &[]).await?;
+        Ok(head.map_or_else(|| GENESIS_SHA256.to_string(), |row| row.get(0)))
+    }
+
+
+    /// Find the row of a table with this new_sha256, i.e. to verify a hash from an external attestation,
+    /// returning its id and dtype (or None if no row has this hash, or the row is redacted)
+    pub async fn find_by_sha256(&self, table: &str, sha256: &str) -> Result<Option<(i32, String)>, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT {} FROM {} WHERE new_sha256 = $1 AND NOT redacted('{}', {})", 
+            table.id_column, table.name, table.name, table.id_column);
+        let rows = self.c.query(&query, &[&sha256]).await?;
+        Ok(rows.get(0).map(|row| (row.get(0), table.dtype.to_string())))
+    }
+
+    /// Whether the row of a table with this new_sha256 is "buried": a later row carries it as its prior_sha256,
+    /// so the row cannot be altered without also rewriting every row after it. 
+    /// The head of a chain is not buried (nothing follows on from it yet), nor is a hash no row has 
+    pub async fn is_buried(&self, table: &str, sha256: &str) -> Result<bool, XtchdError> {
+        let table = self.content_table(table)?;
+        // both sides are indexed: new_sha256 (see public.sql) and prior_id, which is UNIQUE 
+        let query = format!("SELECT EXISTS (SELECT 1 FROM {} buried INNER JOIN {} later ON later.prior_id = buried.{}
+            WHERE buried.new_sha256 = $1 AND later.prior_sha256 = $1)", table.name, table.name, table.id_column);
+        let row = self.c.query_one(&query, &[&sha256]).await?;
+        Ok(row.get(0))
+    }
+
+
+    /// Find articles whose auth_id has no matching author, i.e. orphaned references the art_auth foreign key
+    /// should prevent, but which could exist if the key were dropped or deferred (or on a restore without it)
+    pub async fn audit_author_articles(&self) -> Result<Vec<views::ReferentialAnomaly>, PachyDarn> {
+        let query = "SELECT t.a_id_immut, t.auth_id FROM titles_immut t
+            LEFT JOIN authors a ON a.auth_id = t.auth_id
+            WHERE a.auth_id IS NULL ORDER BY t.a_id_immut ASC";
+        let rows = self.c.query(query, &[]).await?;
+        Ok(rows.iter().map(|row| views::ReferentialAnomaly{table: "titles_immut".to_string(), id: row.get(0), 
+            column: "auth_id".to_string(), missing_id: row.get(1)}).collect())
+    }
+
+
+    /// Find each row of a table whose prior_sha256 is not the new_sha256 of the row at its prior_id (looked up by id, 
+    /// rather than taken from the physically preceding row, in case of gaps), or is not GENESIS_SHA256 when it has no prior_id.
+    /// A row can hash correctly on its own but point at the wrong prior, so this complements verify_chain(). 
+    /// The result should be empty 
+    pub async fn audit_prior_links(&self, table: &str) -> Result<Vec<views::LinkAnomaly>, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT t.{id}, t.prior_id, t.prior_sha256, CASE WHEN t.prior_id IS NULL THEN $1 ELSE p.new_sha256 END AS expected
+            FROM {name} t LEFT JOIN {name} p ON p.{id} = t.prior_id
+            WHERE t.prior_sha256 IS DISTINCT FROM (CASE WHEN t.prior_id IS NULL THEN $1 ELSE p.new_sha256 END)
+            ORDER BY t.{id} ASC", id = table.id_column, name = table.name);
+        let rows = self.c.query(&query, &[&GENESIS_SHA256]).await?;
+        Ok(rows.iter().map(|row| views::LinkAnomaly{table: table.name.to_string(), id: row.get(0), 
+            prior_id: row.get(1), prior_sha256: row.get(2), expected: row.get(3)}).collect())
+    }
+
+
+    /// The new_sha256 of every row of a table, in id order, with the id of each row 
+    async fn chain_hashes(&self, table: &str) -> Result<Vec<(i32, String)>, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT {}, new_sha256 FROM {} ORDER BY {} ASC", table.id_column, table.name, table.id_column);
+        let rows = self.c.query(&query, &[]).await?;
+        Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+    }
+
+    /// The Merkle root over the new_sha256 of each row of a table (in id order).
+    /// Publishing the root (i.e. daily) anchors the whole table without publishing the chain: see integrity::merkle_root()
+    pub async fn merkle_root(&self, table: &str) -> Result<String, XtchdError> {
+        let leaves: Vec<String> = self.chain_hashes(table).await?.into_iter().map(|(_, sha)| sha).collect();
+        integrity::merkle_root(&leaves)
+            .ok_or_else(|| XtchdError::InvalidArgument(format!("merkle_root() requires at least one row in '{}'", table)))
+    }
+
+    /// The audit path proving the row with this id is included under merkle_root(table): see integrity::verify_merkle_proof() 
+    pub async fn merkle_proof(&self, table: &str, id: i32) -> Result<Vec<(String, bool)>, XtchdError> {
+        let hashes = self.chain_hashes(table).await?;
+        let index = hashes.iter().position(|(row_id, _)| *row_id == id)
+            .ok_or_else(|| XtchdError::NotFound("missing row in query for merkle_proof()".to_string()))?;
+        let leaves: Vec<String> = hashes.into_iter().map(|(_, sha)| sha).collect();
+        Ok(integrity::merkle_proof(&leaves, index).unwrap_or_default())
+    }
+
+
+    /// Warm the autocomplete cache for every CachedAutoComp type to its prewarm_depth,
+    /// i.e. on startup after a deploy or after Redis has been flushed
+    pub async fn warm_all_caches<W: CacheWarmer>(&self, warmer: &mut W) -> Result<WarmSummary, PachyDarn> {
+        let authors = warmer.warm::<i32, xrows::Author>(&self.c).await?;
+        let images = warmer.warm::<xrows::ImageThumbnail, xrows::ImmutableImage>(&self.c).await?;
+        let topics = warmer.warm::<String, views::Topic>(&self.c).await?;
+        Ok(WarmSummary{authors, images, topics})
+    }
+
+
+    /// List image thumbnails, most recent first, i.e. for a gallery.
+    /// Only img_id and src_thmb are selected so the (potentially huge) full images are never read 
+    pub async fn list_thumbnails(&self, limit: i64, offset: i64) -> Result<Vec<xrows::ImageThumbnail>, PachyDarn> {
+        let query = "SELECT img_id, src_thmb FROM images_immut ORDER BY img_id DESC LIMIT $1 OFFSET $2";
+        let rows = self.c.query(query, &[&limit, &offset]).await?;
+        Ok(rows.iter().map(|row| xrows::ImageThumbnail{img_id: row.get(0), src_thmb: row.get(1)}).collect())
+    }
+
+    /// Up to limit images referenced as the source (PageSrc::WpTxYt) of the most pages, each with that number of pages, 
+    /// most referenced first (then newest first), i.e. for a "popular images" section. 
+    /// Only the latest revision of each page is counted, so revising a page does not count its image twice 
+    pub async fn popular_images(&self, limit: i64) -> Result<Vec<(xrows::ImageThumbnail, i64)>, PachyDarn> {
+        let stmt = self.c.prepare_cached("SELECT i.img_id, i.src_thmb, refs.pages
+            FROM (SELECT p.img_id, COUNT(*) AS pages FROM pages_immut p
+                WHERE p.img_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = p.p_id_immut)
+                GROUP BY p.img_id) refs
+            INNER JOIN images_immut i ON i.img_id = refs.img_id
+            ORDER BY refs.pages DESC, i.img_id DESC LIMIT $1").await?;
+        let rows = self.c.query(&stmt, &[&limit]).await?;
+        Ok(rows.iter().map(|row| (xrows::ImageThumbnail{img_id: row.get(0), src_thmb: row.get(1)}, row.get(2))).collect())
+    }
+
+    /// The thumbnails of every image taken from the same archive.is snapshot (see ImagePair.archive), oldest first,
+    /// i.e. to find duplicate uploads. As with list_thumbnails(), full images are never read 
+    pub async fn images_by_archive(&self, archive_key: &str) -> Result<Vec<xrows::ImageThumbnail>, PachyDarn> {
+        let stmt = self.c.prepare_cached("SELECT img_id, src_thmb FROM images_immut WHERE archive = $1 ORDER BY img_id").await?;
+        let rows = self.c.query(&stmt, &[&archive_key]).await?;
+        Ok(rows.iter().map(|row| xrows::ImageThumbnail{img_id: row.get(0), src_thmb: row.get(1)}).collect())
+    }
+
+    /// Other images which look like this one, i.e. resized or re-encoded copies: those whose phash (see xrows::image_phash)
+    /// differs from this image's in at most max_distance bits, most similar first. The phash is only computed when 
+    /// images are written with the "image-audit" feature (or by backfill_phashes()), so images without one (including this one) match nothing 
+    pub async fn similar_images(&self, img_id: i32, max_distance: u32) -> Result<Vec<xrows::ImageThumbnail>, PachyDarn> {
+        let stmt = self.c.prepare_cached("SELECT img_id, src_thmb FROM (
+                SELECT other.img_id, i.src_thmb, 
+                    LENGTH(REPLACE(((target.phash # other.phash)::BIT(64))::TEXT, '0', '')) AS distance
+                FROM image_phashes target INNER JOIN image_phashes other ON other.img_id <> target.img_id
+                INNER JOIN images_immut i ON i.img_id = other.img_id
+                WHERE target.img_id = $1) candidates
+            WHERE distance <= $2
+            ORDER BY distance, img_id").await?;
+        let rows = self.c.query(&stmt, &[&img_id, &(max_distance as i32)]).await?;
+        Ok(rows.iter().map(|row| xrows::ImageThumbnail{img_id: row.get(0), src_thmb: row.get(1)}).collect())
+    }
+
+
+    /// The articles whose latest revision of some page cites this article (PageSrc::Xtchd), i.e. "what links here", each listed once 
+    pub async fn articles_citing(&self, a_id_immut: i32) -> Result<Vec<views::NameId>, PachyDarn> {
+        let query = "SELECT t.a_id_immut, 
+                CASE WHEN redacted('titles_immut', t.a_id_immut) 
+                THEN $2 ELSE t.title END
+            FROM titles_immut t
+            WHERE t.a_id_immut IN (SELECT p.a_id_immut FROM pages_immut p WHERE p.refs_a_id_immut = $1
+                AND NOT EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = p.p_id_immut))
+            ORDER BY t.a_id_immut ASC";
+        let rows = self.c.query(query, &[&a_id_immut, &xrows::REDACTED]).await?;
+        Ok(rows.iter().map(|row| views::NameId{id: row.get(0), name: row.get(1)}).collect())
+    }
+
+
+    /// Up to limit other articles sharing the most topics with this article (see apara_ment_topic), i.e. "related articles",
+    /// each with the number of distinct topics shared, most shared first (then newest first). Redacted titles are replaced with xrows::REDACTED
+    pub async fn related_articles(&self, a_id_immut: i32, limit: i64) -> Result<Vec<(views::NameId, i64)>, PachyDarn> {
+        let query = "SELECT t.a_id_immut, 
+                CASE WHEN redacted('titles_immut', t.a_id_immut) 
+                THEN $2 ELSE t.title END, shared.topics
+            FROM (SELECT other.art_id, COUNT(DISTINCT other.tkey) AS topics
+                FROM apara_ment_topic mine INNER JOIN apara_ment_topic other ON other.tkey = mine.tkey
+                WHERE mine.art_id = $1 AND other.art_id <> $1
+                GROUP BY other.art_id) shared
+            INNER JOIN titles_immut t ON t.a_id_immut = shared.art_id
+            ORDER BY shared.topics DESC, t.a_id_immut DESC LIMIT $3";
+        let stmt = self.c.prepare_cached(query).await?;
+        let rows = self.c.query(&stmt, &[&a_id_immut, &xrows::REDACTED, &limit]).await?;
+        Ok(rows.iter().map(|row| (views::NameId{id: row.get(0), name: row.get(1)}, row.get(2))).collect())
+    }
+
+
+    /// The tombstone redacting one row of a table, if there is one 
+    async fn tombstone_for(&self, table: &str, id: i32) -> Result<Option<xrows::Tombstone>, PachyDarn> {
+        Ok(tombstone_in(&*self.c, table, id).await?)
+    }
+
+
+    /// Autocomplete the phrase from the cache, falling back to querying Postgres directly if the cache fails
+    /// (i.e. Redis is down or unreachable), so autocomplete degrades to slower rather than broken
+    pub async fn autocomplete_cached_or_db<U, T: CachedAutoComp<U> + AutoComp<U>, C: AutoCompCache>(&self, cache: &mut C, phrase: &str) -> Result<Vec<WhoWhatWhere<U>>, PachyDarn> {
+        match cache.autocomp::<U, T>(&self.c, phrase).await {
+            Ok(results) => Ok(results),
+            Err(e) => {
+                tracing::warn!(dtype = <T as CachedAutoComp<U>>::dtype(), error = %e, "autocomplete cache failed: falling back to Postgres");
+                T::exec_autocomp(&self.c, phrase).await
+            },
+        }
+    }
+
+
+    /// Get several authors in one query, i.e. for listing articles with their authors without one author_detail() per article.
+    /// Each author has their current name (see rename_author()). Ids with no author, or whose author is redacted, are simply absent from the map 
+    pub async fn authors_by_ids(&self, ids: &[i32]) -> Result<HashMap<i32, xrows::Author>, PachyDarn> {
+        let stmt = self.c.prepare_cached("SELECT auth_id, name FROM author_current WHERE auth_id = ANY($1) AND NOT redacted('authors', auth_id)").await?;
+        let rows = self.c.query(&stmt, &[&ids]).await?;
+        Ok(rows.iter().map(|row| {
+            let author = xrows::Author{auth_id: row.get(0), name: row.get(1)};
+            (author.auth_id, author)
+        }).collect())
+    }
+
+
+    /// Autocomplete authors as xrows::Author::query_autocomp() does, but when that finds fewer than min_results authors
+    /// (i.e. the phrase is misspelled: 'Einstien'), fall back to trigram similarity, appending the closest other matches.
+    /// With AutoCompOrder::Popularity the prefix matches are ordered most cited first (see refresh_author_popularity()).
+    /// TsConfig::Simple matches the phrase as typed, as query_autocomp() does; another configuration stems the phrase and names 
+    /// in that language instead, so the phrase need not appear in the name verbatim. 
+    /// This requires the pg_trgm extension (see public.sql) 
+    pub async fn autocomplete_authors(&self, phrase: &str, min_results: usize, order: xrows::AutoCompOrder, config: xrows::TsConfig) -> Result<Vec<WhoWhatWhere<i32>>, PachyDarn> {
+        let (join, order_by) = match order {
+            xrows::AutoCompOrder::Length => ("", "LENGTH(name) ASC"),
+            xrows::AutoCompOrder::Popularity => ("LEFT JOIN author_popularity USING (auth_id)", "COALESCE(refs, 0) DESC, LENGTH(name) ASC"),
+        };
+        // as in search_paragraphs, the configuration is one of TsConfig, so it is safe to write into the query 
+        let rows = match config {
+            xrows::TsConfig::Simple => {
+                let query = format!("SELECT auth_id, name FROM author_current {}
+                    WHERE ac @@ prefix_tsquery('simple', $1)
+                    AND LOWER(name) LIKE '%' || LOWER($2) || '%'
+                    AND NOT redacted('authors', auth_id)
+                    ORDER BY {} LIMIT 10", join, order_by);
+                let stmt = self.c.prepare_cached(&query).await?;
+                self.c.query(&stmt, &[&phrase, &phrase]).await?
+            },
+            _ => {
+                let query = format!("SELECT auth_id, name FROM author_current {0}
+                    WHERE to_tsvector('{1}', name) @@ prefix_tsquery('{1}', $1)
+                    AND NOT redacted('authors', auth_id)
+                    ORDER BY {2} LIMIT 10", join, config.as_str(), order_by);
+                let stmt = self.c.prepare_cached(&query).await?;
+                self.c.query(&stmt, &[&phrase]).await?
+            },
+        };
+        let mut results: Vec<WhoWhatWhere<i32>> = rows.iter().map(xrows::Author::rowfunc_autocomp).collect();
+        if results.len() >= min_results {
+            return Ok(results)
+        }
+        let found: Vec<i32> = results.iter().map(|www| www.pk).collect();
+        let stmt = self.c.prepare_cached("SELECT auth_id, name FROM author_current
+            WHERE word_similarity($1, name) >= $2 AND auth_id <> ALL($3) AND NOT redacted('authors', auth_id)
+            ORDER BY word_similarity($1, name) DESC, LENGTH(name) ASC
+            LIMIT $4").await?;
+        let limit = (min_results - results.len()) as i64;
+        let rows = self.c.query(&stmt, &[&phrase, &FUZZY_THRESHOLD, &found, &limit]).await?;
+        results.extend(rows.iter().map(xrows::Author::rowfunc_autocomp));
+        Ok(results)
+    }
+
+
+    /// Rebuild the author_popularity table from the number of pages citing each author's articles 
+    /// (see AutoCompOrder::Popularity), i.e. periodically, returning the number of authors who are cited at all.
+    /// Only the latest revision of each page counts, so revising a page does not count its citation again 
+    pub async fn refresh_author_popularity(&mut self) -> Result<u64, PachyDarn> {
+        let tx = self.c.transaction().await?;
+        tx.execute("DELETE FROM author_popularity", &[]).await?;
+        let cited = tx.execute("INSERT INTO author_popularity (auth_id, refs)
+            SELECT t.auth_id, COUNT(*) FROM pages_immut p INNER JOIN titles_immut t ON t.a_id_immut = p.refs_a_id_immut 
+            WHERE NOT EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = p.p_id_immut)
+            GROUP BY t.auth_id", &[]).await?;
+        tx.commit().await?;
+        Ok(cited)
+    }
+
+
+    /// Every author's id and current name in auth_id order, i.e. for generating a sitemap (no hashes are returned).
+    /// Redacted authors are not listed 
+    pub async fn all_authors(&self) -> Result<Vec<views::NameId>, PachyDarn> {
+        let rows = self.c.query("SELECT auth_id, name FROM author_current WHERE NOT redacted('authors', auth_id) ORDER BY auth_id", &[]).await?;
+        Ok(rows.iter().map(|row| views::NameId{id: row.get(0), name: row.get(1)}).collect())
+    }
+
+    /// Every article's a_id_immut in order, i.e. for generating a sitemap 
+    pub async fn all_article_ids(&self) -> Result<Vec<i32>, PachyDarn> {
+        let rows = self.c.query("SELECT a_id_immut FROM titles_immut ORDER BY a_id_immut", &[]).await?;
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// Every title of the author's articles in id order, wrapped in XtchdContent so the browser can verify each one,
+    /// i.e. for an author page with proof per title. Each row is checked as it is read, so this fails on a tampered title. 
+    /// A redacted title is replaced with xrows::REDACTED (as in article_detail) and so will not verify 
+    pub async fn author_article_titles(&self, auth_id: i32) -> Result<Vec<XtchdContent<xrows::ArticleTitle>>, PachyDarn> {
+        let query = <xrows::ArticleTitle as ContentTable>::chain_table().xtchd_sql_query("WHERE auth_id = $1");
+        let rows = self.c.query(&query, &[&auth_id]).await?;
+        let titles = rows.iter().map(|row| row.try_get(0)).collect::<Result<Vec<XtchdContent<xrows::ArticleTitle>>, _>>()?;
+        let redacted: HashSet<i32> = self.c.query("SELECT target_id FROM tombstones WHERE target_table = 'titles_immut'", &[]).await?
+            .iter().map(|row| row.get(0)).collect();
+        Ok(titles.into_iter().map(|title| match redacted.contains(&title.content.a_id_immut) {
+            true => {
+                let content = xrows::ArticleTitle{title: xrows::REDACTED.to_string(), ..title.content};
+                XtchdContent::new(title.prior_id, title.prior_sha256, title.hcl.write_timestamp, content, title.new_sha256)
+            },
+            false => title,
+        }).collect())
+    }
+
+    /// The author's article with the highest a_id_immut, i.e. for a "latest from this author" widget,
+    /// or None if they have no articles. A redacted title is replaced with xrows::REDACTED
+    pub async fn author_latest_article(&self, auth_id: i32) -> Result<Option<views::NameId>, PachyDarn> {
+        let query = "SELECT t.a_id_immut, 
+                CASE WHEN redacted('titles_immut', t.a_id_immut) 
+                THEN $2 ELSE t.title END
+            FROM titles_immut t
+            WHERE t.auth_id = $1
+            ORDER BY t.a_id_immut DESC LIMIT 1";
+        let rows = self.c.query(query, &[&auth_id, &xrows::REDACTED]).await?;
+        Ok(rows.get(0).map(|row| views::NameId{id: row.get(0), name: row.get(1)}))
+    }
+
+
+    /// Full-text search of article paragraphs with the given text search configuration, best match (by ts_rank) first: 
+    /// TsConfig::English (the default) uses the stored tsvector, while other configurations stem and drop stop words in that language, 
+    /// using the expression index for that configuration in public.sql. Paragraphs which are redacted, or whose article is, are not returned 
+    pub async fn search_paragraphs(&self, phrase: &str, config: xrows::TsConfig) -> Result<Vec<views::ParaSearchResult>, PachyDarn> {
+        use pachydurable::fulltext::FullText;
+        // the configuration is one of TsConfig rather than user input, so it is written into the query as a literal:
+        // Postgres only uses an expression index such as article_fulltext_spanish when the configuration is a constant 
+        let vector = match config {
+            xrows::TsConfig::English => "ts".to_string(),
+            _ => format!("to_tsvector('{}', plain)", config.as_str()),
+        };
+        let query = format!("SELECT apara_id, art_id, ts_headline('{0}', plain, q) AS snippet
+            FROM article_para, prefix_tsquery('{0}', $1) q
+            WHERE {1} @@ q
+            AND NOT redacted('article_para', apara_id) AND NOT redacted('titles_immut', art_id)
+            ORDER BY ts_rank({1}, q) DESC, apara_id ASC
+            LIMIT 20", config.as_str(), vector);
+        let stmt = self.c.prepare_cached(&query).await?;
+        let rows = self.c.query(&stmt, &[&phrase]).await?;
+        Ok(rows.iter().map(views::ParaSearchResult::rowfunc_fulltext).collect())
+    }
+
+    /// One page of full-text search results (with the English configuration, as ParaSearchResult::query_fulltext()), 
+    /// ordered by ts_rank then apara_id so pages are deterministic. Pass None for the first page, 
+    /// then the .next of each page to get the page after it: unlike offsets, a paragraph written in between cannot shift results across pages 
+    pub async fn search_paragraphs_page(&self, phrase: &str, after_rank: Option<(f32, i32)>, limit: i64) -> Result<views::ParaSearchPage, PachyDarn> {
+        use pachydurable::fulltext::FullText;
+        let stmt = self.c.prepare_cached("SELECT apara_id, art_id, ts_headline('english', plain, prefix_tsquery('english', $1)) AS snippet, rank
+            FROM (SELECT apara_id, art_id, plain, ts_rank(ts, prefix_tsquery('english', $1)) AS rank
+                FROM article_para WHERE ts @@ prefix_tsquery('english', $1)
+                AND NOT redacted('article_para', apara_id) AND NOT redacted('titles_immut', art_id)) hits
+            WHERE $2::REAL IS NULL OR rank < $2 OR (rank = $2 AND apara_id > $3)
+            ORDER BY rank DESC, apara_id ASC 
+            LIMIT $4").await?;
+        let (rank, apara_id) = (after_rank.map(|(rank, _)| rank), after_rank.map(|(_, apara_id)| apara_id));
+        let rows = self.c.query(&stmt, &[&phrase, &rank, &apara_id, &limit]).await?;
+        let next = match rows.last() {
+            Some(last) if rows.len() as i64 == limit => Some((last.get(3), last.get(0))),
+            _ => None,
+        };
+        Ok(views::ParaSearchPage{results: rows.iter().map(views::ParaSearchResult::rowfunc_fulltext).collect(), next})
+    }
+
+    /// Up to limit paragraphs mentioning a topic (see apara_ment_topic), across all articles in article order,
+    /// each with a snippet from ts_headline with the topic's name wrapped in <b></b>
+    pub async fn topic_paragraph_snippets(&self, tkey: &str, limit: i64) -> Result<Vec<views::ParaSearchResult>, PachyDarn> {
+        use pachydurable::fulltext::FullText;
+        let stmt = self.c.prepare_cached("SELECT p.apara_id, p.art_id, ts_headline('english', p.plain, phraseto_tsquery('english', t.name)) AS snippet
+            FROM apara_ment_topic m
+            INNER JOIN nlp_topics t ON t.tkey = m.tkey
+            INNER JOIN article_para p ON p.art_id = m.art_id AND p.apara_id = m.apara_id
+            WHERE m.tkey = $1
+            ORDER BY p.art_id, p.apara_id
+            LIMIT $2").await?;
+        let rows = self.c.query(&stmt, &[&tkey, &limit]).await?;
+        Ok(rows.iter().map(views::ParaSearchResult::rowfunc_fulltext).collect())
+    }
+
+
+    /// Every channel with its number of videos, most videos first (then in chan_id order), i.e. for a channels index page.
+    /// Channels with no videos yet are included with a count of 0
+    pub async fn channels_with_counts(&self) -> Result<Vec<(xrows::YoutubeChannel, i64)>, PachyDarn> {
+        let stmt = self.c.prepare_cached("SELECT ch.chan_id, ch.url, ch.name, COUNT(v.vid_id) AS videos
+            FROM youtube_channels ch LEFT JOIN youtube_videos v ON v.chan_id = ch.chan_id
+            GROUP BY ch.chan_id ORDER BY videos DESC, ch.chan_id ASC").await?;
+        let rows = self.c.query(&stmt, &[]).await?;
+        Ok(rows.iter().map(|row| (xrows::YoutubeChannel{chan_id: row.get(0), url: row.get(1), name: row.get(2)}, row.get(3))).collect())
+    }
+
+
+    /// Count the rows for each type of content in one round trip (no content is returned).
+    /// Pages are counted once however often they have been revised 
+    pub async fn counts(&self) -> Result<views::ContentCounts, PachyDarn> {
+        let query = "SELECT 
+            (SELECT COUNT(*) FROM authors),
+            (SELECT COUNT(*) FROM titles_immut),
+            (SELECT COUNT(*) FROM pages_immut p WHERE NOT EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = p.p_id_immut)),
+            (SELECT COUNT(*) FROM article_para),
+            (SELECT COUNT(*) FROM youtube_channels),
+            (SELECT COUNT(*) FROM youtube_videos),
+            (SELECT COUNT(*) FROM images_immut)";
+        let row = self.c.query_one(query, &[]).await?;
+        Ok(views::ContentCounts{
+            authors: row.get(0),
+            articles: row.get(1),
+            pages: row.get(2),
+            paragraphs: row.get(3),
+            channels: row.get(4),
+            videos: row.get(5),
+            images: row.get(6),
+        })
+    }
+
+    /// The data_type of each class of content with at least one row, i.e. to only show tabs for populated types:
+    /// the Xtchable::dtype() for authors, articles, videos and images, and "Topic" for topics (as in views::Topic). 
+    /// Authors are always present once the genesis author is written 
+    pub async fn present_content_types(&self) -> Result<Vec<String>, PachyDarn> {
+        let query = "SELECT 
+            EXISTS (SELECT 1 FROM authors),
+            EXISTS (SELECT 1 FROM titles_immut),
+            EXISTS (SELECT 1 FROM youtube_videos),
+            EXISTS (SELECT 1 FROM images_immut),
+            EXISTS (SELECT 1 FROM nlp_topics)";
+        let row = self.c.query_one(query, &[]).await?;
+        let data_types = [<xrows::Author as Xtchable>::dtype(), <xrows::ArticleTitle as Xtchable>::dtype(), 
+            <xrows::YoutubeVideo as Xtchable>::dtype(), <xrows::ImmutableImage as Xtchable>::dtype(), "Topic"];
+        Ok(data_types.iter().enumerate().filter(|(i, _)| row.get::<_, bool>(*i)).map(|(_, data_type)| data_type.to_string()).collect())
+    }
+
+    /// Redact one row of a table with hash chain integrity, i.e. for a legal takedown, by appending a Tombstone.
+    /// The row itself is left in place so its chain still verifies, but read methods return a placeholder for it 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "tombstones", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn tombstone(&mut self, table: &str, id: i32, reason: &str) -> Result<(xrows::Tombstone, HashChainLink), XtchdError> {
+        let target = self.content_table(table)?;
+        let query = format!("SELECT 1 FROM {} WHERE {} = $1", target.name, target.id_column);
+        if self.c.query(&query, &[&id]).await?.is_empty() {
+            return Err(XtchdError::NotFound("missing row in query for tombstone()".to_string()))
+        }
+        let (tx, last_tomb) = self.lock_tail(<xrows::Tombstone as ContentTable>::chain_table()).await?;
+        let tomb = xrows::Tombstone{tomb_id: last_tomb.next_id(), target_table: target.name.to_string(), target_id: id, reason: reason.to_string()};
+        let hclink = HashChainLink::new(&last_tomb.prior_sha256, &tomb);
+        let stmt = tx.prepare_cached("INSERT INTO tombstones
+            (                 prior_id,        tomb_id,         target_table,         target_id,        reason,               prior_sha256,         write_timestamp,          new_sha256)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)").await?;
+        let _x = tx.execute(&stmt,
+            &[&last_tomb.prior_id, &tomb.tomb_id, &tomb.target_table, &tomb.target_id, &tomb.reason, &last_tomb.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
+        ).await?;
+        tx.commit().await?;
+        record_etched(tomb.tomb_id, &last_tomb.prior_sha256, &hclink);
+        Ok((tomb, hclink))
+    }
+
+    /// Write any Xtchable content to the end of its table's chain. The id of the content is overwritten with the next id in the table.
+    /// This is the generic form of the add_* methods, so a new type of content need only implement Xtchable and ToRow.
+    /// The table must be registered (see content_table()) with the dtype of T, otherwise XtchdError::InvalidArgument is returned 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = table, id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn append<T: Xtchable + ToRow>(&mut self, table: &str, content: T) -> Result<(T, HashChainLink), XtchdError> {
+        let table = self.content_table_of::<T>(table)?;
+        let (tx, last_row) = self.lock_tail(table).await?;
+        append_in(tx, table, content, &last_row).await
+    }
+
+    /// Open a transaction holding the advisory lock on the table (released when the transaction ends) and read the tail within it.
+    /// Every write to a table with hash chain integrity goes through this, so concurrent writers (including reservations, 
+    /// see reserve_next()) queue for the lock rather than race for the same id 
+    async fn lock_tail(&mut self, table: &ChainTable) -> Result<(deadpool_postgres::Transaction<'_>, LastRow), tokio_postgres::Error> {
+        let tx = self.c.transaction().await?;
+        lock_table(&tx, table.name).await?;
+        let last_row = last_row_in(&tx, table).await?;
+        Ok((tx, last_row))
+    }
+
+    /// The tail of a table, so a client can build the next row and compute its new_sha256 itself (i.e. to sign it)
+    /// before submitting it with append_presigned() 
+    pub async fn prepare_append(&self, table: &str) -> Result<LastRow, XtchdError> {
+        let table = self.content_table(table)?;
+        let query = format!("SELECT {}, new_sha256 FROM {} ORDER BY {} DESC LIMIT 1", table.id_column, table.name, table.id_column);
+        Ok(get_last_row(&self.c, &query).await?)
+    }
+
+    /// Write content whose new_sha256 was computed by the client from the LastRow returned by prepare_append().
+    /// The hash is recomputed server-side and the write rejected with an IntegrityError if it does not match, 
+    /// or if another row has been written since prepare_append(). 
+    /// A write_timestamp more than PRESIGNED_BACKDATE_SECS before or PRESIGNED_WINDOW_SECS after the server's clock is rejected with XtchdError::InvalidArgument 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = table, id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn append_presigned<T: Xtchable + ToRow>(&mut self, table: &str, mut content: T, prior_sha256: &str, write_timestamp: DateTime<Utc>, new_sha256: &str) -> Result<(T, HashChainLink), XtchdError> {
+        check_presigned_timestamp(write_timestamp)?;
+        let table = self.content_table_of::<T>(table)?;
+        let (tx, last_row) = self.lock_tail(table).await?;
+        // a client building the row with the wrong id will have hashed the wrong state_string
+        content.set_id(last_row.next_id());
+        if last_row.prior_sha256 != prior_sha256 {
+            return Err(XtchdError::from(integrity::IntegrityError::BrokenLink{dtype: <T as Xtchable>::dtype().to_string(), 
+                prior_id: last_row.prior_id, expected: last_row.prior_sha256, found: prior_sha256.to_string()}))
+        }
+        let hclink = HashChainLink::from_timestamp(prior_sha256, write_timestamp, &content);
+        let computed = hclink.new_sha256();
+        if computed != new_sha256 {
+            return Err(XtchdError::from(integrity::IntegrityError::HashMismatch{dtype: <T as Xtchable>::dtype().to_string(), 
+                stored: new_sha256.to_string(), computed}))
+        }
+        insert_row(&tx, table.name, &content, &last_row, &hclink).await?;
+        tx.commit().await?;
+        record_etched(last_row.next_id(), prior_sha256, &hclink);
+        Ok((content, hclink))
+    }
+
+    /// Reserve the next row of a table for a presigned write (see prepare_append), without racing other writers:
+    /// a transaction is opened holding the advisory lock on the table (released when the transaction ends),
+    /// which every write to the table takes, and the tail is read within it. Write the row with Reservation::append_presigned(), 
+    /// or drop the Reservation to roll back and release the lock 
+    pub async fn reserve_next(&mut self, table: &str) -> Result<Reservation<'_>, XtchdError> {
+        let table = self.content_table(table)?;
+        let (tx, last_row) = self.lock_tail(table).await?;
+        Ok(Reservation{tx, table, last_row})
+    }
+
+    /// Insert the genesis author (auth_id 0, the first row of the authors chain) if it is missing, 
+    /// i.e. to bootstrap a fresh database whose authors table was created without the seed row in public.sql. 
+    /// Does nothing if auth_id 0 already exists 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "authors", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn ensure_genesis(&self) -> Result<(), PachyDarn> {
+        let author = xrows::Author{auth_id: 0, name: xrows::GENESIS_AUTHOR.to_string()};
+        let hclink = HashChainLink::new(GENESIS_SHA256, &author);
+        let stmt = self.c.prepare_cached("INSERT INTO authors
+            (prior_id, auth_id, name, prior_sha256, write_timestamp, new_sha256) 
+                VALUES (NULL, $1, $2, $3, $4, $5)
+            ON CONFLICT DO NOTHING").await?;
+        let written = self.c.execute(&stmt, &[&author.auth_id, &author.name, &GENESIS_SHA256, &hclink.write_timestamp, &hclink.new_sha256()]).await?;
+        if written == 1 {
+            record_etched(author.auth_id, GENESIS_SHA256, &hclink);
+        }
+        Ok(())
+    }
+
+    // add an author
+    // Another author's current name (see rename_author()) returns XrowError::Conflict, 
+    // as does the name an author was first written with even once they have been renamed (authors.name is UNIQUE)
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "authors", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_author(&mut self, name: &str) -> Result<(xrows::Author, HashChainLink), XtchdError> {
+        let table = <xrows::Author as ContentTable>::chain_table();
+        let (tx, last_row) = self.lock_tail(table).await?;
+        // rename_author() takes the same lock to check its new name 
+        if !tx.query("SELECT 1 FROM author_current WHERE name = $1", &[&name]).await?.is_empty() {
+            return Err(XtchdError::Xrow(xrows::XrowError::Conflict{constraint: AUTHOR_CURRENT_NAME.to_string()}))
+        }
+        append_in(tx, table, xrows::Author{auth_id: 0, name: name.to_string()}, &last_row).await
+    }
+
+    /// add_author(), but if an author with this current name already exists, that author is returned instead of a Conflict 
+    pub async fn add_author_dedupe(&mut self, name: &str) -> Result<(xrows::Author, HashChainLink), XtchdError> {
+        let auth_id = match self.find_author_by_name(name).await? {
+            Some(auth_id) => auth_id,
+            None => return self.add_author(name).await,
+        };
+        let row = self.c.query_one("SELECT name, prior_sha256, write_timestamp FROM authors WHERE auth_id = $1", &[&auth_id]).await?;
+        let author = xrows::Author{auth_id, name: row.get(0)};
+        let prior_sha256: String = row.get(1);
+        let write_timestamp: DateTime<Utc> = row.get(2);
+        let hclink = HashChainLink::from_timestamp(&prior_sha256, write_timestamp, &author);
+        Ok((author, hclink))
+    }
+
+
+    /// Rename an author by appending an AuthorName to the author_names chain: the author row is never rewritten.
+    /// Names are unique across every author's current name, so taking another author's current name returns XrowError::Conflict.
+    /// The check and the write are made in one transaction holding the advisory locks on authors and author_names (see lock_tail()) 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "author_names", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn rename_author(&mut self, auth_id: i32, name: &str) -> Result<(xrows::AuthorName, HashChainLink), XtchdError> {
+        let table = <xrows::AuthorName as ContentTable>::chain_table();
+        let tx = self.c.transaction().await?;
+        // authors first, as add_author() holds it while checking names 
+        lock_table(&tx, <xrows::Author as ContentTable>::chain_table().name).await?;
+        lock_table(&tx, table.name).await?;
+        if tx.query("SELECT 1 FROM authors WHERE auth_id = $1", &[&auth_id]).await?.is_empty() {
+            return Err(XtchdError::NotFound("missing row in query for rename_author()".to_string()))
+        }
+        if !tx.query("SELECT 1 FROM author_current WHERE name = $1 AND auth_id <> $2", &[&name, &auth_id]).await?.is_empty() {
+            return Err(XtchdError::Xrow(xrows::XrowError::Conflict{constraint: AUTHOR_CURRENT_NAME.to_string()}))
+        }
+        let last_row = last_row_in(&tx, table).await?;
+        append_in(tx, table, xrows::AuthorName{name_id: 0, auth_id, name: name.to_string()}, &last_row).await
+    }
+
+
+    /// The author as first written, and each rename of them in author_names oldest first, all independently verifiable.
+    /// Each rename follows on from the row before it in author_names (not the author's previous name), so verify_chain("author_names")
+    /// checks renames are never rewritten. Redacted renames are skipped; for a redacted author, the name is replaced with xrows::REDACTED
+    /// and no renames are returned 
+    pub async fn author_history(&self, auth_id: i32) -> Result<views::AuthorHistory, XtchdError> {
+        let query = <xrows::Author as ContentTable>::chain_table().xtchd_sql_query("WHERE auth_id = $1");
+        let rows = self.c.query(&query, &[&auth_id]).await?;
+        let author: XtchdContent<xrows::Author> = match rows.get(0) {
+            Some(row) => row.try_get(0)?,
+            None => return Err(XtchdError::NotFound("missing row in query for author_history()".to_string())),
+        };
+        if self.tombstone_for("authors", auth_id).await?.is_some() {
+            let content = xrows::Author{auth_id, name: xrows::REDACTED.to_string()};
+            let author = XtchdContent::new(author.prior_id, author.prior_sha256, author.hcl.write_timestamp, content, author.new_sha256);
+            return Ok(views::AuthorHistory{author, renames: Vec::new()})
+        }
+        let query = <xrows::AuthorName as ContentTable>::chain_table().xtchd_sql_query("WHERE auth_id = $1 AND NOT redacted('author_names', name_id)");
+        let renames = self.c.query(&query, &[&auth_id]).await?.iter()
+            .map(|row| row.try_get(0)).collect::<Result<Vec<XtchdContent<xrows::AuthorName>>, _>>()?;
+        Ok(views::AuthorHistory{author, renames})
+    }
+
+
+    // add an article (but not the text thereof)
+    // If an idempotency_key is provided and has been seen before, the article created by the earlier call is returned
+    // instead of appending a duplicate, so the write is safe to retry (i.e. after an HTTP timeout)
+    // If no a_id_draft is provided, one is generated with xrows::new_draft_id()
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "titles_immut", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_article_title(&mut self, auth_id: i32, a_id_draft: Option<&str>, title: &str, idempotency_key: Option<&str>) -> Result<(xrows::ArticleTitle, HashChainLink), XtchdError> {
+        let a_id_draft = a_id_draft.map_or_else(xrows::new_draft_id, str::to_string);
+        xrows::check_draft_id(&a_id_draft, xrows::DRAFT_ID_LEN)?;
+        let (tx, last_article) = self.lock_tail(<xrows::ArticleTitle as ContentTable>::chain_table()).await?;
+        let a_id_immut = last_article.next_id();
+        // the key is recorded first, in the same transaction as the title: a retry either finds the key (and so the title) committed, 
+        // or waits on the key until the first write commits or rolls back 
+        if let Some(key) = idempotency_key {
+            let recorded = tx.execute("INSERT INTO write_idempotency (idem_key, table_name, row_id) VALUES ($1, 'titles_immut', $2)
+                ON CONFLICT (idem_key) DO NOTHING", &[&key, &a_id_immut]).await?;
+            if recorded == 0 {
+                let row = tx.query_one("SELECT table_name, row_id FROM write_idempotency WHERE idem_key = $1", &[&key]).await?;
+                let (table_name, row_id): (String, i32) = (row.get(0), row.get(1));
+                if table_name != "titles_immut" {
+                    return Err(XtchdError::InvalidArgument(format!("idempotency key '{}' was used for a write to {}", key, table_name)))
+                }
+                tracing::debug!(idempotency_key = key, id = row_id, "idempotency key already recorded: returning the earlier write");
+                return article_title_link(&tx, row_id).await?
+                    .ok_or_else(|| XtchdError::NotFound("missing row in query for add_article_title()".to_string()))
+            }
+        }
+        let title = title.to_string();
+        let art_title = xrows::ArticleTitle{a_id_immut, auth_id, title, a_id_draft: a_id_draft.clone()};
+        let hclink = HashChainLink::new(&last_article.prior_sha256, &art_title);
+        let stmt = tx.prepare_cached("INSERT INTO titles_immut
+            (                   prior_id,  a_id_draft, a_id_immut, auth_id,            title,               prior_sha256,         write_timestamp,          new_sha256)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ").await?;
+        let _x = tx.execute(&stmt,
+        &[&last_article.prior_id, &a_id_draft, &a_id_immut, &auth_id, &art_title.title, &last_article.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256() ]
+        ).await?;
+        tx.commit().await?;
+        record_etched(a_id_immut, &last_article.prior_sha256, &hclink);
+        Ok((art_title, hclink))
+    }
+
+    /// add_article_title() without an idempotency key, but if this author already has an article with this title, 
+    /// that article is returned instead of appending another 
+    pub async fn add_article_title_dedupe(&mut self, auth_id: i32, a_id_draft: Option<&str>, title: &str) -> Result<(xrows::ArticleTitle, HashChainLink), XtchdError> {
+        if let Some(a_id_immut) = self.find_article_by_title(auth_id, title).await? {
+            if let Some(existing) = article_title_link(&*self.c, a_id_immut).await? {
+                return Ok(existing)
+            }
+        }
+        self.add_article_title(auth_id, a_id_draft, title, None).await
+    }
+
+
+    /// The auth_id of the author whose current name (see rename_author()) is exactly this name, if any, 
+    /// so callers can avoid writing a duplicate 
+    pub async fn find_author_by_name(&self, name: &str) -> Result<Option<i32>, PachyDarn> {
+        let rows = self.c.query("SELECT auth_id FROM author_current WHERE name = $1", &[&name]).await?;
+        Ok(rows.get(0).map(|row| row.get(0)))
+    }
+
+    /// The a_id_immut of the (first) article by this author with exactly this title, if any, so callers can avoid writing a duplicate 
+    pub async fn find_article_by_title(&self, auth_id: i32, title: &str) -> Result<Option<i32>, PachyDarn> {
+        let rows = self.c.query("SELECT a_id_immut FROM titles_immut WHERE auth_id = $1 AND title = $2 ORDER BY a_id_immut LIMIT 1", 
+            &[&auth_id, &title]).await?;
+        Ok(rows.get(0).map(|row| row.get(0)))
+    }
+
+
+    /// add a paragraph (written in Markdown) to an article.
+    /// The plaintext of the paragraph is stored alongside the Markdown for full-text indexing 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "article_para", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_article_para(&mut self, art_id: i32, md: &str) -> Result<(xrows::ArticlePara, HashChainLink), XtchdError> {
+        let (tx, last_para) = self.lock_tail(<xrows::ArticlePara as ContentTable>::chain_table()).await?;
+        let apara_id = last_para.next_id();
+        let para = xrows::ArticlePara{apara_id, art_id, md: md.to_string()};
+        let hclink = HashChainLink::new(&last_para.prior_sha256, &para);
+        let stmt = tx.prepare_cached("INSERT INTO article_para
+            (                  prior_id,  apara_id,  art_id,         md,             plain,                prior_sha256,         write_timestamp,          new_sha256)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)").await?;
+        let _x = tx.execute(&stmt,
+            &[&last_para.prior_id, &apara_id, &art_id, &para.md, &para.plaintext(), &last_para.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
+        ).await?;
+        tx.commit().await?;
+        record_etched(apara_id, &last_para.prior_sha256, &hclink);
+        Ok((para, hclink))
+    }
+
+
+    /// add a (new) page to an article 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "pages_immut", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_article_page(&mut self, a_id_immut: i32, p_id_draft: Option<&str>, paragraphs: Vec<String>, source: xrows::PageSrc) -> Result<(xrows::ArticlePage, HashChainLink), XtchdError> {
+        let mut builder = xrows::ArticlePage::builder().a_id_immut(a_id_immut).paragraphs(paragraphs).source(source);
+        if let Some(p_id_draft) = p_id_draft {
+            builder = builder.p_id_draft(p_id_draft);
+        }
+        self.write_article_page(builder.build()?).await
+    }
+
+    /// Revise a page of an article (i.e. to fix a typo) by appending a new page which supersedes it. 
+    /// The superseded page is unchanged and remains verifiable, but article_detail() returns the revision in its place.
+    /// The revision keeps the p_id_draft of the page it supersedes. Only the latest revision of a page can be revised 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "pages_immut", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn revise_article_page(&mut self, a_id_immut: i32, supersedes_p_id: i32, paragraphs: Vec<String>, source: xrows::PageSrc) -> Result<(xrows::ArticlePage, HashChainLink), XtchdError> {
+        let rows = self.c.query("SELECT p_id_draft, EXISTS (SELECT 1 FROM pages_immut later WHERE later.supersedes = p.p_id_immut)
+            FROM pages_immut p WHERE p.p_id_immut = $1 AND p.a_id_immut = $2", &[&supersedes_p_id, &a_id_immut]).await?;
+        let row = match rows.get(0) {
+            Some(val) => val,
+            None => return Err(XtchdError::NotFound("missing row in query for revise_article_page()".to_string())),
+        };
+        let already_revised: bool = row.get(1);
+        if already_revised {
+            return Err(XtchdError::InvalidArgument(format!("page {} has already been revised: revise its latest revision instead", supersedes_p_id)))
+        }
+        let p_id_draft: String = row.get(0);
+        let page = xrows::ArticlePage::builder().a_id_immut(a_id_immut).p_id_draft(&p_id_draft).paragraphs(paragraphs)
+            .source(source).supersedes(supersedes_p_id).build()?;
+        self.write_article_page(page).await
+    }
+
+    /// Append a page (or a revision of one) built with xrows::ArticlePage::builder() to pages_immut, 
+    /// for add_article_page() and revise_article_page(). The p_id_immut is assigned here.
+    /// Returns XrowError::PageTooLarge if the page exceeds the page_limits
+    async fn write_article_page(&mut self, mut page: xrows::ArticlePage) -> Result<(xrows::ArticlePage, HashChainLink), XtchdError> {
+        page.check_size(&self.page_limits)?;
+        let (tx, last_page) = self.lock_tail(<xrows::ArticlePage as ContentTable>::chain_table()).await?;
+        let p_id_immut = last_page.next_id();
+        page.p_id_immut = p_id_immut;
+        let a_id_immut = page.a_id_immut;
+        let hclink = HashChainLink::new(&last_page.prior_sha256, &page);
+        let (img_id, image_file, refs_a_id_immut) = &page.source.src_columns();
+        let stmt = tx.prepare_cached("INSERT INTO pages_immut
+            (               prior_id,  p_id_draft,  p_id_immut, a_id_immut,        paragraphs, img_id, image_file, refs_a_id_immut, supersedes,              prior_sha256,         write_timestamp,           new_sha256,  hash_v)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) ").await?;
+        let _x = tx.execute(&stmt,
+        &[&last_page.prior_id, &page.p_id_draft, &p_id_immut, &a_id_immut, &page.paragraphs, &img_id, &image_file, &refs_a_id_immut, &page.supersedes, &last_page.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256(), &page.hash_v ]
+        ).await?;
+        tx.commit().await?;
+        record_etched(p_id_immut, &last_page.prior_sha256, &hclink);
+        Ok((page, hclink))
+    }
+
+
+    // create a new record for a youtube channel
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "youtube_channels", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_youtube_channel(&mut self, url: &str, name: &str) -> Result<(xrows::YoutubeChannel, HashChainLink), XtchdError> {
+        let (tx, last_chan) = self.lock_tail(<xrows::YoutubeChannel as ContentTable>::chain_table()).await?;
+        let chan_id = last_chan.next_id();
+        let url = url.to_lowercase();
+        let name = name.to_string();
+        let chan = xrows::YoutubeChannel{chan_id, url, name};
+        let hclink = HashChainLink::new(&last_chan.prior_sha256, &chan);
+        let stmt = tx.prepare_cached("INSERT INTO youtube_channels 
+            (                    prior_id, chan_id,       url,       name,             prior_sha256,        write_timestamp,           new_sha256)
+                VALUES ($1, $2, $3, $4, $5, $6, $7) ").await?;
+        let _x = tx.execute(&stmt,
+            &[&last_chan.prior_id, &chan_id, &chan.url, &chan.name, &last_chan.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
+        ).await?;
+        tx.commit().await?;
+        record_etched(chan_id, &last_chan.prior_sha256, &hclink);
+        Ok((chan, hclink))
+    }
+
+
+    // create a new record for a youtube video 
+    // Returns XrowError::InvalidVidPk unless vid_pk is an 11-character YouTube video id,
+    // and XrowError::Conflict if a video with this vid_pk (the primary key of youtube_videos) already exists 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "youtube_videos", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_youtube_video(&mut self, chan_id: i32, vid_pk: &str, title: &str, date_uploaded: &NaiveDate) -> Result<(xrows::YoutubeVideo, HashChainLink), XtchdError> {
+        xrows::check_vid_pk(vid_pk)?;
+        let (tx, last_vid) = self.lock_tail(<xrows::YoutubeVideo as ContentTable>::chain_table()).await?;
+        let vid_id = last_vid.next_id();
+        let vid_pk = vid_pk.to_string();
+        let title = title.to_string();
+        let date_uploaded = date_uploaded.clone();
+        let video = xrows::YoutubeVideo{vid_id, vid_pk, chan_id, title, date_uploaded};
+        let hclink = HashChainLink::new(&last_vid.prior_sha256, &video);
+        let stmt = tx.prepare_cached("INSERT INTO youtube_videos 
+            (                  prior_id,  vid_id,         vid_pk,       chan_id,        title,        date_uploaded,           prior_sha256,         write_timestamp,           new_sha256)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT (vid_pk) DO NOTHING").await?;
+        let inserted = tx.execute(&stmt,
+            &[&last_vid.prior_id, &vid_id, &video.vid_pk, &video.chan_id, &video.title, &video.date_uploaded, &last_vid.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256()]
+        ).await?;
+        // nothing was written, so the transaction is rolled back (on drop) rather than returning a link that is not in the chain 
+        if inserted == 0 {
+            return Err(XtchdError::Xrow(xrows::XrowError::Conflict{constraint: "youtube_videos_pkey".to_string()}))
+        }
+        tx.commit().await?;
+        record_etched(vid_id, &last_vid.prior_sha256, &hclink);
+        Ok((video, hclink))
+    }
+
+
+    /// Write a channel's videos (i.e. its back catalog) in one transaction, skipping any vid_pk that already exists.
+    /// The table is locked for the transaction and only videos that don't already exist are chained,
+    /// so a skipped video never leaves a gap in (or forks) the chain. 
+    /// Videos are written batch_size rows per INSERT (see DEFAULT_IMPORT_BATCH), each batch following on from the last, 
+    /// so large imports stay within the Postgres limit on parameters per statement 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "youtube_videos", chan_id = chan_id))]
+    pub async fn import_channel_videos(&mut self, chan_id: i32, videos: Vec<xrows::VideoImport>, batch_size: usize) -> Result<views::ImportSummary, XtchdError> {
+        const COLUMNS: usize = 9;
+        if batch_size == 0 || batch_size * COLUMNS > MAX_QUERY_PARAMS {
+            return Err(XtchdError::InvalidArgument(format!("import_channel_videos() requires 0 < batch_size <= {}, got {}", MAX_QUERY_PARAMS / COLUMNS, batch_size)))
+        }
+        // validate every video before writing any, so an invalid id doesn't abort the import part way through 
+        for vi in videos.iter() {
+            xrows::check_vid_pk(&vi.vid_pk)?;
+        }
+        let (tx, mut last_vid) = self.lock_tail(<xrows::YoutubeVideo as ContentTable>::chain_table()).await?;
+        // the advisory lock queues other writers through xtchd, and this also blocks any INSERT made directly 
+        tx.batch_execute("LOCK TABLE youtube_videos IN EXCLUSIVE MODE").await?;
+        // with the table locked, the videos which already exist can be found up front rather than by ON CONFLICT 
+        let vid_pks: Vec<&str> = videos.iter().map(|vi| vi.vid_pk.as_str()).collect();
+        let rows = tx.query("SELECT vid_pk FROM youtube_videos WHERE vid_pk = ANY($1)", &[&vid_pks]).await?;
+        let mut existing: HashSet<String> = rows.iter().map(|row| row.get(0)).collect();
+        let mut summary = views::ImportSummary{imported: Vec::new(), skipped: Vec::new()};
+        let mut links: Vec<(LastRow, HashChainLink)> = Vec::new();
+        for vi in videos {
+            if !existing.insert(vi.vid_pk.clone()) {
+                tracing::warn!(vid_pk = %vi.vid_pk, "video already exists: skipped the write");
+                summary.skipped.push(vi.vid_pk);
+                continue
+            }
+            let vid_id = last_vid.next_id();
+            let video = xrows::YoutubeVideo{vid_id, vid_pk: vi.vid_pk, chan_id, title: vi.title, date_uploaded: vi.date_uploaded};
+            let hclink = HashChainLink::new(&last_vid.prior_sha256, &video);
+            let prior = std::mem::replace(&mut last_vid, LastRow{prior_id: Some(vid_id), prior_sha256: hclink.new_sha256()});
+            links.push((prior, hclink));
+            summary.imported.push(video);
+        }
+        for (videos, links) in summary.imported.chunks(batch_size).zip(links.chunks(batch_size)) {
+            let values: Vec<String> = (0..videos.len())
+                .map(|row| format!("({})", (1..=COLUMNS).map(|col| format!("${}", row * COLUMNS + col)).collect::<Vec<String>>().join(", ")))
+                .collect();
+            let query = format!("INSERT INTO youtube_videos 
+                (prior_id, vid_id, vid_pk, chan_id, title, date_uploaded, prior_sha256, write_timestamp, new_sha256)
+                VALUES {}", values.join(", "));
+            let new_sha256s: Vec<String> = links.iter().map(|(_, hclink)| hclink.new_sha256()).collect();
+            let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(videos.len() * COLUMNS);
+            for ((video, (prior, hclink)), new_sha256) in videos.iter().zip(links).zip(new_sha256s.iter()) {
+                params.extend([&prior.prior_id as &(dyn tokio_postgres::types::ToSql + Sync), &video.vid_id, &video.vid_pk, &video.chan_id, 
+                    &video.title, &video.date_uploaded, &prior.prior_sha256, &hclink.write_timestamp, new_sha256]);
+            }
+            tx.execute(&query, &params).await?;
+            for (video, (prior, hclink)) in videos.iter().zip(links) {
+                tracing::debug!(id = video.vid_id, prior_sha256 = %prior.prior_sha256, new_sha256 = %hclink.new_sha256(), "etched");
+            }
+        }
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+
+    /// add a new immutable image/thumbnail pair, returning the img_id
+    /// Both images are put in canonical form first (see xrows::ImagePair::canonical_data_uri), so they are hashed as canonical.
+    /// Returns XrowError::ImageTooLarge if either image exceeds the image_limits, and XrowError::SvgRejected if either is SVG.
+    /// With the "compress-images" feature, a full image given as a base64 data URI is stored as binary (see xrows::split_data_uri)
+    /// but is hashed, and read back, as the same data URI 
+    #[tracing::instrument(level = "debug", skip_all, fields(table = "images_immut", id = Empty, prior_sha256 = Empty, new_sha256 = Empty))]
+    pub async fn add_image_immutable(&mut self, pair: xrows::ImagePair) -> Result<i32, XtchdError> {
+        let pair = pair.canonicalized()?;
+        pair.check_format()?;
+        pair.check_size(&self.image_limits)?;
+        let (tx, last_ref) = self.lock_tail(<xrows::ImmutableImage as ContentTable>::chain_table()).await?;
+        let img_id = last_ref.next_id();
+        let ii = xrows::ImmutableImage{img_id, pair, hash_v: xrows::IMAGE_HASH_V};
+        let hclink = HashChainLink::new(&last_ref.prior_sha256, &ii);
+        #[cfg(feature = "compress-images")]
+        let (src_full, full_prefix, full_bytes) = match xrows::split_data_uri(&ii.pair.src_full) {
+            Some((prefix, bytes)) => (None, Some(prefix), Some(bytes)),
+            None => (Some(ii.pair.src_full.as_str()), None, None),
+        };
+        #[cfg(not(feature = "compress-images"))]
+        let (src_full, full_prefix, full_bytes): (Option<&str>, Option<&str>, Option<Vec<u8>>) = (Some(ii.pair.src_full.as_str()), None, None);
+        let stmt = tx.prepare_cached("INSERT INTO images_immut 
+            (                  prior_id,  img_id,  src_full,  full_prefix,  full_bytes,          src_thmb,          alt,          url,          archive,           prior_sha256,         write_timestamp,          new_sha256,     hash_v) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)").await?;
+        let _x = tx.execute(&stmt,
+            &[&last_ref.prior_id, &img_id, &src_full, &full_prefix, &full_bytes, &ii.pair.src_thmb, &ii.pair.alt, &ii.pair.url, &ii.pair.archive, &last_ref.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256(), &ii.hash_v]).await?;
+        // an image which cannot be decoded is still written, but without a phash it is never found by similar_images() 
+        #[cfg(feature = "image-audit")]
+        if let Ok(phash) = xrows::image_phash(&ii.pair.src_full) {
+            tx.execute("INSERT INTO image_phashes (img_id, phash) VALUES ($1, $2)", &[&img_id, &(phash as i64)]).await?;
+        }
+        tx.commit().await?;
+        record_etched(img_id, &last_ref.prior_sha256, &hclink);
+        Ok(img_id)
+    }
+
+
+    /// add or update a new mutable image/thumbnail pair, with both images in canonical form (see xrows::ImagePair::canonical_data_uri). 
+    /// An existing image is only overwritten if some field differs,
+    /// so retrying the same upsert leaves the row (and any triggers) untouched and reports MutImageOutcome::Unchanged.
+    /// Returns XrowError::SvgRejected if either image is SVG 
+    pub async fn add_image_mutable(&self, mi: &xrows::MutableImage) -> Result<xrows::MutImageOutcome, XtchdError> {
+        xrows::check_draft_id(&mi.id, xrows::MUTABLE_IMAGE_ID_LEN)?;
+        let src_full = xrows::ImagePair::canonical_data_uri(&mi.pair.src_full)?;
+        let src_thmb = xrows::ImagePair::canonical_data_uri(&mi.pair.src_thmb)?;
+        xrows::reject_svg(&src_full)?;
+        xrows::reject_svg(&src_thmb)?;
+        // xmax is 0 for a newly inserted row and set for an updated one; no row is returned if the WHERE skipped the update 
+        let rows = self.c.query("INSERT INTO images_mut
+            (            id,          src_full,          src_thmb,          alt,          url) VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT(id) DO UPDATE SET src_full = $2, src_thmb = $3, alt = $4, url = $5
+                WHERE (images_mut.src_full, images_mut.src_thmb, images_mut.alt, images_mut.url) IS DISTINCT FROM ($2, $3, $4, $5)
+                RETURNING (xmax = 0)",
+            &[&mi.id, &src_full, &src_thmb, &mi.pair.alt, &mi.pair.url]).await?;
+        Ok(match rows.get(0).map(|row| row.get::<_, bool>(0)) {
+            Some(true) => xrows::MutImageOutcome::Inserted,
+            Some(false) => xrows::MutImageOutcome::Updated,
+            None => xrows::MutImageOutcome::Unchanged,
+        })
+    }
+
+}
+
+
+#[cfg(test)]
+impl Xtchr {
+    /// Panic unless Postgres accepts the new_sha256 computed in Rust (see Xtchable::state_string) for this content,
+    /// i.e. unless the *_verify_sha256 CHECK constraint of the table for T has not drifted from the struct.
+    /// The content is inserted into a scratch copy of the table carrying only that constraint, 
+    /// so the row need not follow on from the chain (nor be written within a second of ts)
+    pub async fn assert_constraint_parity<T: Xtchable + Serialize>(&self, content: &T, prior_sha256: &str, ts: DateTime<Utc>) {
+        let table = CHAIN_TABLES.iter().find(|table| table.dtype == T::dtype())
+            .unwrap_or_else(|| panic!("no table with hash chain integrity holds {}", T::dtype()));
+        let scratch = format!("pg_temp.parity_{}", table.name);
+        let row = self.c.query_one("SELECT pg_get_constraintdef(oid) FROM pg_constraint 
+            WHERE conrelid = $1::TEXT::REGCLASS AND contype = 'c' AND conname LIKE '%\\_verify\\_sha256'", &[&table.name]).await.unwrap();
+        let check: String = row.get(0);
+        self.c.batch_execute(&format!("CREATE TABLE {scratch} AS SELECT * FROM {} WITH NO DATA; 
+            ALTER TABLE {scratch} ADD CONSTRAINT parity_verify_sha256 {check}", table.name)).await.unwrap();
+        // the columns are named for the fields of the content, except those of nested structs (ImmutableImage.pair)
+        // which are flattened, and the PageSrc of an ArticlePage which is one of three columns 
+        let mut columns = serde_json::Map::new();
+        for (field, value) in serde_json::to_value(content).unwrap().as_object().unwrap() {
+            match (field.as_str(), value) {
+                ("source", value) => {
+                    let (img_id, image_file, refs_a_id_immut) = serde_json::from_value::<xrows::PageSrc>(value.clone()).unwrap().src_columns();
+                    columns.insert("img_id".to_string(), serde_json::json!(img_id));
+                    columns.insert("image_file".to_string(), serde_json::json!(image_file));
+                    columns.insert("refs_a_id_immut".to_string(), serde_json::json!(refs_a_id_immut));
+                },
+                (_, serde_json::Value::Object(nested)) => columns.extend(nested.clone()),
+                (field, value) => { columns.insert(field.to_string(), value.clone()); },
+            }
+        }
+        let hclink = HashChainLink::from_timestamp(prior_sha256, ts, content);
+        columns.insert("prior_sha256".to_string(), serde_json::json!(prior_sha256));
+        columns.insert("write_timestamp".to_string(), serde_json::json!(ts.to_rfc3339()));
+        columns.insert("new_sha256".to_string(), serde_json::json!(hclink.new_sha256()));
+        let json = serde_json::Value::Object(columns).to_string();
+        let inserted = self.c.execute(&format!("INSERT INTO {scratch} SELECT * FROM json_populate_record(NULL::{scratch}, $1::TEXT::JSON)"), &[&json]).await;
+        self.c.batch_execute(&format!("DROP TABLE {scratch}")).await.unwrap();
+        if let Err(e) = inserted {
+            panic!("{} rejected the Rust hash of '{}': {}", table.name, hclink.string_to_hash, e);
+        }
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tokio::runtime::Runtime;
+    use crate::test_harness::{TestSchema, draft_id};
+
+    #[test]
+    fn test_init_author() {
+        // Test the author_detail function by getting the initia "seed" author
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let au = ts.x.author_detail(0, VerifyMode::Trust).await.unwrap();
+            assert_eq!(au.author.content.name, "Xtchd Admins".to_string());
+            // the genesis row has no prior row: its prior_id is NULL and its prior_sha256 is the genesis constant 
+            assert_eq!(au.author.prior_id, None);
+            assert_eq!(au.author.prior_sha256, GENESIS_SHA256);
+            assert!(au.author.hcl.string_to_hash.ends_with(&format!(" prior_sha256={}", GENESIS_SHA256)));
+            assert!(ts.x.author_detail(0, VerifyMode::Recompute).await.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_idempotent_article_title() {
+        // Retrying add_article_title with the same idempotency key must not append a second article 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let key = "test_idempotent_article_title";
+            let title = "Idempotency test";
+            let (first, first_hcl) = x.add_article_title(0, Some(draft_id(0).as_str()), title, Some(key)).await.unwrap();
+            let (retry, retry_hcl) = x.add_article_title(0, Some(draft_id(0).as_str()), title, Some(key)).await.unwrap();
+            assert_eq!(first.a_id_immut, retry.a_id_immut);
+            assert_eq!(first_hcl.string_to_hash, retry_hcl.string_to_hash);
+            assert_eq!(first_hcl.new_sha256(), retry_hcl.new_sha256());
+            let rows = x.c.query("SELECT COUNT(*) FROM titles_immut WHERE title = $1", &[&title]).await.unwrap();
+            let count: i64 = rows[0].get(0);
+            assert_eq!(count, 1);
+            // a write which fails records no key, so retrying it (once fixed) still writes the title 
+            let key = "test_idempotent_article_title_failed";
+            assert!(x.add_article_title(999, None, title, Some(key)).await.is_err());
+            let (written, _) = x.add_article_title(0, None, "Idempotency retried", Some(key)).await.unwrap();
+            assert_eq!(written.a_id_immut, first.a_id_immut + 1);
+            let (retry, _) = x.add_article_title(0, None, "Idempotency retried", Some(key)).await.unwrap();
+            assert_eq!(retry.a_id_immut, written.a_id_immut);
+        });
+    }
+
+    #[test]
+    fn test_debug_state_string() {
+        // The string_to_hash for the seed author must match the CONCAT(...) in the auth_verify_sha256 constraint
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let author = xrows::Author{auth_id: 0, name: "Xtchd Admins".to_string()};
+            let written = Utc.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).unwrap();
+            let prior_sha256 = "0000000000000000000000000000000000000000000000000000000000000000";
+            let expected = "auth_id=0 name=Xtchd Admins write_timestamp=2023.01.02 03:04:05 prior_sha256=0000000000000000000000000000000000000000000000000000000000000000";
+            assert_eq!(ts.x.debug_state_string(&author, prior_sha256, written), expected);
+        });
+    }
+
+    #[test]
+    fn test_add_image_mutable_outcome() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let x = &ts.x;
+            let image = |alt: &str| xrows::MutableImage{id: "mutableimage0001".to_string(), pair: xrows::ImagePair{
+                src_full: "data:image/png;base64,QUJD".to_string(), src_thmb: "data:image/png;base64,QUI=".to_string(), 
+                alt: alt.to_string(), url: None, archive: None}};
+            assert_eq!(x.add_image_mutable(&image("Thumbnail")).await.unwrap(), xrows::MutImageOutcome::Inserted);
+            assert_eq!(x.add_image_mutable(&image("Thumbnail")).await.unwrap(), xrows::MutImageOutcome::Unchanged);
+            assert_eq!(x.add_image_mutable(&image("Recaptioned")).await.unwrap(), xrows::MutImageOutcome::Updated);
+            assert_eq!(x.add_image_mutable(&image("Recaptioned")).await.unwrap(), xrows::MutImageOutcome::Unchanged);
+            // the same image written with a non-canonical data URI is stored canonically, so is unchanged
+            let mut messy = image("Recaptioned");
+            messy.pair.src_full = "DATA:Image/PNG;Base64,QU\nJD".to_string();
+            assert_eq!(x.add_image_mutable(&messy).await.unwrap(), xrows::MutImageOutcome::Unchanged);
+            let alt: String = x.c.query_one("SELECT alt FROM images_mut WHERE id = 'mutableimage0001'", &[]).await.unwrap().get(0);
+            assert_eq!(alt, "Recaptioned");
+        });
+    }
+
+    #[test]
+    fn test_svg_rejected_on_write() {
+        // SVG can embed script, so it is rejected as either image of a pair, immutable or mutable, however it is labeled 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let svg = "data:image/png;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciPjwvc3ZnPg==";
+            let png = "data:image/png;base64,iVBORw0KGgo=";
+            for (src_full, src_thmb) in [(svg, png), (png, svg)] {
+                let pair = || xrows::ImagePair{src_full: src_full.to_string(), src_thmb: src_thmb.to_string(), alt: "SVG".to_string(), url: None, archive: None};
+                assert!(matches!(x.add_image_immutable(pair()).await, Err(XtchdError::Xrow(xrows::XrowError::SvgRejected))));
+                let mutable = xrows::MutableImage{id: "mutableimage0002".to_string(), pair: pair()};
+                assert!(matches!(x.add_image_mutable(&mutable).await, Err(XtchdError::Xrow(xrows::XrowError::SvgRejected))));
+            }
+            let count: i64 = x.c.query_one("SELECT (SELECT COUNT(*) FROM images_immut) + (SELECT COUNT(*) FROM images_mut)", &[]).await.unwrap().get(0);
+            assert_eq!(count, 0);
+        });
+    }
+
+    #[test]
+    fn test_present_content_types() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            assert_eq!(x.present_content_types().await.unwrap(), vec!["Author"]);
+            x.add_article_title(0, Some(draft_id(0).as_str()), "Present types test", None).await.unwrap();
+            assert_eq!(x.present_content_types().await.unwrap(), vec!["Author", "ArticleTitle"]);
+        });
+    }
+
+    #[test]
+    fn test_counts() {
+        // a fresh schema has the seed author and channel: add a known number of rows on top of those 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Count test 1").await.unwrap();
+            x.add_author("Count test 2").await.unwrap();
+            let (art, _) = x.add_article_title(1, Some(draft_id(0).as_str()), "Count test", None).await.unwrap();
+            x.add_youtube_channel("c/CountTest", "Count test").await.unwrap();
+            // a page revised twice is still one page 
+            let (page, _) = x.add_article_page(art.a_id_immut, None, vec!["Count test".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let (revised, _) = x.revise_article_page(art.a_id_immut, page.p_id_immut, vec!["Count test, revised".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            x.revise_article_page(art.a_id_immut, revised.p_id_immut, vec!["Count test, revised again".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let expected = views::ContentCounts{authors: 3, articles: 1, pages: 1, paragraphs: 0, channels: 2, videos: 0, images: 0};
+            assert_eq!(x.counts().await.unwrap(), expected);
+        });
+    }
+
+    #[test]
+    fn test_channels_with_counts() {
+        // the seed channel and a new channel have no videos, but are listed after the channel that does  
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let uploaded = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+            let (empty, _) = x.add_youtube_channel("c/EmptyCountTest", "Empty count test").await.unwrap();
+            let (busy, _) = x.add_youtube_channel("c/BusyCountTest", "Busy count test").await.unwrap();
+            for vid_pk in ["chancount01", "chancount02"] {
+                x.add_youtube_video(busy.chan_id, vid_pk, vid_pk, &uploaded).await.unwrap();
+            }
+            let counts: Vec<(i32, i64)> = x.channels_with_counts().await.unwrap().iter().map(|(chan, count)| (chan.chan_id, *count)).collect();
+            assert_eq!(counts, vec![(busy.chan_id, 2), (0, 0), (empty.chan_id, 0)]);
+        });
+    }
+
+    #[test]
+    fn test_verify_on_read() {
+        // Tamper with the seed author's name (keeping its hash):
+        // the trusting read returns the tampered name while the recomputing read rejects it 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let x = &ts.x;
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256;
+                ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later;
+                UPDATE authors SET name = 'Tampered Admins' WHERE auth_id = 0;").await.unwrap();
+            let trusted = x.author_detail(0, VerifyMode::Trust).await;
+            let verified = x.author_detail(0, VerifyMode::Recompute).await;
+            assert_eq!(trusted.unwrap().author.content.name, "Tampered Admins".to_string());
+            assert!(matches!(verified, Err(XtchdError::Integrity(_))));
+        });
+    }
+
+    #[test]
+    fn test_article_pages_range() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(0).as_str()), "Pages range test", None).await.unwrap();
+            for i in 0..3 {
+                let paragraphs = vec![format!("Page {}", i)];
+                x.add_article_page(art.a_id_immut, Some(draft_id(i).as_str()), paragraphs, xrows::PageSrc::Author(String::new())).await.unwrap();
+            }
+            let middle = x.article_pages_range(art.a_id_immut, 1, 5).await.unwrap();
+            assert_eq!(middle.iter().map(|page| page.content.paragraphs[0].clone()).collect::<Vec<String>>(), vec!["Page 1", "Page 2"]);
+            let past_the_end = x.article_pages_range(art.a_id_immut, 3, 5).await.unwrap();
+            assert!(past_the_end.is_empty());
+            let no_pages = x.article_pages_range(art.a_id_immut, 0, 0).await;
+            assert!(matches!(no_pages, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_article_plaintext() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, None, "Plaintext test", None).await.unwrap();
+            assert_eq!(x.article_plaintext(art.a_id_immut).await.unwrap(), "");
+            let first = vec!["First page, first paragraph.".to_string(), "First page, second paragraph.".to_string()];
+            let (page, _) = x.add_article_page(art.a_id_immut, None, first, xrows::PageSrc::Author(String::new())).await.unwrap();
+            x.add_article_page(art.a_id_immut, None, vec!["Second page.".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let text = x.article_plaintext(art.a_id_immut).await.unwrap();
+            assert_eq!(text, "First page, first paragraph.\n\nFirst page, second paragraph.\n\nSecond page.");
+            // a revised page keeps its place in the order 
+            x.revise_article_page(art.a_id_immut, page.p_id_immut, vec!["First page, revised.".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            assert_eq!(x.article_plaintext(art.a_id_immut).await.unwrap(), "First page, revised.\n\nSecond page.");
+            x.tombstone("titles_immut", art.a_id_immut, "Plaintext test redaction").await.unwrap();
+            assert_eq!(x.article_plaintext(art.a_id_immut).await.unwrap(), "");
+        });
+    }
+
+    #[test]
+    fn test_topic_detail() {
+        // link a new topic to two new articles and confirm both are listed, the more-mentioned one first 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let tkey = "test_topic";
+            x.c.execute("INSERT INTO nlp_topics (pos, tkey, name) VALUES ('NCK', $1, $1)", &[&tkey]).await.unwrap();
+            let mut a_ids = Vec::new();
+            for (i, mentions) in [1, 2].iter().enumerate() {
+                let title = format!("Topic test {}", i);
+                let (art, _) = x.add_article_title(0, Some(draft_id(i as i32).as_str()), &title, None).await.unwrap();
+                for _ in 0..*mentions {
+                    let (para, _) = x.add_article_para(art.a_id_immut, &format!("Mentions {}", tkey)).await.unwrap();
+                    x.c.execute("INSERT INTO apara_ment_topic (tkey, art_id, apara_id) VALUES ($1, $2, $3)", 
+                        &[&tkey, &art.a_id_immut, &para.apara_id]).await.unwrap();
+                }
+                a_ids.push(art.a_id_immut);
+            }
+            let detail = x.topic_detail(tkey).await.unwrap();
+            let listed: Vec<i32> = detail.articles.iter().map(|name_id| name_id.id).collect();
+            assert_eq!(listed, vec![a_ids[1], a_ids[0]]);
+            assert!(x.topic_detail("no such topic").await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_related_articles() {
+        // the first article shares two topics with the second and one with the third
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for tkey in ["related_a", "related_b", "related_c"] {
+                x.c.execute("INSERT INTO nlp_topics (pos, tkey, name) VALUES ('NCK', $1, $1)", &[&tkey]).await.unwrap();
+            }
+            let mut a_ids = Vec::new();
+            for (i, tkeys) in [vec!["related_a", "related_b", "related_c"], vec!["related_a", "related_b"], vec!["related_c"], vec![]].iter().enumerate() {
+                let (art, _) = x.add_article_title(0, None, &format!("Related test {}", i), None).await.unwrap();
+                for tkey in tkeys {
+                    // the same topic mentioned twice in one article counts once 
+                    for _ in 0..2 {
+                        let (para, _) = x.add_article_para(art.a_id_immut, &format!("Mentions {}", tkey)).await.unwrap();
+                        x.c.execute("INSERT INTO apara_ment_topic (tkey, art_id, apara_id) VALUES ($1, $2, $3)", 
+                            &[tkey, &art.a_id_immut, &para.apara_id]).await.unwrap();
+                    }
+                }
+                a_ids.push(art.a_id_immut);
+            }
+            let related: Vec<(i32, i64)> = x.related_articles(a_ids[0], 10).await.unwrap().iter().map(|(art, shared)| (art.id, *shared)).collect();
+            assert_eq!(related, vec![(a_ids[1], 2), (a_ids[2], 1)]);
+            assert_eq!(x.related_articles(a_ids[0], 1).await.unwrap().len(), 1);
+            assert!(x.related_articles(a_ids[3], 10).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_topic_paragraph_snippets() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.c.execute("INSERT INTO nlp_topics (pos, tkey, name) VALUES ('PER', 'ada_lovelace', 'Ada Lovelace')", &[]).await.unwrap();
+            let (art, _) = x.add_article_title(0, None, "Snippet test", None).await.unwrap();
+            let (mentions, _) = x.add_article_para(art.a_id_immut, "The analytical engine was described by Ada Lovelace in her notes.").await.unwrap();
+            x.add_article_para(art.a_id_immut, "Ada Lovelace is named here but not linked to the topic.").await.unwrap();
+            x.c.execute("INSERT INTO apara_ment_topic (tkey, art_id, apara_id) VALUES ('ada_lovelace', $1, $2)", 
+                &[&art.a_id_immut, &mentions.apara_id]).await.unwrap();
+            let snippets = x.topic_paragraph_snippets("ada_lovelace", 10).await.unwrap();
+            assert_eq!(snippets.len(), 1);
+            assert_eq!((snippets[0].apara_id, snippets[0].art_id), (mentions.apara_id, art.a_id_immut));
+            assert!(snippets[0].snippet.contains("<b>Ada</b> <b>Lovelace</b>"), "{}", snippets[0].snippet);
+            assert!(x.topic_paragraph_snippets("no_such_topic", 10).await.unwrap().is_empty());
+        });
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    #[ignore = "requires a Postgres server that requires TLS, configured with PSQL_* and PSQL_SSLMODE=require"]
+    fn test_tls_pool() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            assert_eq!(SslMode::from_env(), SslMode::Require);
+            let pool = Pool::new_from_env().await;
+            let x = pool.get().await.unwrap();
+            let row = x.c.query_one("SELECT ssl FROM pg_stat_ssl WHERE pid = pg_backend_pid()", &[]).await.unwrap();
+            let ssl: bool = row.get(0);
+            assert!(ssl);
+        });
+    }
+
+    #[test]
+    fn test_export_chain() {
+        // export the authors chain, confirm it verifies, then tamper with one item and confirm it does not
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Export test 1").await.unwrap();
+            x.add_author("Export test 2").await.unwrap();
+            let mut chain = x.export_chain::<xrows::Author>("authors").await.unwrap();
+            assert_eq!(chain.len(), 3);
+            assert!(crate::integrity::verify_bundle(&chain).is_ok());
+            let json = serde_json::to_string(&chain).unwrap();
+            let _round_trip: Vec<XtchdContent<xrows::Author>> = serde_json::from_str(&json).unwrap();
+            chain[1].content.name = "Tampered".to_string();
+            assert!(crate::integrity::verify_bundle(&chain).is_err());
+            assert!(x.export_chain::<xrows::Author>("pg_authid").await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_chain_segment() {
+        // a segment from the middle of the chain verifies against the new_sha256 of the row before it 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let mut hashes = vec![x.find_head_sha256(chain_table("authors").unwrap(), 0).await.unwrap()];
+            for i in 1..6 {
+                let (_, hcl) = x.add_author(&format!("Segment test {}", i)).await.unwrap();
+                hashes.push(hcl.new_sha256());
+            }
+            let segment = x.chain_segment::<xrows::Author>("authors", 2, 4).await.unwrap();
+            assert_eq!(segment.iter().map(|author| author.content.auth_id).collect::<Vec<i32>>(), vec![2, 3, 4]);
+            assert_eq!(segment[0].prior_sha256, hashes[1]);
+            crate::integrity::verify_batch(&segment, Some(1), &hashes[1]).unwrap();
+            assert!(crate::integrity::verify_batch(&segment, Some(0), &hashes[0]).is_err());
+            let single = x.chain_segment::<xrows::Author>("authors", 5, 5).await.unwrap();
+            assert_eq!(single.len(), 1);
+            assert!(matches!(x.chain_segment::<xrows::Author>("authors", 4, 2).await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_pool_stats() {
+        // new_from_env() opens (and returns) one connection, which is then available to get()
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let pool = Pool::new_from_env().await;
+            let before = pool.stats();
+            assert_eq!(before.available, 1);
+            let _x = pool.get().await.unwrap();
+            let after = pool.stats();
+            assert_eq!(after.available, before.available - 1);
+            assert_eq!(after.in_use, before.in_use + 1);
+        });
+    }
+
+    #[test]
+    fn test_pool_max_size() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let pool = Pool::from_config(PoolConfig{max_size: Some(3), ..PoolConfig::from_env_prefixed("")}).await;
+            assert_eq!(pool.stats().max_size, 3);
+        });
+    }
+
+    #[test]
+    fn test_verify_row() {
+        // spot check a good row, then tamper with one row's content and another row's predecessor
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for i in 1..4 {
+                x.add_author(&format!("Verify row test {}", i)).await.unwrap();
+            }
+            let good = RowVerification{prior_matches: true, hash_matches: true};
+            assert_eq!(x.verify_row("authors", 0).await.unwrap(), good);
+            assert_eq!(x.verify_row("authors", 1).await.unwrap(), good);
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256;
+                ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later;
+                ALTER TABLE authors DROP CONSTRAINT auth_no_delete;
+                UPDATE authors SET name = 'Tampered' WHERE auth_id = 1;
+                UPDATE authors SET new_sha256 = REPEAT('f', 64) WHERE auth_id = 2;").await.unwrap();
+            let tampered = x.verify_row("authors", 1).await.unwrap();
+            assert_eq!(tampered, RowVerification{prior_matches: true, hash_matches: false});
+            let broken_prior = x.verify_row("authors", 3).await.unwrap();
+            assert_eq!(broken_prior, RowVerification{prior_matches: false, hash_matches: true});
+            assert!(!broken_prior.is_valid());
+            assert!(x.verify_row("authors", 99).await.is_err());
+            assert!(x.verify_row("pg_authid", 0).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_verify_channel() {
+        // one channel's videos verify, then a tampered video of the other channel is reported 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let date = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+            let (good, _) = x.add_youtube_channel("https://www.youtube.com/@verifygood", "Verify Good").await.unwrap();
+            let (bad, _) = x.add_youtube_channel("https://www.youtube.com/@verifybad", "Verify Bad").await.unwrap();
+            x.add_youtube_video(good.chan_id, "aaaaaaaaaa1", "Good one", &date).await.unwrap();
+            let (tampered, _) = x.add_youtube_video(bad.chan_id, "bbbbbbbbbb1", "Bad one", &date).await.unwrap();
+            x.add_youtube_video(bad.chan_id, "bbbbbbbbbb2", "Bad two", &date).await.unwrap();
+            x.add_youtube_video(good.chan_id, "aaaaaaaaaa2", "Good two", &date).await.unwrap();
+            let verification = x.verify_channel(good.chan_id).await.unwrap();
+            assert!(verification.is_valid());
+            assert_eq!(verification.videos_checked, 2);
+            x.c.batch_execute(&format!("ALTER TABLE youtube_videos DROP CONSTRAINT ytvid_verify_sha256;
+                ALTER TABLE youtube_videos DROP CONSTRAINT ytvid_no_rewrite_later;
+                UPDATE youtube_videos SET title = 'Tampered' WHERE vid_id = {};", tampered.vid_id)).await.unwrap();
+            let verification = x.verify_channel(bad.chan_id).await.unwrap();
+            assert!(!verification.is_valid());
+            assert!(verification.channel.is_valid());
+            assert_eq!(verification.videos_checked, 2);
+            assert_eq!(verification.failed_videos, vec![tampered.vid_id]);
+            assert!(x.verify_channel(good.chan_id).await.unwrap().is_valid());
+            assert!(matches!(x.verify_channel(99).await, Err(XtchdError::NotFound(_))));
+        });
+    }
+
+    #[test]
+    fn test_recent_activity_pages() {
+        // page through 6 authors (the seed author + 5 more) 2 at a time, newest first
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for i in 1..6 {
+                x.add_author(&format!("Activity test {}", i)).await.unwrap();
+            }
+            let mut before = None;
+            let mut pages: Vec<Vec<i32>> = Vec::new();
+            loop {
+                let page = x.recent_activity(2, before, Some(vec![views::ContentClass::Author])).await.unwrap();
+                if page.is_empty() {
+                    break;
+                }
+                before = page.last().map(views::ActivityItem::cursor);
+                pages.push(page.iter().map(|item| item.id).collect());
+            }
+            assert_eq!(pages, vec![vec![5, 4], vec![3, 2], vec![1, 0]]);
+            // items written at the same instant are ordered by id, so neither is skipped by the next page 
+            let (tx, last_row) = x.lock_tail(chain_table("authors").unwrap()).await.unwrap();
+            let written = integrity::now();
+            let first = xrows::Author{auth_id: last_row.next_id(), name: "Activity twin 1".to_string()};
+            let first_hcl = HashChainLink::from_timestamp(&last_row.prior_sha256, written, &first);
+            insert_row(&tx, "authors", &first, &last_row, &first_hcl).await.unwrap();
+            let last_row = LastRow{prior_id: Some(first.auth_id), prior_sha256: first_hcl.new_sha256()};
+            let second = xrows::Author{auth_id: last_row.next_id(), name: "Activity twin 2".to_string()};
+            insert_row(&tx, "authors", &second, &last_row, &HashChainLink::from_timestamp(&last_row.prior_sha256, written, &second)).await.unwrap();
+            tx.commit().await.unwrap();
+            let newest = x.recent_activity(1, None, Some(vec![views::ContentClass::Author])).await.unwrap();
+            let next = x.recent_activity(1, newest.last().map(views::ActivityItem::cursor), Some(vec![views::ContentClass::Author])).await.unwrap();
+            assert_eq!((newest[0].id, next[0].id), (second.auth_id, first.auth_id));
+            assert_eq!(newest[0].write_timestamp, next[0].write_timestamp);
+        });
+    }
+
+    #[test]
+    fn test_recent_activity_types() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Activity type test").await.unwrap();
+            let (chan, _) = x.add_youtube_channel("c/ActivityTypeTest", "Activity type test").await.unwrap();
+            let channels = x.recent_activity(10, None, Some(vec![views::ContentClass::YoutubeChannel])).await.unwrap();
+            assert!(channels.iter().all(|item| item.class == views::ContentClass::YoutubeChannel));
+            assert_eq!(channels[0].id, chan.chan_id);
+            let all = x.recent_activity(10, None, None).await.unwrap();
+            assert!(all.iter().any(|item| item.class == views::ContentClass::Author));
+        });
+    }
+
+    /// Records which types were warmed instead of writing to Redis
+    struct FakeWarmer {
+        warmed: Vec<&'static str>,
+    }
+
+    impl CacheWarmer for FakeWarmer {
+        async fn warm<U, T: CachedAutoComp<U>>(&mut self, _c: &ClientNoTLS) -> Result<usize, PachyDarn> {
+            self.warmed.push(T::dtype());
+            Ok(self.warmed.len())
+        }
+    }
+
+    #[test]
+    fn test_warm_all_caches() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let mut warmer = FakeWarmer{warmed: Vec::new()};
+            let summary = ts.x.warm_all_caches(&mut warmer).await.unwrap();
+            assert_eq!(warmer.warmed, vec!["author", "Image", "Topic"]);
+            assert_eq!(summary, WarmSummary{authors: 1, images: 2, topics: 3});
+        });
+    }
+
+    #[test]
+    fn test_tombstone() {
+        // a tombstoned article reads as redacted, while both its chain and the tombstones chain still verify 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(0).as_str()), "Tombstone test", None).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(0).as_str()), vec!["Takedown".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let (tomb, _) = x.tombstone("titles_immut", art.a_id_immut, "legal takedown").await.unwrap();
+            let detail = x.article_detail(art.a_id_immut, VerifyMode::Recompute).await.unwrap();
+            assert_eq!(detail.title.content.title, xrows::REDACTED);
+            assert!(detail.pages.is_empty());
+            assert_eq!(detail.redacted, Some(tomb.clone()));
+            assert!(x.verify_row("titles_immut", art.a_id_immut).await.unwrap().is_valid());
+            assert!(x.verify_row("tombstones", tomb.tomb_id).await.unwrap().is_valid());
+            assert!(x.tombstone("titles_immut", 99, "no such article").await.is_err());
+        });
+    }
+
+    /// The ids (and the author's new_sha256) of an author and an article which are redacted, and of an author and article which are not.
+    /// Each article has one page and one paragraph mentioning narwhals 
+    struct RedactedFixture {
+        visible_author: i32,
+        hidden_author: i32,
+        hidden_author_sha256: String,
+        visible_article: i32,
+        hidden_article: i32,
+    }
+
+    async fn redacted_fixture(x: &mut Xtchr) -> RedactedFixture {
+        let (visible, _) = x.add_author("Visible redaction author").await.unwrap();
+        let (hidden, hidden_hcl) = x.add_author("Hidden redaction author").await.unwrap();
+        let mut articles = Vec::new();
+        for (i, (auth_id, title)) in [(visible.auth_id, "Visible redaction article"), (hidden.auth_id, "Hidden redaction article")].into_iter().enumerate() {
+            let (art, _) = x.add_article_title(auth_id, Some(draft_id(i as i32).as_str()), title, None).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(10 + i as i32).as_str()), vec![format!("{} narwhals", title)], xrows::PageSrc::Author(String::new())).await.unwrap();
+            x.add_article_para(art.a_id_immut, &format!("{} narwhals", title)).await.unwrap();
+            articles.push(art.a_id_immut);
+        }
+        x.tombstone("authors", hidden.auth_id, "legal takedown").await.unwrap();
+        x.tombstone("titles_immut", articles[1], "legal takedown").await.unwrap();
+        RedactedFixture{visible_author: visible.auth_id, hidden_author: hidden.auth_id, hidden_author_sha256: hidden_hcl.new_sha256(), 
+            visible_article: articles[0], hidden_article: articles[1]}
+    }
+
+    #[test]
+    fn test_redacted_article_pages_range() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            assert_eq!(x.article_pages_range(fx.visible_article, 0, 10).await.unwrap().len(), 1);
+            assert!(x.article_pages_range(fx.hidden_article, 0, 10).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_redacted_recent_activity() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let items: Vec<(views::ContentClass, i32)> = x.recent_activity(100, None, None).await.unwrap()
+                .iter().map(|item| (item.class, item.id)).collect();
+            assert!(items.contains(&(views::ContentClass::Author, fx.visible_author)));
+            assert!(items.contains(&(views::ContentClass::ArticleTitle, fx.visible_article)));
+            assert!(!items.contains(&(views::ContentClass::Author, fx.hidden_author)));
+            assert!(!items.contains(&(views::ContentClass::ArticleTitle, fx.hidden_article)));
+        });
+    }
+
+    #[test]
+    fn test_redacted_export_chain() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let ids: Vec<i32> = x.export_chain::<xrows::Author>("authors").await.unwrap().iter().map(|author| author.content.auth_id).collect();
+            assert!(ids.contains(&fx.visible_author));
+            assert!(!ids.contains(&fx.hidden_author));
+        });
+    }
+
+    #[test]
+    fn test_redacted_chain_segment() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let segment = x.chain_segment::<xrows::ArticleTitle>("titles_immut", fx.visible_article, fx.hidden_article).await.unwrap();
+            assert_eq!(segment.iter().map(|title| title.content.a_id_immut).collect::<Vec<i32>>(), vec![fx.visible_article]);
+        });
+    }
+
+    #[test]
+    fn test_redacted_export_paragraphs_ndjson() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let fx = redacted_fixture(&ts.x).await;
+            let mut buf: Vec<u8> = Vec::new();
+            assert_eq!(ts.x.export_paragraphs_ndjson(&mut buf).await.unwrap(), 1);
+            let para: xrows::ArticlePara = serde_json::from_slice(&buf).unwrap();
+            assert_eq!(para.art_id, fx.visible_article);
+        });
+    }
+
+    #[test]
+    fn test_redacted_search_paragraphs() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            for config in [xrows::TsConfig::English, xrows::TsConfig::Simple] {
+                let art_ids: Vec<i32> = x.search_paragraphs("narwhals", config).await.unwrap().iter().map(|hit| hit.art_id).collect();
+                assert_eq!(art_ids, vec![fx.visible_article], "{:?}", config);
+            }
+        });
+    }
+
+    #[test]
+    fn test_redacted_autocomplete() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let results = x.autocomplete_cached_or_db::<i32, xrows::Author, _>(&mut FailingCache, "redaction author").await.unwrap();
+            assert_eq!(results.iter().map(|www| www.pk).collect::<Vec<i32>>(), vec![fx.visible_author]);
+            for order in [xrows::AutoCompOrder::Length, xrows::AutoCompOrder::Popularity] {
+                let results = x.autocomplete_authors("redaction author", 5, order, xrows::TsConfig::Simple).await.unwrap();
+                assert_eq!(results.iter().map(|www| www.pk).collect::<Vec<i32>>(), vec![fx.visible_author]);
+            }
+        });
+    }
+
+    #[test]
+    fn test_redacted_find_by_sha256() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            assert_eq!(x.find_by_sha256("authors", &fx.hidden_author_sha256).await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn test_redacted_authors_by_ids() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let authors = x.authors_by_ids(&[fx.visible_author, fx.hidden_author]).await.unwrap();
+            assert_eq!(authors.keys().copied().collect::<Vec<i32>>(), vec![fx.visible_author]);
+        });
+    }
+
+    #[test]
+    fn test_redacted_all_authors() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let fx = redacted_fixture(x).await;
+            let ids: Vec<i32> = x.all_authors().await.unwrap().iter().map(|name_id| name_id.id).collect();
+            assert!(ids.contains(&fx.visible_author));
+            assert!(!ids.contains(&fx.hidden_author));
+        });
+    }
+
+    #[test]
+    fn test_list_thumbnails() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for i in 0..3 {
+                let pair = xrows::ImagePair{src_full: format!("full{}", i), src_thmb: format!("thmb{}", i), alt: format!("Thumbnail test {}", i), url: None, archive: None};
+                x.add_image_immutable(pair).await.unwrap();
+            }
+            let thumbnails = x.list_thumbnails(2, 0).await.unwrap();
+            assert_eq!(thumbnails.iter().map(|thumb| thumb.src_thmb.as_str()).collect::<Vec<&str>>(), vec!["thmb2", "thmb1"]);
+            // the serialized thumbnail carries only the id and thumbnail, never the full image 
+            let json = serde_json::to_value(&thumbnails[0]).unwrap();
+            let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+            keys.sort();
+            assert_eq!(keys, vec!["img_id", "src_thmb"]);
+        });
+    }
+
+    #[test]
+    fn test_reindex_fulltext() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(1).as_str()), "Reindex test", None).await.unwrap();
+            for md in ["Imported narwhals", "More imported narwhals", "Unrelated"] {
+                x.add_article_para(art.a_id_immut, md).await.unwrap();
+            }
+            assert_eq!(x.reindex_fulltext("article_para").await.unwrap(), 3);
+            // the rebuilt index still finds every matching row 
+            x.c.batch_execute("SET enable_seqscan = off").await.unwrap();
+            let found = x.search_paragraphs("narwhals", xrows::TsConfig::English).await.unwrap();
+            assert_eq!(found.len(), 2);
+            assert!(matches!(x.reindex_fulltext("write_idempotency").await, Err(XtchdError::InvalidArgument(_))));
+            assert!(x.reindex_fulltext("no_such_table").await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_popular_images() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let mut img_ids = Vec::new();
+            for i in 0..3 {
+                let pair = xrows::ImagePair{src_full: format!("full{}", i), src_thmb: format!("thmb{}", i), alt: format!("Popular test {}", i), url: None, archive: None};
+                img_ids.push(x.add_image_immutable(pair).await.unwrap());
+            }
+            let (art, _) = x.add_article_title(0, None, "Popular images test", None).await.unwrap();
+            let mut page_ids = Vec::new();
+            for img_id in [img_ids[0], img_ids[1], img_ids[1]] {
+                let (page, _) = x.add_article_page(art.a_id_immut, None, vec!["A screenshot".to_string()], xrows::PageSrc::WpTxYt(img_id)).await.unwrap();
+                page_ids.push(page.p_id_immut);
+            }
+            // a revision replaces the page it supersedes rather than adding a reference 
+            x.revise_article_page(art.a_id_immut, page_ids[0], vec!["A revised screenshot".to_string()], xrows::PageSrc::WpTxYt(img_ids[0])).await.unwrap();
+            let popular: Vec<(i32, i64)> = x.popular_images(10).await.unwrap().iter().map(|(thumb, pages)| (thumb.img_id, *pages)).collect();
+            assert_eq!(popular, vec![(img_ids[1], 2), (img_ids[0], 1)]);
+            assert_eq!(x.popular_images(1).await.unwrap()[0].0.src_thmb, "thmb1");
+        });
+    }
+
+    #[test]
+    fn test_images_by_archive() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let mut img_ids = Vec::new();
+            for (i, archive) in ["83cXk", "83cXk", "Zz9Qa"].iter().enumerate() {
+                let pair = xrows::ImagePair{src_full: format!("full{}", i), src_thmb: format!("thmb{}", i), alt: format!("Archive test {}", i), 
+                    url: None, archive: Some(archive.to_string())};
+                img_ids.push(x.add_image_immutable(pair).await.unwrap());
+            }
+            let same = x.images_by_archive("83cXk").await.unwrap();
+            assert_eq!(same.iter().map(|thumb| thumb.img_id).collect::<Vec<i32>>(), vec![img_ids[0], img_ids[1]]);
+            assert_eq!(same[1].src_thmb, "thmb1");
+            assert!(x.images_by_archive("nokey").await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_pool_config_prefixed() {
+        // use a prefix unique to this test so other tests reading the environment are unaffected
+        let prefix = "XTCHD_PREFIX_TEST_";
+        for (name, val) in [("PSQL_HOST", "10.0.0.7"), ("PSQL_PORT", "6543"), ("PSQL_PW", "hunter2"), 
+            ("PSQL_USER", "replica_reader"), ("PSQL_DB", "xtchd_ro"), ("PSQL_SSLMODE", "verify-full"), ("PSQL_STATEMENT_TIMEOUT_MS", "5000"),
+            ("PSQL_POOL_SIZE", "64")] {
+            std::env::set_var(format!("{}{}", prefix, name), val);
+        }
+        let config = PoolConfig::from_env_prefixed(prefix);
+        assert_eq!(config.host, "10.0.0.7");
+        assert_eq!(config.port, 6543);
+        assert_eq!(config.password, Some("hunter2".to_string()));
+        assert_eq!(config.user, "replica_reader");
+        assert_eq!(config.dbname, "xtchd_ro");
+        assert_eq!(config.ssl_mode, SslMode::Require);
+        assert_eq!(config.statement_timeout, std::time::Duration::from_secs(5));
+        assert_eq!(config.max_size, Some(64));
+        assert_eq!(config.deadpool_config().options, Some("-c TimeZone=UTC -c statement_timeout=5000".to_string()));
+        // an unused prefix falls back to the defaults 
+        let defaults = PoolConfig::from_env_prefixed("XTCHD_PREFIX_UNSET_");
+        assert_eq!((defaults.host.as_str(), defaults.port, defaults.password), ("127.0.0.1", 5432, None));
+        assert_eq!(defaults.ssl_mode, SslMode::Disable);
+        assert_eq!(defaults.statement_timeout, DEFAULT_STATEMENT_TIMEOUT);
+        assert_eq!(defaults.max_size, None);
+        assert!(defaults.deadpool_config().pool.is_none());
+    }
+
+    #[test]
+    fn test_statement_timeout() {
+        // a statement running longer than the statement_timeout is cancelled by Postgres
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let config = PoolConfig{statement_timeout: std::time::Duration::from_millis(100), ..PoolConfig::from_env_prefixed("")};
+            let pool = Pool::from_config(config).await;
             let x = pool.get().await.unwrap();
-            let au = x.author_detail(0).await.unwrap();
-            assert_eq!(au.author.content.name, "Xtchd Admins".to_string());
+            x.c.query("SELECT pg_sleep(0.01)", &[]).await.unwrap();
+            let slow = x.c.query("SELECT pg_sleep(1)", &[]).await.unwrap_err();
+            assert!(matches!(XtchdError::from(slow), XtchdError::Timeout));
+        });
+    }
+
+    #[test]
+    fn test_article_detail_cited() {
+        // one page cites a prior article, the other a screenshot: only the first has the citation resolved 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (prior, prior_hcl) = x.add_article_title(0, Some(draft_id(0).as_str()), "Cited article", None).await.unwrap();
+            let (art, _) = x.add_article_title(0, Some(draft_id(1).as_str()), "Citing article", None).await.unwrap();
+            let pair = xrows::ImagePair{src_full: "full".to_string(), src_thmb: "thmb".to_string(), alt: "Screenshot".to_string(), url: None, archive: None};
+            let img_id = x.add_image_immutable(pair).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(0).as_str()), vec!["Cites".to_string()], xrows::PageSrc::Xtchd(prior.a_id_immut)).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(1).as_str()), vec!["Screenshot".to_string()], xrows::PageSrc::WpTxYt(img_id)).await.unwrap();
+            let detail = x.article_detail(art.a_id_immut, VerifyMode::Recompute).await.unwrap();
+            let cited = detail.pages[0].cited.as_ref().unwrap();
+            assert_eq!((cited.id, cited.name.as_str()), (prior.a_id_immut, "Cited article"));
+            assert_eq!(detail.pages[0].cited_sha256, Some(prior_hcl.new_sha256()));
+            assert!(detail.pages[1].cited.is_none());
+            assert!(detail.pages[1].cited_sha256.is_none());
+        });
+    }
+
+    #[test]
+    fn test_merkle_proof() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Merkle test 1").await.unwrap();
+            let (author, hcl) = x.add_author("Merkle test 2").await.unwrap();
+            let root = x.merkle_root("authors").await.unwrap();
+            let proof = x.merkle_proof("authors", author.auth_id).await.unwrap();
+            assert!(integrity::verify_merkle_proof(&hcl.new_sha256(), &proof, &root));
+            assert!(x.merkle_proof("authors", 99).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_autocomplete_special_characters() {
+        // tsquery operators in user input must not cause an error (see prefix_tsquery in public.sql)
+        use pachydurable::autocomplete::AutoComp;
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("AT&T Research").await.unwrap();
+            x.add_author("C++ Guild").await.unwrap();
+            for (input, expected) in [("AT&T", Some("AT&T Research")), ("C++", Some("C++ Guild")), ("foo | bar", None), ("(unbalanced", None), ("", None)] {
+                let rows = x.c.query(xrows::Author::query_autocomp(), &[&input, &input]).await.unwrap();
+                let names: Vec<String> = rows.iter().map(|row| row.get(1)).collect();
+                if let Some(name) = expected {
+                    assert_eq!(names, vec![name.to_string()]);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_chain_heads() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let before = x.chain_heads().await.unwrap();
+            let (author, hcl) = x.add_author("Chain head test").await.unwrap();
+            let after = x.chain_heads().await.unwrap();
+            assert_eq!(after["authors"], (author.auth_id, hcl.new_sha256()));
+            assert_ne!(before["authors"], after["authors"]);
+            assert_eq!(before["youtube_channels"], after["youtube_channels"]);
+            assert!(!after.contains_key("tombstones"));
+        });
+    }
+
+    #[test]
+    fn test_chain_fingerprint() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let before = x.chain_fingerprint("authors").await.unwrap();
+            assert_eq!(x.chain_fingerprint("authors").await.unwrap(), before);
+            let (_, hcl) = x.add_author("Fingerprint test").await.unwrap();
+            let after = x.chain_fingerprint("authors").await.unwrap();
+            assert_ne!(after, before);
+            assert_eq!(after, hcl.new_sha256());
+            assert_eq!(x.chain_fingerprint("authors").await.unwrap(), after);
+            assert_eq!(x.chain_fingerprint("tombstones").await.unwrap(), GENESIS_SHA256);
+            assert!(matches!(x.chain_fingerprint("pg_authid").await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_export_pages() {
+        // pages of each source round trip through the pages_immut entry in CHAIN_TABLES and still verify
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(0).as_str()), "Export pages test", None).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(0).as_str()), vec!["Opinion".to_string()], xrows::PageSrc::Author("splash.png".to_string())).await.unwrap();
+            x.add_article_page(art.a_id_immut, Some(draft_id(1).as_str()), vec!["Cites".to_string()], xrows::PageSrc::Xtchd(art.a_id_immut)).await.unwrap();
+            let chain = x.export_chain::<xrows::ArticlePage>("pages_immut").await.unwrap();
+            assert_eq!(chain[1].content.source, xrows::PageSrc::Xtchd(art.a_id_immut));
+            assert!(crate::integrity::verify_bundle(&chain).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_duplicate_video_conflict() {
+        // a vid_pk already written is a Conflict, and the rolled back write leaves no gap in the chain 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let uploaded = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+            let (chan, _) = x.add_youtube_channel("c/DuplicateTest", "Duplicate test").await.unwrap();
+            let (first, _) = x.add_youtube_video(chan.chan_id, "duplicate01", "First", &uploaded).await.unwrap();
+            let again = x.add_youtube_video(chan.chan_id, "duplicate01", "Again", &uploaded).await;
+            assert!(matches!(again, Err(XtchdError::Xrow(xrows::XrowError::Conflict{constraint})) if constraint == "youtube_videos_pkey"));
+            let (next, _) = x.add_youtube_video(chan.chan_id, "duplicate02", "Next", &uploaded).await.unwrap();
+            assert_eq!(next.vid_id, first.vid_id + 1);
+        });
+    }
+
+    #[test]
+    fn test_import_channel_videos() {
+        // import 5 videos, 2 of which already exist: only the other 3 are chained 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let uploaded = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+            let (chan, _) = ts.x.add_youtube_channel("c/ImportTest", "Import test").await.unwrap();
+            for vid_pk in ["import00001", "import00003"] {
+                ts.x.add_youtube_video(chan.chan_id, vid_pk, vid_pk, &uploaded).await.unwrap();
+            }
+            let videos = (0..5).map(|i| xrows::VideoImport{vid_pk: format!("import0000{}", i), title: format!("Import {}", i), date_uploaded: uploaded}).collect();
+            let summary = ts.x.import_channel_videos(chan.chan_id, videos, DEFAULT_IMPORT_BATCH).await.unwrap();
+            assert_eq!(summary.skipped, vec!["import00001", "import00003"]);
+            let imported: Vec<&str> = summary.imported.iter().map(|video| video.vid_pk.as_str()).collect();
+            assert_eq!(imported, vec!["import00000", "import00002", "import00004"]);
+            let chain = ts.x.export_chain::<xrows::YoutubeVideo>("youtube_videos").await.unwrap();
+            assert_eq!(chain.len(), 5);
+            assert!(crate::integrity::verify_bundle(&chain).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_import_channel_videos_batched() {
+        // 5 videos in batches of 2: the chain continues across each seam between INSERTs
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let uploaded = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+            let (chan, _) = ts.x.add_youtube_channel("c/BatchTest", "Batch test").await.unwrap();
+            ts.x.add_youtube_video(chan.chan_id, "batch000000", "Existing", &uploaded).await.unwrap();
+            let videos = || (0..6).map(|i| xrows::VideoImport{vid_pk: format!("batch00000{}", i), title: format!("Batch {}", i), date_uploaded: uploaded}).collect();
+            assert!(matches!(ts.x.import_channel_videos(chan.chan_id, videos(), 0).await, Err(XtchdError::InvalidArgument(_))));
+            assert!(matches!(ts.x.import_channel_videos(chan.chan_id, videos(), MAX_QUERY_PARAMS).await, Err(XtchdError::InvalidArgument(_))));
+            let summary = ts.x.import_channel_videos(chan.chan_id, videos(), 2).await.unwrap();
+            assert_eq!(summary.skipped, vec!["batch000000"]);
+            assert_eq!(summary.imported.len(), 5);
+            ts.x.verify_chain("youtube_videos").await.unwrap();
+            assert!(ts.x.audit_prior_links("youtube_videos").await.unwrap().is_empty());
+            let chain = ts.x.export_chain::<xrows::YoutubeVideo>("youtube_videos").await.unwrap();
+            let vid_pks: Vec<&str> = chain.iter().map(|video| video.content.vid_pk.as_str()).collect();
+            assert_eq!(vid_pks, vec!["batch000000", "batch000001", "batch000002", "batch000003", "batch000004", "batch000005"]);
+        });
+    }
+
+    #[test]
+    #[tracing_test::traced_test]
+    fn test_add_author_traced() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let (_, hcl) = ts.x.add_author("Traced author").await.unwrap();
+            assert!(logs_contain("add_author"));
+            assert!(logs_contain("etched"));
+            assert!(logs_contain(&hcl.new_sha256()));
+        });
+    }
+
+    #[test]
+    fn test_find_by_sha256() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, hcl) = x.add_author("Find by hash test").await.unwrap();
+            let found = x.find_by_sha256("authors", &hcl.new_sha256()).await.unwrap();
+            assert_eq!(found, Some((author.auth_id, "Author".to_string())));
+            let random = crate::integrity::sha256("not the hash of any row");
+            assert_eq!(x.find_by_sha256("authors", &random).await.unwrap(), None);
+        });
+    }
+
+    #[test]
+    fn test_is_buried() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (_, buried) = x.add_author("Buried author").await.unwrap();
+            let (_, head) = x.add_author("Head author").await.unwrap();
+            assert!(x.is_buried("authors", &buried.new_sha256()).await.unwrap());
+            assert!(!x.is_buried("authors", &head.new_sha256()).await.unwrap());
+            assert!(!x.is_buried("authors", &crate::integrity::sha256("not the hash of any row")).await.unwrap());
+            // a hash is only buried within its own table 
+            assert!(!x.is_buried("titles_immut", &buried.new_sha256()).await.unwrap());
+            assert!(x.is_buried("no_such_table", &head.new_sha256()).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_inline_image_hash_verifies() {
+        // an image written before hash_v (with the full image inline in its state string) still exports and verifies 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let pair = xrows::ImagePair{src_full: "data:image/png;base64,iVBORw0KGgo=".to_string(), src_thmb: "thmb".to_string(), 
+                alt: "Legacy".to_string(), url: None, archive: None};
+            let (tx, last_ref) = x.lock_tail(<xrows::ImmutableImage as ContentTable>::chain_table()).await.unwrap();
+            let ii = xrows::ImmutableImage{img_id: last_ref.next_id(), pair, hash_v: xrows::IMAGE_HASH_V_INLINE};
+            let hclink = HashChainLink::new(&last_ref.prior_sha256, &ii);
+            tx.execute("INSERT INTO images_immut (prior_id, img_id, src_full, src_thmb, alt, prior_sha256, write_timestamp, new_sha256, hash_v) 
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)", &[&last_ref.prior_id, &ii.img_id, &ii.pair.src_full, &ii.pair.src_thmb, &ii.pair.alt, 
+                &last_ref.prior_sha256, &hclink.write_timestamp, &hclink.new_sha256(), &ii.hash_v]).await.unwrap();
+            tx.commit().await.unwrap();
+            let pair = xrows::ImagePair{src_full: "data:image/png;base64,iVBORw0KGgo=".to_string(), src_thmb: "thmb".to_string(), 
+                alt: "Current".to_string(), url: None, archive: None};
+            let img_id = x.add_image_immutable(pair).await.unwrap();
+            let chain = x.export_chain::<xrows::ImmutableImage>("images_immut").await.unwrap();
+            assert_eq!(chain.iter().map(|row| row.content.hash_v).collect::<Vec<i16>>(), vec![xrows::IMAGE_HASH_V_INLINE, xrows::IMAGE_HASH_V]);
+            assert!(crate::integrity::verify_bundle(&chain).is_ok());
+            assert!(x.verify_row("images_immut", ii.img_id).await.unwrap().is_valid());
+            assert!(x.verify_row("images_immut", img_id).await.unwrap().is_valid());
+        });
+    }
+
+    #[test]
+    fn test_page_hash_escaped_paragraphs() {
+        // JSON escapes quotes, backslashes and newlines with a backslash: the page must still be written and verify 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, None, "Escaped page test", None).await.unwrap();
+            let paragraphs = vec!["He said \"hi\"".to_string(), "one line\nanother\tand a \\ backslash".to_string()];
+            let (page, _) = x.add_article_page(art.a_id_immut, None, paragraphs.clone(), xrows::PageSrc::Author(String::new())).await.unwrap();
+            assert_eq!(page.hash_v, xrows::PAGE_HASH_V);
+            assert!(x.verify_row("pages_immut", page.p_id_immut).await.unwrap().is_valid());
+            let read = x.article_pages_range(art.a_id_immut, 0, 1).await.unwrap();
+            assert_eq!(read[0].content.paragraphs, paragraphs);
+        });
+    }
+
+    #[cfg(feature = "compress-images")]
+    #[test]
+    fn test_compressed_image_round_trip() {
+        // the full image is stored as bytes, but reads back as the original data URI and still verifies 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            // long enough that Postgres would wrap the base64 if the newlines were not removed 
+            let src_full = format!("data:image/png;base64,{}", "iVBORw0KGgoAAAANSUhEUgAA".repeat(20));
+            let pair = xrows::ImagePair{src_full: src_full.clone(), src_thmb: "thmb".to_string(), alt: "Compressed".to_string(), url: None, archive: None};
+            let img_id = x.add_image_immutable(pair).await.unwrap();
+            let row = x.c.query_one("SELECT src_full IS NULL, full_bytes IS NOT NULL FROM images_immut WHERE img_id = $1", &[&img_id]).await.unwrap();
+            assert_eq!((row.get::<_, bool>(0), row.get::<_, bool>(1)), (true, true));
+            let chain = x.export_chain::<xrows::ImmutableImage>("images_immut").await.unwrap();
+            assert_eq!(chain[0].content.pair.src_full, src_full);
+            assert!(crate::integrity::verify_bundle(&chain).is_ok());
+        });
+    }
+
+    #[cfg(feature = "signatures")]
+    #[test]
+    fn test_sign_head() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+            let key = signing_key.verifying_key();
+            let (author, hcl) = x.add_author("Signed head").await.unwrap();
+            let signed = x.sign_head("authors", &signing_key).await.unwrap();
+            assert_eq!(signed.head_id, author.auth_id);
+            assert_eq!(signed.head_sha256, hcl.new_sha256());
+            assert!(signed.verify(&key));
+            assert!(x.verify_head_signature("authors", author.auth_id, &key).await.unwrap());
+            // the signature is bound to the hash, the id and the table: it does not verify another head 
+            for moved in [
+                views::HeadSignature{head_sha256: GENESIS_SHA256.to_string(), ..signed.clone()},
+                views::HeadSignature{head_id: 0, ..signed.clone()},
+                views::HeadSignature{table: "titles_immut".to_string(), ..signed.clone()},
+            ] {
+                assert!(!moved.verify(&key));
+            }
+            assert!(!x.verify_head_signature("authors", 0, &key).await.unwrap());
+            // a signature by another key is not accepted for the expected key, whatever its pubkey field says 
+            let other_key = ed25519_dalek::SigningKey::from_bytes(&[8u8; 32]);
+            let (other, _) = x.add_author("Signed by another key").await.unwrap();
+            let forged = x.sign_head("authors", &other_key).await.unwrap();
+            assert!(forged.verify(&other_key.verifying_key()));
+            assert!(!forged.verify(&key));
+            assert!(!views::HeadSignature{pubkey: key.to_bytes().to_vec(), ..forged.clone()}.verify(&key));
+            assert!(!x.verify_head_signature("authors", other.auth_id, &key).await.unwrap());
+            assert!(x.verify_head_signature("authors", other.auth_id, &other_key.verifying_key()).await.unwrap());
+            assert!(x.sign_head("no_such_table", &signing_key).await.is_err());
+            assert!(x.sign_head("tombstones", &signing_key).await.is_err());
+        });
+    }
+
+    #[cfg(feature = "signatures")]
+    #[test]
+    fn test_sign_head_tampered() {
+        // rewriting the signed row, or the stored signature, fails verification 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let signing_key = ed25519_dalek::SigningKey::from_bytes(&[7u8; 32]);
+            let key = signing_key.verifying_key();
+            let (author, _) = x.add_author("Tampered signed head").await.unwrap();
+            x.sign_head("authors", &signing_key).await.unwrap();
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256; ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later").await.unwrap();
+            x.c.execute("UPDATE authors SET name = 'Rewritten', new_sha256 = $2 WHERE auth_id = $1", &[&author.auth_id, &GENESIS_SHA256]).await.unwrap();
+            assert!(!x.verify_head_signature("authors", author.auth_id, &key).await.unwrap());
+
+            let (channel, _) = x.add_youtube_channel("https://www.youtube.com/@signed", "Signed channel").await.unwrap();
+            x.sign_head("youtube_channels", &signing_key).await.unwrap();
+            assert!(x.verify_head_signature("youtube_channels", channel.chan_id, &key).await.unwrap());
+            x.c.execute("UPDATE chain_signatures SET signature = SET_BYTE(signature, 0, GET_BYTE(signature, 0) # 1) WHERE table_name = 'youtube_channels'", &[]).await.unwrap();
+            assert!(!x.verify_head_signature("youtube_channels", channel.chan_id, &key).await.unwrap());
+        });
+    }
+
+    #[cfg(feature = "tsa")]
+    #[test]
+    fn test_timestamp_head() {
+        // a mocked TSA grants a token for the head, which stops verifying once the head is rewritten 
+        use crate::test_harness::{granted_tsa_response, mock_http_once};
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, hcl) = x.add_author("Timestamped head").await.unwrap();
+            let digest = tsa::digest_from_hex(&hcl.new_sha256()).unwrap();
+            let (tsa_url, received) = mock_http_once(granted_tsa_response(&digest)).await;
+            let token = x.timestamp_head("authors", &tsa_url).await.unwrap();
+            assert_eq!((token.head_id, token.head_sha256.as_str()), (author.auth_id, hcl.new_sha256().as_str()));
+            assert!(token.mentions_head());
+            assert!(tsa::token_mentions_digest(&received.await.unwrap(), &digest));
+            assert!(x.tsa_token_mentions_head("authors", author.auth_id).await.unwrap());
+            assert!(!x.tsa_token_mentions_head("authors", 0).await.unwrap());
+            // a TSA granting a token for some other hash, or rejecting the request, stores nothing 
+            let (tsa_url, _) = mock_http_once(granted_tsa_response(&[0u8; 32])).await;
+            assert!(matches!(x.timestamp_head("authors", &tsa_url).await, Err(XtchdError::Tsa(tsa::TsaError::Malformed(_)))));
+            let rejection = tsa::der(tsa::SEQUENCE, &tsa::der(tsa::SEQUENCE, &tsa::der(tsa::INTEGER, &[2])));
+            let (tsa_url, _) = mock_http_once(rejection).await;
+            assert!(matches!(x.timestamp_head("authors", &tsa_url).await, Err(XtchdError::Tsa(tsa::TsaError::Rejected{status: 2}))));
+            let stored: i64 = x.c.query_one("SELECT COUNT(*) FROM tsa_tokens", &[]).await.unwrap().get(0);
+            assert_eq!(stored, 1);
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256; ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later").await.unwrap();
+            x.c.execute("UPDATE authors SET name = 'Rewritten', new_sha256 = $2 WHERE auth_id = $1", &[&author.auth_id, &GENESIS_SHA256]).await.unwrap();
+            assert!(!x.tsa_token_mentions_head("authors", author.auth_id).await.unwrap());
+        });
+    }
+
+    #[cfg(feature = "image-audit")]
+    #[test]
+    fn test_similar_images() {
+        // a resized copy of an image is similar to it, the same image reversed is not 
+        use crate::test_harness::gradient_png;
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let pair = |src_full: String| xrows::ImagePair{src_full, src_thmb: gradient_png(64, 48, false), alt: "Similar".to_string(), url: None, archive: None};
+            let original = x.add_image_immutable(pair(gradient_png(640, 480, false))).await.unwrap();
+            let resized = x.add_image_immutable(pair(gradient_png(600, 450, false))).await.unwrap();
+            x.add_image_immutable(pair(gradient_png(640, 480, true))).await.unwrap();
+            let undecodable = x.add_image_immutable(pair("data:image/png;base64,QUJD".to_string())).await.unwrap();
+            let similar: Vec<i32> = x.similar_images(original, 10).await.unwrap().iter().map(|thumbnail| thumbnail.img_id).collect();
+            assert_eq!(similar, vec![resized]);
+            assert!(x.similar_images(undecodable, 64).await.unwrap().is_empty());
+            // images written without a phash are found once it is backfilled, and an undecodable image is skipped 
+            x.c.execute("DELETE FROM image_phashes", &[]).await.unwrap();
+            assert!(x.similar_images(original, 10).await.unwrap().is_empty());
+            assert_eq!(x.backfill_phashes().await.unwrap(), 3);
+            assert_eq!(x.backfill_phashes().await.unwrap(), 0);
+            let similar: Vec<i32> = x.similar_images(original, 10).await.unwrap().iter().map(|thumbnail| thumbnail.img_id).collect();
+            assert_eq!(similar, vec![resized]);
+        });
+    }
+
+    #[cfg(feature = "image-audit")]
+    #[test]
+    fn test_audit_thumbnail_fidelity() {
+        use crate::test_harness::gradient_png;
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let pair = |src_thmb: String| xrows::ImagePair{src_full: gradient_png(640, 480, false), src_thmb, alt: "Fidelity".to_string(), url: None, archive: None};
+            let matching = x.add_image_immutable(pair(gradient_png(64, 48, false))).await.unwrap();
+            let mismatched = x.add_image_immutable(pair(gradient_png(64, 48, true))).await.unwrap();
+            assert!(x.audit_thumbnail_fidelity(matching).await.unwrap());
+            assert!(!x.audit_thumbnail_fidelity(mismatched).await.unwrap());
+            assert!(x.audit_thumbnail_fidelity(999).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_audit_author_articles() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_article_title(0, Some(draft_id(0).as_str()), "Audit test valid", None).await.unwrap();
+            assert!(x.audit_author_articles().await.unwrap().is_empty());
+            x.c.batch_execute("ALTER TABLE titles_immut DROP CONSTRAINT art_auth").await.unwrap();
+            let (orphan, _) = x.add_article_title(999, Some(draft_id(1).as_str()), "Audit test orphan", None).await.unwrap();
+            let expected = views::ReferentialAnomaly{table: "titles_immut".to_string(), id: orphan.a_id_immut, column: "auth_id".to_string(), missing_id: 999};
+            assert_eq!(x.audit_author_articles().await.unwrap(), vec![expected]);
+        });
+    }
+
+    #[test]
+    fn test_audit_prior_links() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Prior link test 1").await.unwrap();
+            x.add_author("Prior link test 2").await.unwrap();
+            assert!(x.audit_prior_links("authors").await.unwrap().is_empty());
+            // a row which hashes correctly, but whose prior_sha256 is that of auth_id 1 rather than its prior_id 2
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_no_delete;
+                INSERT INTO authors (prior_id, auth_id, name, prior_sha256, write_timestamp, new_sha256)
+                SELECT 2, 3, 'Wrong prior', new_sha256, CURRENT_TIMESTAMP, ENCODE(SHA256(CONCAT('auth_id=3 name=Wrong prior write_timestamp=', 
+                    TO_CHAR(CURRENT_TIMESTAMP, 'YYYY.MM.DD HH24:MI:SS'), ' prior_sha256=', new_sha256)::BYTEA), 'hex')
+                FROM authors WHERE auth_id = 1;").await.unwrap();
+            let anomalies = x.audit_prior_links("authors").await.unwrap();
+            assert_eq!(anomalies.len(), 1);
+            let anomaly = &anomalies[0];
+            assert_eq!((anomaly.id, anomaly.prior_id), (3, Some(2)));
+            assert_eq!(anomaly.prior_sha256, x.find_head_sha256(chain_table("authors").unwrap(), 1).await.unwrap());
+            assert_eq!(anomaly.expected, Some(x.find_head_sha256(chain_table("authors").unwrap(), 2).await.unwrap()));
+            assert!(x.verify_row("authors", 3).await.unwrap().hash_matches);
+            assert!(matches!(x.audit_prior_links("nope").await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_conflict() {
+        // a duplicate name is a typed conflict naming the constraint, rather than an opaque Postgres error
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Conflict test").await.unwrap();
+            let conflict = x.add_author("Conflict test").await;
+            assert!(matches!(conflict, Err(XtchdError::Xrow(xrows::XrowError::Conflict{ref constraint})) if constraint == AUTHOR_CURRENT_NAME));
+            x.add_youtube_channel("c/ConflictTest", "Conflict test").await.unwrap();
+            let conflict = x.add_youtube_channel("c/ConflictTest", "Conflict test 2").await;
+            assert!(matches!(conflict, Err(XtchdError::Xrow(xrows::XrowError::Conflict{..}))));
+            // other failures are not conflicts 
+            let missing_author = x.add_article_title(999, None, "Conflict test article", None).await;
+            assert!(matches!(missing_author, Err(XtchdError::Pachy(_))));
+        });
+    }
+
+    #[test]
+    fn test_statement_cache() {
+        // the statements for add_author are prepared on the first call and reused after that 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Statement cache test 1").await.unwrap();
+            let prepared = x.c.statement_cache.size();
+            assert!(prepared >= 2);
+            x.add_author("Statement cache test 2").await.unwrap();
+            x.add_author("Statement cache test 3").await.unwrap();
+            assert_eq!(x.c.statement_cache.size(), prepared);
+        });
+    }
+
+    #[test]
+    fn test_author_history() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("History test").await.unwrap();
+            x.rename_author(author.auth_id, "History test renamed").await.unwrap();
+            x.rename_author(author.auth_id, "History test renamed again").await.unwrap();
+            let history = x.author_history(author.auth_id).await.unwrap();
+            assert_eq!(history.author.content.name, "History test");
+            assert!(history.author.verify().is_ok());
+            let names: Vec<&str> = history.renames.iter().map(|rename| rename.content.name.as_str()).collect();
+            assert_eq!(names, vec!["History test renamed", "History test renamed again"]);
+            assert!(history.renames.iter().all(|rename| rename.verify().is_ok()));
+            assert!(x.verify_chain("author_names").await.is_ok());
+            assert!(matches!(x.author_history(99).await, Err(XtchdError::NotFound(_))));
+            // every read resolves the current name
+            assert_eq!(x.author_detail(author.auth_id, VerifyMode::Recompute).await.unwrap().name, "History test renamed again");
+            assert_eq!(x.authors_by_ids(&[author.auth_id]).await.unwrap()[&author.auth_id].name, "History test renamed again");
+            assert_eq!(x.find_author_by_name("History test renamed again").await.unwrap(), Some(author.auth_id));
+            assert_eq!(x.find_author_by_name("History test").await.unwrap(), None);
+            assert!(x.all_authors().await.unwrap().iter().any(|author| author.name == "History test renamed again"));
+            let results = xrows::Author::exec_autocomp(&x.c, "History test renamed again").await.unwrap();
+            assert!(results.iter().any(|www| www.pk == author.auth_id));
+            // names are unique across current names
+            let (other, _) = x.add_author("History test other").await.unwrap();
+            let taken = x.rename_author(other.auth_id, "History test renamed again").await;
+            assert!(matches!(taken, Err(XtchdError::Xrow(xrows::XrowError::Conflict{ref constraint})) if constraint == AUTHOR_CURRENT_NAME));
+            let taken = x.add_author("History test renamed again").await;
+            assert!(matches!(taken, Err(XtchdError::Xrow(xrows::XrowError::Conflict{ref constraint})) if constraint == AUTHOR_CURRENT_NAME));
+            // the first name is still held by the authors row 
+            let taken = x.add_author("History test").await;
+            assert!(matches!(taken, Err(XtchdError::Xrow(xrows::XrowError::Conflict{ref constraint})) if constraint == "authors_name_key"));
+            assert!(matches!(x.rename_author(99, "History test missing").await, Err(XtchdError::NotFound(_))));
+        });
+    }
+
+    /// Fails every lookup, as if Redis were down 
+    struct FailingCache;
+
+    impl AutoCompCache for FailingCache {
+        async fn autocomp<U, T: CachedAutoComp<U>>(&mut self, _c: &ClientNoTLS, _phrase: &str) -> Result<Vec<WhoWhatWhere<U>>, GenericError> {
+            Err("connection refused".into())
+        }
+    }
+
+    #[test]
+    fn test_autocomplete_fallback() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("Fallback Author").await.unwrap();
+            let results = x.autocomplete_cached_or_db::<i32, xrows::Author, _>(&mut FailingCache, "Fallback").await.unwrap();
+            assert!(results.iter().any(|www| www.pk == author.auth_id));
+        });
+    }
+
+    #[test]
+    fn test_articles_citing() {
+        // two articles cite the target (one of them twice): each is listed once 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (target, _) = x.add_article_title(0, Some(draft_id(0).as_str()), "Citing test target", None).await.unwrap();
+            let mut citing = Vec::new();
+            let mut pages = Vec::new();
+            for i in 1..3 {
+                let (art, _) = x.add_article_title(0, Some(draft_id(i).as_str()), &format!("Citing test {}", i), None).await.unwrap();
+                for j in 0..i {
+                    let (page, _) = x.add_article_page(art.a_id_immut, Some(draft_id(10 * i + j).as_str()), vec!["Cites".to_string()], xrows::PageSrc::Xtchd(target.a_id_immut)).await.unwrap();
+                    pages.push(page);
+                }
+                citing.push(art.a_id_immut);
+            }
+            let listed: Vec<i32> = x.articles_citing(target.a_id_immut).await.unwrap().iter().map(|name_id| name_id.id).collect();
+            assert_eq!(listed, citing);
+            assert!(x.articles_citing(citing[0]).await.unwrap().is_empty());
+            // an article whose only citing page is revised to cite something else no longer cites the target 
+            x.revise_article_page(citing[0], pages[0].p_id_immut, vec!["No longer cites".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let listed: Vec<i32> = x.articles_citing(target.a_id_immut).await.unwrap().iter().map(|name_id| name_id.id).collect();
+            assert_eq!(listed, vec![citing[1]]);
+        });
+    }
+
+    #[test]
+    fn test_author_article_titles() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("Titles test author").await.unwrap();
+            let mut a_ids = Vec::new();
+            for i in 0..3 {
+                let (art, _) = x.add_article_title(author.auth_id, Some(draft_id(i).as_str()), &format!("Titles test {}", i), None).await.unwrap();
+                a_ids.push(art.a_id_immut);
+                x.add_article_title(0, Some(draft_id(10 + i).as_str()), &format!("Titles test other {}", i), None).await.unwrap();
+            }
+            let titles = x.author_article_titles(author.auth_id).await.unwrap();
+            assert_eq!(titles.iter().map(|title| title.content.a_id_immut).collect::<Vec<i32>>(), a_ids);
+            assert!(titles.iter().all(|title| title.verify().is_ok() && title.content.auth_id == author.auth_id));
+            assert!(x.author_article_titles(999).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_author_latest_article() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (prolific, _) = x.add_author("Latest test prolific").await.unwrap();
+            let (quiet, _) = x.add_author("Latest test quiet").await.unwrap();
+            x.add_article_title(prolific.auth_id, Some(draft_id(0).as_str()), "Latest test first", None).await.unwrap();
+            let (latest, _) = x.add_article_title(prolific.auth_id, Some(draft_id(1).as_str()), "Latest test second", None).await.unwrap();
+            x.add_article_title(0, Some(draft_id(2).as_str()), "Latest test by someone else", None).await.unwrap();
+            let found = x.author_latest_article(prolific.auth_id).await.unwrap().unwrap();
+            assert_eq!((found.id, found.name.as_str()), (latest.a_id_immut, "Latest test second"));
+            assert!(x.author_latest_article(quiet.auth_id).await.unwrap().is_none());
+        });
+    }
+
+    /// A content type defined outside xtchd, for testing ContentTable registration 
+    #[derive(Serialize, Deserialize)]
+    struct Note {
+        note_id: i32,
+        text: String,
+    }
+
+    impl Xtchable for Note {
+        fn state_string(&self) -> String {
+            format!("note_id={} text={}", self.note_id, self.text)
+        }
+        fn dtype() -> &'static str {
+            "Note"
+        }
+    }
+
+    static NOTES: ChainTable = ChainTable{name: "notes", id_column: "note_id", dtype: "Note", 
+        content_json: "JSON_BUILD_OBJECT('note_id', note_id, 'text', text)"};
+
+    impl ContentTable for Note {
+        fn chain_table() -> &'static ChainTable {
+            &NOTES
+        }
+    }
+
+    #[test]
+    fn test_verify_all_registered() {
+        // a registered content type is verified along with the built-in tables, without editing verify_chain()
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            ts.x.c.batch_execute("CREATE TABLE notes (prior_id INTEGER UNIQUE, note_id INTEGER NOT NULL PRIMARY KEY, text VARCHAR NOT NULL, 
+                prior_sha256 CHAR(64) NOT NULL, write_timestamp TIMESTAMPTZ NOT NULL, new_sha256 CHAR(64) NOT NULL)").await.unwrap();
+            let mut prior_sha256 = GENESIS_SHA256.to_string();
+            for note_id in 0..2 {
+                let note = Note{note_id, text: format!("Note {}", note_id)};
+                let hcl = HashChainLink::new(&prior_sha256, &note);
+                let prior_id = if note_id == 0 { None } else { Some(note_id - 1) };
+                ts.x.c.execute("INSERT INTO notes (prior_id, note_id, text, prior_sha256, write_timestamp, new_sha256) VALUES ($1, $2, $3, $4, $5, $6)", 
+                    &[&prior_id, &note.note_id, &note.text, &prior_sha256, &hcl.write_timestamp, &hcl.new_sha256()]).await.unwrap();
+                prior_sha256 = hcl.new_sha256();
+            }
+            let names = |results: &[(&'static str, Result<(), XtchdError>)]| results.iter().map(|(name, _)| *name).collect::<Vec<&str>>();
+            assert!(!names(&ts.x.verify_all().await).contains(&"notes"));
+            assert!(matches!(ts.x.verify_chain("notes").await, Err(XtchdError::InvalidArgument(_))));
+
+            ts.x.register_content_table::<Note>();
+            let results = ts.x.verify_all().await;
+            assert_eq!(names(&results).len(), CHAIN_TABLES.len() + 1);
+            assert_eq!(names(&results).last(), Some(&"notes"));
+            assert!(results.iter().all(|(_, result)| result.is_ok()));
+            assert!(ts.x.verify_row("notes", 1).await.unwrap().is_valid());
+            assert_eq!(ts.x.chain_heads().await.unwrap()["notes"], (1, prior_sha256));
+            // registration is per Xtchr (or per Pool, with Pool::with_content_table)
+            assert!(ts.connect().await.verify_chain("notes").await.is_err());
+
+            ts.x.c.execute("UPDATE notes SET text = 'Tampered' WHERE note_id = 1", &[]).await.unwrap();
+            let results = ts.x.verify_all().await;
+            let notes = results.iter().find(|(name, _)| *name == "notes").unwrap();
+            assert!(matches!(notes.1, Err(XtchdError::Integrity(_))));
+            assert!(results.iter().filter(|(name, _)| *name != "notes").all(|(_, result)| result.is_ok()));
+        });
+    }
+
+    #[test]
+    fn test_integrity_status() {
+        // tamper with a row before the head: only the deep check notices 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.add_author("Status test 1").await.unwrap();
+            x.add_author("Status test 2").await.unwrap();
+            let authors = |status: &views::IntegrityStatus| status.per_table.iter().find(|table| table.name == "authors").unwrap().verified;
+            assert!(authors(&x.integrity_status(true).await.unwrap()));
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256;
+                ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later;
+                UPDATE authors SET name = 'Tampered' WHERE auth_id = 1;").await.unwrap();
+            let shallow = x.integrity_status(false).await.unwrap();
+            let deep = x.integrity_status(true).await.unwrap();
+            assert!(authors(&shallow));
+            assert!(!authors(&deep));
+            // the cheap check counts nothing: rows follows from the head's id 
+            let shallow_rows = shallow.per_table.iter().find(|table| table.name == "authors").unwrap().rows;
+            assert_eq!(shallow_rows, 3);
+            let json = serde_json::to_value(&deep).unwrap();
+            let authors_json = json["per_table"].as_array().unwrap().iter().find(|table| table["name"] == "authors").unwrap();
+            assert_eq!(authors_json["verified"], serde_json::Value::Bool(false));
+            assert_eq!(authors_json["rows"], serde_json::json!(3));
+        });
+    }
+
+    #[test]
+    fn test_append() {
+        // the generic path and the bespoke SQL CHECK constraint agree on the hash, and the chain continues across both 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (first, hcl1) = x.append("authors", xrows::Author{auth_id: -1, name: "Appended author".to_string()}).await.unwrap();
+            // the table must be registered and hold the type appended 
+            let wrong = x.append("titles_immut", xrows::Author{auth_id: -1, name: "Misfiled author".to_string()}).await;
+            assert!(matches!(wrong, Err(XtchdError::InvalidArgument(_))));
+            let unknown = x.append("authors; DROP TABLE authors", xrows::Author{auth_id: -1, name: "Injected author".to_string()}).await;
+            assert!(matches!(unknown, Err(XtchdError::InvalidArgument(_))));
+            let (second, hcl2) = x.add_author("Added author").await.unwrap();
+            assert_eq!(second.auth_id, first.auth_id + 1);
+            let row = x.c.query_one("SELECT new_sha256, prior_sha256 FROM authors WHERE auth_id = $1", &[&second.auth_id]).await.unwrap();
+            let (new_sha256, prior_sha256): (String, String) = (row.get(0), row.get(1));
+            assert_eq!(new_sha256, hcl2.new_sha256());
+            assert_eq!(prior_sha256, hcl1.new_sha256());
+            let bespoke = HashChainLink::from_timestamp(&prior_sha256, hcl2.write_timestamp, &second);
+            assert_eq!(bespoke.new_sha256(), new_sha256);
+            assert!(x.verify_row("authors", first.auth_id).await.unwrap().is_valid());
+            assert!(x.verify_row("authors", second.auth_id).await.unwrap().is_valid());
+        });
+    }
+
+    #[test]
+    fn test_constraint_parity() {
+        // every Xtchable type hashes the same in Rust as in its table's CHECK constraint, 
+        // including content with delimiters, quotes, unicode, and optional fields left empty  
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let x = &ts.x;
+            let prior = "ab".repeat(32);
+            let now = integrity::now();
+            let earlier = Utc.with_ymd_and_hms(2021, 2, 3, 4, 5, 6).unwrap();
+            for write_timestamp in [now, earlier] {
+                x.assert_constraint_parity(&xrows::Author{auth_id: 7, name: "Zoë name=Smith".to_string()}, &prior, write_timestamp).await;
+                x.assert_constraint_parity(&xrows::ArticleTitle{a_id_draft: draft_id(1), a_id_immut: 3, auth_id: 7, 
+                    title: "A \"quoted\" title".to_string()}, &prior, write_timestamp).await;
+                x.assert_constraint_parity(&xrows::ArticlePara{apara_id: 4, art_id: 3, md: "Some *markdown*\nacross lines".to_string()}, &prior, write_timestamp).await;
+                for (source, supersedes) in [(xrows::PageSrc::Author("wiki/splash.jpg".to_string()), None), 
+                        (xrows::PageSrc::Xtchd(2), Some(5)), (xrows::PageSrc::WpTxYt(9), None)] {
+                    // pages written before hash_v (PAGE_HASH_V_JOINED) still verify against the constraint 
+                    for hash_v in [xrows::PAGE_HASH_V_JOINED, xrows::PAGE_HASH_V] {
+                        let page = xrows::ArticlePage{p_id_draft: draft_id(2), p_id_immut: 6, a_id_immut: 3, 
+                            paragraphs: vec!["a | b".to_string(), "\"c\"".to_string()], source: source.clone(), supersedes, hash_v};
+                        x.assert_constraint_parity(&page, &prior, write_timestamp).await;
+                    }
+                }
+                x.assert_constraint_parity(&xrows::YoutubeChannel{chan_id: 1, name: "A channel".to_string(), 
+                    url: "https://www.youtube.com/@channel".to_string()}, &prior, write_timestamp).await;
+                x.assert_constraint_parity(&xrows::YoutubeVideo{chan_id: 1, vid_id: 2, vid_pk: "dQw4w9WgXcQ".to_string(), 
+                    title: "A video".to_string(), date_uploaded: NaiveDate::from_ymd_opt(2020, 1, 2).unwrap()}, &prior, write_timestamp).await;
+                // images written before hash_v (IMAGE_HASH_V_INLINE) still verify against the constraint 
+                for hash_v in [xrows::IMAGE_HASH_V_INLINE, xrows::IMAGE_HASH_V] {
+                    for (url, archive) in [(None, None), (Some("https://example.com".to_string()), Some("83cXk".to_string()))] {
+                        let pair = xrows::ImagePair{src_full: "data:image/png;base64,iVBORw0KGgo=".to_string(), 
+                            src_thmb: "data:image/png;base64,iVBO=".to_string(), alt: "alt text".to_string(), url, archive};
+                        x.assert_constraint_parity(&xrows::ImmutableImage{img_id: 8, pair, hash_v}, &prior, write_timestamp).await;
+                    }
+                }
+                x.assert_constraint_parity(&xrows::Tombstone{tomb_id: 1, target_table: "titles_immut".to_string(), 
+                    target_id: 3, reason: "A takedown".to_string()}, &prior, write_timestamp).await;
+            }
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "rejected the Rust hash")]
+    fn test_constraint_parity_drift() {
+        // a constraint which has drifted from state_string() fails the parity check
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let ts = TestSchema::new().await;
+            let x = &ts.x;
+            x.c.batch_execute("ALTER TABLE tombstones DROP CONSTRAINT tomb_verify_sha256;
+                ALTER TABLE tombstones ADD CONSTRAINT tomb_verify_sha256 CHECK (
+                    ENCODE(SHA256(CONCAT('tomb_id=', tomb_id::VARCHAR, ' reason=', reason,
+                        ' write_timestamp=', TO_CHAR(write_timestamp, 'YYYY.MM.DD HH24:MI:SS'), ' prior_sha256=', prior_sha256)::BYTEA), 'hex') = new_sha256) NOT VALID").await.unwrap();
+            x.assert_constraint_parity(&xrows::Tombstone{tomb_id: 1, target_table: "titles_immut".to_string(), 
+                target_id: 3, reason: "A takedown".to_string()}, &"ab".repeat(32), integrity::now()).await;
+        });
+    }
+
+    #[test]
+    fn test_append_presigned() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let tail = x.prepare_append("authors").await.unwrap();
+            let author = xrows::Author{auth_id: tail.next_id(), name: "Presigned author".to_string()};
+            let written = integrity::now();
+            // a wrong hash is rejected and nothing is written 
+            let wrong = integrity::sha256("not the hash of this author");
+            let rejected = x.append_presigned("authors", xrows::Author{auth_id: tail.next_id(), name: author.name.clone()}, &tail.prior_sha256, written, &wrong).await;
+            assert!(matches!(rejected, Err(XtchdError::Integrity(integrity::IntegrityError::HashMismatch{..}))));
+            assert_eq!(x.prepare_append("authors").await.unwrap(), tail);
+            // so is a write_timestamp outside the window, even with a matching hash, including one backdated by less than 
+            // PRESIGNED_WINDOW_SECS that Postgres would reject (see the *_no_rewrite_later constraints)
+            for skew in [-60, -3, 60] {
+                let postdated = written + chrono::Duration::seconds(skew);
+                let hash = HashChainLink::from_timestamp(&tail.prior_sha256, postdated, &author).new_sha256();
+                let rejected = x.append_presigned("authors", xrows::Author{auth_id: tail.next_id(), name: author.name.clone()}, &tail.prior_sha256, postdated, &hash).await;
+                assert!(matches!(rejected, Err(XtchdError::InvalidArgument(_))));
+            }
+            // the hash computed client-side is accepted 
+            let signed = HashChainLink::from_timestamp(&tail.prior_sha256, written, &author).new_sha256();
+            let (author, hcl) = x.append_presigned("authors", author, &tail.prior_sha256, written, &signed).await.unwrap();
+            assert_eq!(hcl.new_sha256(), signed);
+            assert!(x.verify_row("authors", author.auth_id).await.unwrap().is_valid());
+            // the old tail is now stale 
+            let stale = x.append_presigned("authors", xrows::Author{auth_id: tail.next_id(), name: "Stale".to_string()}, &tail.prior_sha256, written, &signed).await;
+            assert!(matches!(stale, Err(XtchdError::Integrity(integrity::IntegrityError::BrokenLink{..}))));
+        });
+    }
+
+    #[test]
+    fn test_reserve_next() {
+        // a second reservation waits until the first is written, then follows on from it 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let mut second = ts.connect().await;
+            let first = ts.x.reserve_next("authors").await.unwrap();
+            assert_eq!(first.last_row.prior_id, Some(0));
+            let pending = tokio::spawn(async move {
+                let reservation = second.reserve_next("authors").await.unwrap();
+                reservation.last_row.clone()
+            });
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            assert!(!pending.is_finished());
+            let author = xrows::Author{auth_id: first.last_row.next_id(), name: "Reserve test".to_string()};
+            let hcl = HashChainLink::new(&first.last_row.prior_sha256, &author);
+            let (author, _) = first.append_presigned(author, hcl.write_timestamp, &hcl.new_sha256()).await.unwrap();
+            let after = pending.await.unwrap();
+            assert_eq!(after, LastRow{prior_id: Some(author.auth_id), prior_sha256: hcl.new_sha256()});
+            ts.x.verify_chain("authors").await.unwrap();
+            // a mismatched hash is rejected and the reservation rolled back 
+            let rejected = ts.x.reserve_next("authors").await.unwrap()
+                .append_presigned(xrows::Author{auth_id: 0, name: "Reserve rejected".to_string()}, Utc::now(), &"0".repeat(64)).await;
+            assert!(matches!(rejected, Err(XtchdError::Integrity(_))));
+            assert!(ts.x.find_author_by_name("Reserve rejected").await.unwrap().is_none());
+            let backdated = Utc::now() - chrono::Duration::seconds(60);
+            let author = xrows::Author{auth_id: 0, name: "Reserve backdated".to_string()};
+            let reservation = ts.x.reserve_next("authors").await.unwrap();
+            let hash = HashChainLink::from_timestamp(&reservation.last_row.prior_sha256, backdated, &author).new_sha256();
+            assert!(matches!(reservation.append_presigned(author, backdated, &hash).await, Err(XtchdError::InvalidArgument(_))));
+            assert!(matches!(ts.x.reserve_next("nope").await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_write_waits_for_reservation() {
+        // every write takes the table's advisory lock, so an add_* made while a row is reserved follows on from the reserved row 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let mut writer = ts.connect().await;
+            let reservation = ts.x.reserve_next("authors").await.unwrap();
+            let pending = tokio::spawn(async move {
+                writer.add_author("Waiting author").await.unwrap().0
+            });
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            assert!(!pending.is_finished());
+            let author = xrows::Author{auth_id: reservation.last_row.next_id(), name: "Reserved author".to_string()};
+            let hcl = HashChainLink::new(&reservation.last_row.prior_sha256, &author);
+            let (reserved, _) = reservation.append_presigned(author, hcl.write_timestamp, &hcl.new_sha256()).await.unwrap();
+            let waited = pending.await.unwrap();
+            assert_eq!(waited.auth_id, reserved.auth_id + 1);
+            ts.x.verify_chain("authors").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_chain_contended() {
+        // a writer not holding the table's lock (i.e. not xtchd) taking the same position first is reported as retryable, not as a Conflict 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let mut interloper = ts.connect().await;
+            let tx = interloper.c.transaction().await.unwrap();
+            let last_row = last_row_in(&tx, chain_table("authors").unwrap()).await.unwrap();
+            let author = xrows::Author{auth_id: last_row.next_id(), name: "Interloping author".to_string()};
+            insert_row(&tx, "authors", &author, &last_row, &HashChainLink::new(&last_row.prior_sha256, &author)).await.unwrap();
+            let (contended, committed) = tokio::join!(ts.x.add_author("Contended author"), async {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                tx.commit().await
+            });
+            committed.unwrap();
+            assert!(matches!(contended, Err(XtchdError::ChainContended{ref constraint}) if is_chain_position(constraint)));
+            let (retried, _) = ts.x.add_author("Contended author").await.unwrap();
+            assert_eq!(retried.auth_id, author.auth_id + 1);
+            assert!(is_chain_position("authors_prior_id_key"));
+            assert!(!is_chain_position("authors_name_key"));
+            assert!(!is_chain_position("write_idempotency_pkey"));
+        });
+    }
+
+    #[test]
+    fn test_ensure_genesis() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            x.c.execute("DELETE FROM authors", &[]).await.unwrap();
+            assert!(x.author_detail(0, VerifyMode::Trust).await.is_err());
+            x.ensure_genesis().await.unwrap();
+            x.ensure_genesis().await.unwrap(); // idempotent
+            let au = x.author_detail(0, VerifyMode::Recompute).await.unwrap();
+            assert_eq!(au.author.content.name, xrows::GENESIS_AUTHOR);
+            assert_eq!(au.author.prior_id, None);
+            x.add_author("After genesis").await.unwrap();
+            x.verify_chain("authors").await.unwrap();
+        });
+    }
+
+    #[test]
+    fn test_search_paragraphs_language() {
+        // stop words differ between languages: 'los' is only a stop word in Spanish, 'the' only in English 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(1).as_str()), "Search language test", None).await.unwrap();
+            x.add_article_para(art.a_id_immut, "Los gatos duermen").await.unwrap();
+            x.add_article_para(art.a_id_immut, "The cats sleep").await.unwrap();
+            let hits = |results: Vec<views::ParaSearchResult>| results.len();
+            assert_eq!(hits(x.search_paragraphs("los", xrows::TsConfig::English).await.unwrap()), 1);
+            assert_eq!(hits(x.search_paragraphs("los", xrows::TsConfig::Spanish).await.unwrap()), 0);
+            assert_eq!(hits(x.search_paragraphs("the", xrows::TsConfig::English).await.unwrap()), 0);
+            assert_eq!(hits(x.search_paragraphs("the", xrows::TsConfig::Spanish).await.unwrap()), 1);
+            assert_eq!(hits(x.search_paragraphs("gatos", xrows::TsConfig::default()).await.unwrap()), 1);
+            // the paragraph mentioning cats most is ranked first, in either language 
+            let (most, _) = x.add_article_para(art.a_id_immut, "Los gatos y mas gatos y otros gatos").await.unwrap();
+            let cats = x.search_paragraphs("gatos", xrows::TsConfig::Spanish).await.unwrap();
+            assert_eq!((cats.len(), cats[0].apara_id), (2, most.apara_id));
+            let (most, _) = x.add_article_para(art.a_id_immut, "Cats, more cats and other cats").await.unwrap();
+            let cats = x.search_paragraphs("cats", xrows::TsConfig::English).await.unwrap();
+            assert_eq!((cats.len(), cats[0].apara_id), (2, most.apara_id));
+            // every configuration but English (which has the ts column) has an expression index 
+            let indexed: i64 = x.c.query_one("SELECT COUNT(*) FROM pg_indexes WHERE tablename = 'article_para' AND indexname LIKE 'article_fulltext_%'", &[])
+                .await.unwrap().get(0);
+            assert_eq!(indexed, 8);
+        });
+    }
+
+    #[test]
+    fn test_search_paragraphs_page() {
+        // consecutive pages neither overlap nor skip a result, including paragraphs tied on rank 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, Some(draft_id(1).as_str()), "Search paging test", None).await.unwrap();
+            for md in ["Walrus", "Walrus walrus", "Walrus walrus walrus", "A walrus", "A walrus", "No match here"] {
+                x.add_article_para(art.a_id_immut, md).await.unwrap();
+            }
+            let all = x.search_paragraphs_page("walrus", None, 10).await.unwrap();
+            assert_eq!(all.results.len(), 5);
+            assert!(all.next.is_none());
+            let first = x.search_paragraphs_page("walrus", None, 2).await.unwrap();
+            let second = x.search_paragraphs_page("walrus", first.next, 2).await.unwrap();
+            let third = x.search_paragraphs_page("walrus", second.next, 2).await.unwrap();
+            assert_eq!(third.results.len(), 1);
+            assert!(third.next.is_none());
+            let paged: Vec<i32> = [first, second, third].iter().flat_map(|page| page.results.iter().map(|result| result.apara_id)).collect();
+            let unpaged: Vec<i32> = all.results.iter().map(|result| result.apara_id).collect();
+            assert_eq!(paged, unpaged);
+        });
+    }
+
+    #[test]
+    fn test_authors_by_ids() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("Batch lookup test").await.unwrap();
+            let authors = x.authors_by_ids(&[0, author.auth_id, 9999]).await.unwrap();
+            assert_eq!(authors.len(), 2);
+            assert_eq!(authors[&author.auth_id].name, "Batch lookup test");
+            assert!(!authors.contains_key(&9999));
+            assert!(x.authors_by_ids(&[]).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_dedupe() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            assert_eq!(x.find_author_by_name("Dedupe test").await.unwrap(), None);
+            let (author, hcl) = x.add_author_dedupe("Dedupe test").await.unwrap();
+            assert_eq!(x.find_author_by_name("Dedupe test").await.unwrap(), Some(author.auth_id));
+            // a dedupe hit returns the existing author rather than appending (or failing the UNIQUE constraint)
+            let (again, again_hcl) = x.add_author_dedupe("Dedupe test").await.unwrap();
+            assert_eq!(again.auth_id, author.auth_id);
+            assert_eq!(again_hcl.new_sha256(), hcl.new_sha256());
+            assert!(x.add_author("Dedupe test").await.is_err());
+            // titles are only deduped per author
+            assert_eq!(x.find_article_by_title(author.auth_id, "Dedupe title").await.unwrap(), None);
+            let (art, art_hcl) = x.add_article_title_dedupe(author.auth_id, None, "Dedupe title").await.unwrap();
+            assert_eq!(x.find_article_by_title(author.auth_id, "Dedupe title").await.unwrap(), Some(art.a_id_immut));
+            let (hit, hit_hcl) = x.add_article_title_dedupe(author.auth_id, None, "Dedupe title").await.unwrap();
+            assert_eq!(hit.a_id_immut, art.a_id_immut);
+            assert_eq!(hit_hcl.new_sha256(), art_hcl.new_sha256());
+            let (other, _) = x.add_article_title_dedupe(0, None, "Dedupe title").await.unwrap();
+            assert_ne!(other.a_id_immut, art.a_id_immut);
+            let (duplicate, _) = x.add_article_title(author.auth_id, None, "Dedupe title", None).await.unwrap();
+            assert_eq!(duplicate.a_id_immut, other.a_id_immut + 1);
+        });
+    }
+
+    #[test]
+    fn test_revise_article_page() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, None, "Revision test", None).await.unwrap();
+            let src = || xrows::PageSrc::Author(String::new());
+            let (first, _) = x.add_article_page(art.a_id_immut, None, vec!["Teh typo".to_string()], src()).await.unwrap();
+            x.add_article_page(art.a_id_immut, None, vec!["Second page".to_string()], src()).await.unwrap();
+            let (revision, _) = x.revise_article_page(art.a_id_immut, first.p_id_immut, vec!["The typo".to_string()], src()).await.unwrap();
+            assert_eq!(revision.supersedes, Some(first.p_id_immut));
+            assert_eq!(revision.p_id_draft, first.p_id_draft);
+            // the revision replaces the first page in place 
+            let detail = x.article_detail(art.a_id_immut, VerifyMode::Recompute).await.unwrap();
+            let texts: Vec<&str> = detail.pages.iter().map(|page| page.page.content.paragraphs[0].as_str()).collect();
+            assert_eq!(texts, vec!["The typo", "Second page"]);
+            let range = x.article_pages_range(art.a_id_immut, 0, 1).await.unwrap();
+            assert_eq!(range[0].content.p_id_immut, revision.p_id_immut);
+            // the superseded page still exists and verifies, as does the whole chain 
+            assert!(x.verify_row("pages_immut", first.p_id_immut).await.unwrap().is_valid());
+            assert!(x.verify_row("pages_immut", revision.p_id_immut).await.unwrap().is_valid());
+            x.verify_chain("pages_immut").await.unwrap();
+            // only the latest revision can be revised 
+            let stale = x.revise_article_page(art.a_id_immut, first.p_id_immut, vec!["Again".to_string()], src()).await;
+            assert!(matches!(stale, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_verify_chain_batched() {
+        // the batched path agrees with the simple path, including on the first error in a tampered chain 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for i in 0..20 {
+                x.add_author(&format!("Batched verify test {}", i)).await.unwrap();
+            }
+            x.verify_chain("authors").await.unwrap();
+            for batch_size in [1, 7, 21, 1000] {
+                x.verify_chain_batched("authors", batch_size).await.unwrap();
+            }
+            assert!(matches!(x.verify_chain_batched("authors", 0).await, Err(XtchdError::InvalidArgument(_))));
+            x.c.batch_execute("ALTER TABLE authors DROP CONSTRAINT auth_verify_sha256;
+                ALTER TABLE authors DROP CONSTRAINT auth_no_rewrite_later;
+                UPDATE authors SET name = 'Tampered 9' WHERE auth_id = 9;
+                UPDATE authors SET name = 'Tampered 15' WHERE auth_id = 15;").await.unwrap();
+            let simple = x.verify_chain("authors").await.unwrap_err().to_string();
+            assert!(simple.contains("Author"));
+            // reads which would send the rows to a client refuse the tampered rows 
+            assert!(x.export_chain::<xrows::Author>("authors").await.is_err());
+            for batch_size in [1, 7, 21, 1000] {
+                let batched = x.verify_chain_batched("authors", batch_size).await.unwrap_err().to_string();
+                assert_eq!(batched, simple);
+            }
+        });
+    }
+
+    #[test]
+    fn test_article_document() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("Document test").await.unwrap();
+            let (art, _) = x.add_article_title(author.auth_id, None, "Document test article", None).await.unwrap();
+            for i in 0..2 {
+                x.add_article_page(art.a_id_immut, None, vec![format!("Document page {}", i)], xrows::PageSrc::Author(String::new())).await.unwrap();
+            }
+            let doc = x.article_document(art.a_id_immut).await.unwrap();
+            assert_eq!(doc.author.content.name, "Document test");
+            assert_eq!(doc.pages.len(), 2);
+            let json = serde_json::to_string(&doc).unwrap();
+            let round_trip: views::ArticleDocument = serde_json::from_str(&json).unwrap();
+            integrity::verify_article_document(&round_trip).unwrap();
+            // tampering with a page is caught by the page's own hash 
+            let mut tampered: views::ArticleDocument = serde_json::from_str(&json).unwrap();
+            tampered.pages[1].content.paragraphs = vec!["Tampered".to_string()];
+            assert!(matches!(integrity::verify_article_document(&tampered), Err(integrity::IntegrityError::HashMismatch{..})));
+            // and a page rehashed to hide the tampering is caught by the aggregate 
+            let page = tampered.pages.pop().unwrap();
+            let hcl = HashChainLink::from_timestamp(&page.prior_sha256, page.hcl.write_timestamp, &page.content);
+            tampered.pages.push(XtchdContent::new(page.prior_id, page.prior_sha256, page.hcl.write_timestamp, page.content, hcl.new_sha256()));
+            assert!(matches!(integrity::verify_article_document(&tampered), Err(integrity::IntegrityError::DocumentMismatch{..})));
+            // valid rows spliced in from another document are caught even with the aggregate recomputed 
+            let (other_author, _) = x.add_author("Document splice").await.unwrap();
+            let (other_art, _) = x.add_article_title(other_author.auth_id, None, "Document splice article", None).await.unwrap();
+            x.add_article_page(other_art.a_id_immut, None, vec!["Spliced page".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let other = x.article_document(other_art.a_id_immut).await.unwrap();
+            let mut spliced: views::ArticleDocument = serde_json::from_str(&json).unwrap();
+            spliced.pages = other.pages;
+            spliced.doc_sha256 = spliced.compute_sha256();
+            assert!(matches!(integrity::verify_article_document(&spliced), Err(integrity::IntegrityError::ForeignPart{..})));
+            let mut spliced: views::ArticleDocument = serde_json::from_str(&json).unwrap();
+            spliced.author = other.author;
+            spliced.doc_sha256 = spliced.compute_sha256();
+            assert!(matches!(integrity::verify_article_document(&spliced), Err(integrity::IntegrityError::ForeignPart{..})));
+            x.tombstone("titles_immut", art.a_id_immut, "Document test redaction").await.unwrap();
+            assert!(matches!(x.article_document(art.a_id_immut).await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_page_limits() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            ts.x.page_limits = xrows::PageLimits{max_paragraphs: 2, max_paragraph_chars: 10};
+            let (art, _) = ts.x.add_article_title(0, None, "Page limits test", None).await.unwrap();
+            let src = || xrows::PageSrc::Author(String::new());
+            let (page, _) = ts.x.add_article_page(art.a_id_immut, None, vec!["a".repeat(10), "b".to_string()], src()).await.unwrap();
+            let too_many = ts.x.add_article_page(art.a_id_immut, None, vec!["a".to_string(); 3], src()).await;
+            assert!(matches!(too_many, Err(XtchdError::Xrow(xrows::XrowError::PageTooLarge{what: "paragraphs", size: 3, limit: 2}))));
+            // revisions are limited too 
+            let too_long = ts.x.revise_article_page(art.a_id_immut, page.p_id_immut, vec!["a".repeat(11)], src()).await;
+            assert!(matches!(too_long, Err(XtchdError::Xrow(xrows::XrowError::PageTooLarge{size: 11, limit: 10, ..}))));
+            assert_eq!(ts.x.article_detail(art.a_id_immut, VerifyMode::Trust).await.unwrap().pages.len(), 1);
+        });
+    }
+
+    #[test]
+    fn test_article_document_consistent() {
+        // a page written by another connection during a snapshot is not read until the snapshot ends 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let mut writer = ts.connect().await;
+            let (art, _) = ts.x.add_article_title(0, None, "Snapshot test article", None).await.unwrap();
+            ts.x.add_article_page(art.a_id_immut, None, vec!["Snapshot page 0".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let before = ts.x.article_document_consistent(art.a_id_immut).await.unwrap();
+            assert_eq!(before.pages.len(), 1);
+
+            let tx = ts.x.snapshot().await.unwrap();
+            let title = article_detail_in(&tx, art.a_id_immut, VerifyMode::Trust).await.unwrap().title;
+            writer.add_article_page(art.a_id_immut, None, vec!["Snapshot page 1".to_string()], xrows::PageSrc::Author(String::new())).await.unwrap();
+            let during = article_document_in(&tx, art.a_id_immut).await;
+            let during = end_snapshot(tx, during).await.unwrap();
+            assert_eq!(during.title.new_sha256, title.new_sha256);
+            assert_eq!(during.pages.len(), 1);
+            assert_eq!(during.doc_sha256, before.doc_sha256);
+
+            let after = ts.x.article_detail_consistent(art.a_id_immut, VerifyMode::Recompute).await.unwrap();
+            assert_eq!(after.pages.len(), 2);
+            // a failed read ends its snapshot too, returning the read's error and leaving the connection usable
+            assert!(matches!(ts.x.article_document_consistent(-1).await, Err(XtchdError::NotFound(_))));
+            assert!(ts.x.c.query_one("SELECT 1", &[]).await.is_ok());
+            assert!(ts.x.add_author("After snapshot").await.is_ok());
+        });
+    }
+
+    #[test]
+    fn test_detail() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (author, _) = x.add_author("Detail test").await.unwrap();
+            let (art, _) = x.add_article_title(author.auth_id, None, "Detail test article", None).await.unwrap();
+            let au = x.detail("author", author.auth_id).await.unwrap();
+            assert_eq!(au["author"]["content"]["name"], "Detail test");
+            assert_eq!(au, x.detail("Author", author.auth_id).await.unwrap());
+            let article = x.detail("ArticleTitle", art.a_id_immut).await.unwrap();
+            assert_eq!(article["title"]["content"]["title"], "Detail test article");
+            let (chan, _) = x.add_youtube_channel("c/DetailTest", "Detail test channel").await.unwrap();
+            let uploaded = NaiveDate::from_ymd_opt(2023, 1, 2).unwrap();
+            let (vid, _) = x.add_youtube_video(chan.chan_id, "dQw4w9WgXcQ", "Detail test video", &uploaded).await.unwrap();
+            let img_id = x.add_image_immutable(xrows::ImagePair{src_full: "detail_full".to_string(),
+                src_thmb: "thmb".to_string(), alt: "Detail test image".to_string(), url: None, archive: None}).await.unwrap();
+            assert_eq!(x.detail("YoutubeChannel", chan.chan_id).await.unwrap()["content"]["name"], "Detail test channel");
+            assert_eq!(x.detail("YoutubeVideo", vid.vid_id).await.unwrap()["content"]["title"], "Detail test video");
+            assert_eq!(x.detail("Image", img_id).await.unwrap()["content"]["pair"]["alt"], "Detail test image");
+            assert!(matches!(x.detail("Image", img_id + 1).await, Err(XtchdError::NotFound(_))));
+            assert!(matches!(x.detail("Spaceship", 0).await, Err(XtchdError::InvalidArgument(_))));
+        });
+    }
+
+    #[test]
+    fn test_export_paragraphs_ndjson() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, None, "NDJSON test", None).await.unwrap();
+            for md in ["First paragraph", "Second with a\nnewline", "## Third"] {
+                x.add_article_para(art.a_id_immut, md).await.unwrap();
+            }
+            let mut buf: Vec<u8> = Vec::new();
+            let written = x.export_paragraphs_ndjson(&mut buf).await.unwrap();
+            assert_eq!(written, 3);
+            let lines: Vec<&str> = std::str::from_utf8(&buf).unwrap().lines().collect();
+            assert_eq!(lines.len(), 3);
+            let paras: Vec<xrows::ArticlePara> = lines.iter().map(|line| serde_json::from_str(line).unwrap()).collect();
+            assert_eq!(paras[1].md, "Second with a\nnewline");
+            assert!(paras.windows(2).all(|pair| pair[0].apara_id < pair[1].apara_id));
+        });
+    }
+
+    #[test]
+    fn test_article_detail_source_image() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (art, _) = x.add_article_title(0, None, "Source image test", None).await.unwrap();
+            let pair = xrows::ImagePair{src_full: "full".to_string(), src_thmb: "thumbnail".to_string(), alt: "Tweet".to_string(), url: None, archive: None};
+            let img_id = x.add_image_immutable(pair).await.unwrap();
+            x.add_article_page(art.a_id_immut, None, vec!["Screenshot".to_string()], xrows::PageSrc::WpTxYt(img_id)).await.unwrap();
+            x.add_article_page(art.a_id_immut, None, vec!["Opinion".to_string()], xrows::PageSrc::Author("page.png".to_string())).await.unwrap();
+            let detail = x.article_detail(art.a_id_immut, VerifyMode::Recompute).await.unwrap();
+            let thumb = detail.pages[0].source_image.as_ref().unwrap();
+            assert_eq!((thumb.img_id, thumb.src_thmb.as_str()), (img_id, "thumbnail"));
+            assert!(detail.pages[1].source_image.is_none());
+        });
+    }
+
+    #[test]
+    fn test_image_searchable() {
+        // an image without an archive key is found by both autocomplete and full-text search 
+        use pachydurable::{autocomplete::AutoComp, fulltext::FullText};
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let pair = xrows::ImagePair{src_full: "full".to_string(), src_thmb: "thmb".to_string(), alt: "Lighthouse at dusk".to_string(), url: None, archive: None};
+            let img_id = x.add_image_immutable(pair).await.unwrap();
+            assert!(x.audit_image_tables().await.unwrap().is_empty());
+            let rows = x.c.query(xrows::ImmutableImage::query_autocomp(), &[&"lighthouse", &"lighthouse"]).await.unwrap();
+            let found: Vec<i32> = rows.iter().map(|row| xrows::ImmutableImage::rowfunc_autocomp(row).pk.img_id).collect();
+            assert_eq!(found, vec![img_id]);
+            let rows = x.c.query(xrows::Thumbnail::query_fulltext(), &[&"lighthouses"]).await.unwrap();
+            let found: Vec<i32> = rows.iter().map(|row| xrows::Thumbnail::rowfunc_fulltext(row).img_id).collect();
+            assert_eq!(found, vec![img_id]);
+        });
+    }
+
+    #[test]
+    fn test_storage_sizes() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let pair = xrows::ImagePair{src_full: "f".repeat(10_000), src_thmb: "thmb".to_string(), alt: "Storage test".to_string(), url: None, archive: None};
+            x.add_image_immutable(pair).await.unwrap();
+            let sizes = x.storage_sizes().await.unwrap();
+            assert_eq!(sizes.len(), CHAIN_TABLES.len() + 1);
+            assert_eq!(sizes[0].0, "authors");
+            let images = sizes.iter().find(|(name, _)| name == "images_immut").unwrap();
+            assert!(images.1 > 0);
+        });
+    }
+
+    #[test]
+    fn test_all_authors() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            for name in ["Roster C", "Roster A", "Roster B"] {
+                x.add_author(name).await.unwrap();
+            }
+            let roster = x.all_authors().await.unwrap();
+            let ids: Vec<i32> = roster.iter().map(|author| author.id).collect();
+            assert_eq!(ids, vec![0, 1, 2, 3]);
+            let names: Vec<&str> = roster.iter().skip(1).map(|author| author.name.as_str()).collect();
+            assert_eq!(names, vec!["Roster C", "Roster A", "Roster B"]);
+            let (first, _) = x.add_article_title(1, None, "Roster article 1", None).await.unwrap();
+            let (second, _) = x.add_article_title(2, None, "Roster article 2", None).await.unwrap();
+            assert_eq!(x.all_article_ids().await.unwrap(), vec![first.a_id_immut, second.a_id_immut]);
+        });
+    }
+
+    #[test]
+    fn test_autocomplete_authors_fuzzy() {
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (einstein, _) = x.add_author("Albert Einstein").await.unwrap();
+            x.add_author("Marie Curie").await.unwrap();
+            // the prefix path finds the author without the fallback 
+            let exact = x.autocomplete_authors("Einst", 1, xrows::AutoCompOrder::Length, xrows::TsConfig::Simple).await.unwrap();
+            assert_eq!(exact.iter().map(|www| www.pk).collect::<Vec<i32>>(), vec![einstein.auth_id]);
+            // a misspelling only matches by trigram similarity 
+            let misspelled = x.autocomplete_authors("Einstien", 5, xrows::AutoCompOrder::Length, xrows::TsConfig::Simple).await.unwrap();
+            assert_eq!(misspelled.first().map(|www| www.pk), Some(einstein.auth_id));
+            assert!(misspelled.iter().all(|www| www.name != "Marie Curie"));
+            assert!(x.autocomplete_authors("Einstien", 0, xrows::AutoCompOrder::Length, xrows::TsConfig::Simple).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_autocomplete_authors_language() {
+        // 'hermanos' and 'hermanas' only share a stem in Spanish 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let x = &mut ts.x;
+            let (sisters, _) = x.add_author("Las Hermanas Gomez").await.unwrap();
+            let spanish = x.autocomplete_authors("hermanos", 0, xrows::AutoCompOrder::Length, xrows::TsConfig::Spanish).await.unwrap();
+            assert_eq!(spanish.iter().map(|www| www.pk).collect::<Vec<i32>>(), vec![sisters.auth_id]);
+            assert!(x.autocomplete_authors("hermanos", 0, xrows::AutoCompOrder::Length, xrows::TsConfig::Simple).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn test_autocomplete_authors_popularity() {
+        // a longer name ranks first once its articles are cited, but only when ordering by popularity 
+        let rt = Runtime::new().unwrap();
+        rt.block_on(async {
+            let mut ts = TestSchema::new().await;
+            let (short, _) = ts.x.add_author("Popular Al").await.unwrap();
+            let (long, _) = ts.x.add_author("Popular Alexandria Longname").await.unwrap();
+            let (cited, _) = ts.x.add_article_title(long.auth_id, None, "Popularity test cited", None).await.unwrap();
+            let (citing, _) = ts.x.add_article_title(short.auth_id, None, "Popularity test citing", None).await.unwrap();
+            ts.x.add_article_page(citing.a_id_immut, None, vec!["As cited".to_string()], xrows::PageSrc::Xtchd(cited.a_id_immut)).await.unwrap();
+            let order = |results: Vec<WhoWhatWhere<i32>>| results.iter().map(|www| www.pk).collect::<Vec<i32>>();
+            let by_length = ts.x.autocomplete_authors("Popular Al", 1, xrows::AutoCompOrder::Length, xrows::TsConfig::Simple).await.unwrap();
+            assert_eq!(order(by_length), vec![short.auth_id, long.auth_id]);
+            // with no popularity computed yet, popularity ordering falls back to length 
+            let unrefreshed = ts.x.autocomplete_authors("Popular Al", 1, xrows::AutoCompOrder::Popularity, xrows::TsConfig::Simple).await.unwrap();
+            assert_eq!(order(unrefreshed), vec![short.auth_id, long.auth_id]);
+            assert_eq!(ts.x.refresh_author_popularity().await.unwrap(), 1);
+            let by_popularity = ts.x.autocomplete_authors("Popular Al", 1, xrows::AutoCompOrder::Popularity, xrows::TsConfig::Simple).await.unwrap();
+            assert_eq!(order(by_popularity), vec![long.auth_id, short.auth_id]);
         });
     }
 
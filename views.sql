@@ -1,13 +1,39 @@
+CREATE OR REPLACE FUNCTION redacted(target VARCHAR, id INTEGER) RETURNS BOOLEAN AS $$
+	/*Whether the row of the target table with this id has been redacted with a tombstone (see Xtchr::tombstone).
+	Every read of content filters on NOT redacted(...), so a redacted row is absent from lists, searches and exports.
+	This is here rather than in public.sql as the tombstones table must exist before the function is created */
+	SELECT EXISTS (SELECT 1 FROM tombstones WHERE target_table = target AND target_id = id)
+$$ LANGUAGE SQL STABLE;
+
+
+
+
+CREATE VIEW author_current AS (
+    /* each author's current name: that of their latest rename in author_names (see Xtchr::rename_author),
+    or their name in authors if they have never been renamed. A redacted rename is skipped, reverting to the name before it.
+    Every read of an author's name goes through this view, and names are unique across it */
+    SELECT au.auth_id, COALESCE(latest.name, au.name) AS name, COALESCE(latest.ac, au.ac) AS ac
+    FROM authors au
+    LEFT JOIN LATERAL (
+        SELECT n.name, n.ac FROM author_names n
+        WHERE n.auth_id = au.auth_id AND NOT redacted('author_names', n.name_id)
+        ORDER BY n.name_id DESC LIMIT 1
+    ) latest ON TRUE
+);
+
 
 
 CREATE VIEW author_detail AS (
     -- this view yields the view.rs::AuthorDetail struct
     WITH authorship AS (
-        SELECT auth_id, ARRAY_AGG(JSON_BUILD_OBJECT('id', art_id, 'name', title)) AS authored
-        FROM articles GROUP BY auth_id
-    ) SELECT au.prior_id, au.auth_id, au.name, au.prior_sha256, au.write_timestamp, au.new_sha256, authored
-    FROM authorship
-    INNER JOIN authors au ON authorship.auth_id = au.auth_id
+        SELECT auth_id, ARRAY_AGG(JSON_BUILD_OBJECT('id', a_id_immut, 'name', title)) AS authored
+        FROM titles_immut WHERE NOT redacted('titles_immut', a_id_immut) GROUP BY auth_id
+    ) SELECT au.prior_id, au.auth_id, au.name, au.prior_sha256, au.write_timestamp, au.new_sha256, 
+        COALESCE(authored, '{}') AS authored, -- authors with no articles yet still have a detail 
+        cur.name AS current_name
+    FROM authors au
+    INNER JOIN author_current cur ON cur.auth_id = au.auth_id
+    LEFT JOIN authorship ON authorship.auth_id = au.auth_id
 );
 
 